@@ -1,5 +1,7 @@
 pub mod cfg;
 
+pub mod cli;
+
 pub mod editor;
 
 pub mod event_handling;
@@ -13,3 +15,7 @@ pub mod lib;
 pub mod io;
 
 pub mod input;
+
+pub mod terminal;
+
+pub mod headless;
@@ -0,0 +1,137 @@
+//! # Command line
+//! The arguments the editor is started with. They follow vim where it has an equivalent:
+//! ```text
+//! neoxide [options] [file ...]
+//! ```
+//! Every file gets its own window, files that don't exist yet get an empty one.
+use super::editor::session;
+
+pub const USAGE: &str = "\
+usage: neoxide [options] [file ...]
+
+  +N              put the cursor on line N of the first window
+  +               put the cursor on the last line of the first window
+  +{command}      run {command} after startup, like -c
+  -               read the text from stdin into a scratch buffer
+  -R              readonly mode
+  -c {command}    run {command} after startup (up to 10 times)
+  -S [session]    restore a session saved with :mksession (default: Session.neo)
+  --clean         don't read or write the state file
+  --headless      run without a terminal, for scripting
+  --              only file names follow
+  -h, --help      show this help
+  --version       show the version
+";
+
+/// like vim, only a few commands are accepted
+const MAX_COMMANDS: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    pub files: Vec<String>,
+    /// line to put the cursor on in the first window, starting at 1. ``u32::MAX`` for the last one
+    pub line: Option<u32>,
+    /// ``-``: the first window shows the text from stdin
+    pub stdin: bool,
+    pub readonly: bool,
+    pub clean: bool,
+    /// ex commands (without ``:``), run in order after startup
+    pub commands: Vec<String>,
+    pub headless: bool,
+    pub session: Option<String>,
+    pub help: bool,
+    pub version: bool,
+}
+
+/// parses the arguments, without the name of the program
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut ret = Args::default();
+    let mut args = args.into_iter().peekable();
+    let mut only_files = false;
+    while let Some(arg) = args.next() {
+        if only_files {
+            ret.files.push(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "--" => only_files = true,
+            "-" => ret.stdin = true,
+            "-R" => ret.readonly = true,
+            "--clean" => ret.clean = true,
+            "--headless" => ret.headless = true,
+            "-h" | "--help" => ret.help = true,
+            "--version" => ret.version = true,
+            "-c" => {
+                let command = args.next().ok_or("-c needs a command")?;
+                ret.commands.push(command);
+            }
+            "-S" => {
+                // the session file is optional, like in vim
+                let path = args.next_if(|next| !next.starts_with(['-', '+']));
+                ret.session = Some(path.unwrap_or(session::DEFAULT_PATH.to_string()));
+            }
+            "+" => ret.line = Some(u32::MAX),
+            _ if arg.starts_with('+') => match arg[1..].parse::<u32>() {
+                Ok(line) => ret.line = Some(line),
+                Err(_) => ret.commands.push(arg[1..].to_string()),
+            },
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {arg}")),
+            _ => ret.files.push(arg),
+        }
+    }
+    if ret.commands.len() > MAX_COMMANDS {
+        return Err(format!(
+            "too many commands, at most {MAX_COMMANDS} are allowed"
+        ));
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn files_and_options() {
+        assert_eq!(
+            args(&["-R", "a.rs", "+12", "b.rs", "-c", "vsplit", "+set nu", "-"]).unwrap(),
+            Args {
+                files: vec!["a.rs".to_string(), "b.rs".to_string()],
+                line: Some(12),
+                stdin: true,
+                readonly: true,
+                commands: vec!["vsplit".to_string(), "set nu".to_string()],
+                ..Default::default()
+            }
+        );
+        assert_eq!(args(&["+"]).unwrap().line, Some(u32::MAX));
+        assert_eq!(args(&["--", "-R", "+3"]).unwrap().files, vec!["-R", "+3"]);
+    }
+
+    #[test]
+    fn optional_session_file() {
+        assert_eq!(
+            args(&["-S"]).unwrap().session.as_deref(),
+            Some("Session.neo")
+        );
+        assert_eq!(
+            args(&["-S", "-R"]).unwrap().session.as_deref(),
+            Some("Session.neo")
+        );
+        assert_eq!(
+            args(&["-S", "work.neo"]).unwrap().session.as_deref(),
+            Some("work.neo")
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(args(&["-x"]), Err("unknown option: -x".to_string()));
+        assert!(args(&["-c"]).is_err());
+        assert!(args(&["-c", "split"].repeat(11)).is_err());
+    }
+}
@@ -1,22 +1,24 @@
+pub mod autoread;
+pub mod commands;
+pub mod document;
+pub mod events;
+pub mod fileformat;
+pub mod history;
+pub mod keymap;
+pub mod marks;
+pub mod mode;
 pub mod motions;
+pub mod mouse;
+pub mod paste;
+pub mod prompt;
+pub mod registers;
+pub mod session;
+pub mod shada;
+pub mod text_objects;
+pub mod visual;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct CursorPosition {
     pub x: u32,
     pub y: u32,
 }
-
-#[derive(Clone)]
-pub struct Buffer {
-    pub content: Vec<String>,
-    pub cursor_position: CursorPosition,
-}
-
-impl Buffer {
-    pub async fn new(content: Vec<String>, cursor_position: CursorPosition) -> Buffer {
-        Buffer {
-            content,
-            cursor_position,
-        }
-    }
-}
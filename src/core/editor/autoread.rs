@@ -0,0 +1,198 @@
+//! # Autoread
+//! Notices files, that were changed on disk by another program, like vim's ``autoread`` and
+//! ``:checktime``. Documents without unsaved changes are simply loaded again, for all others the
+//! user is asked what to do. The files are checked whenever the terminal gets the focus and
+//! every few seconds by a watcher task.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crossterm::event::Event;
+use tokio::sync::Mutex;
+
+use super::document::{Document, DocumentRef};
+use super::events::{self, EditorEvent, EditorEventData};
+use super::prompt;
+use crate::core::event_handling::EventCallback;
+use crate::core::input::{self, InputEvent};
+use crate::core::io;
+use crate::core::logger::{self, LogLevel};
+use crate::core::render::manager;
+
+static AUTOREAD: AtomicBool = AtomicBool::new(true);
+
+/// how often the watcher looks at the files
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(4);
+
+pub fn enabled() -> bool {
+    AUTOREAD.load(Ordering::Relaxed)
+}
+pub fn set_enabled(enabled: bool) {
+    AUTOREAD.store(enabled, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Nothing,
+    Reload,
+    Ask,
+}
+
+/// what to do with a document, whose file was last seen with ``known`` and now has ``on_disk``
+fn decide(
+    known: Option<SystemTime>,
+    on_disk: Option<SystemTime>,
+    modified: bool,
+    autoread: bool,
+) -> Action {
+    match (known, on_disk) {
+        (Some(known), Some(on_disk)) if known != on_disk => {
+            if modified || !autoread {
+                Action::Ask
+            } else {
+                Action::Reload
+            }
+        }
+        // new documents and file systems without mtimes can't be checked
+        _ => Action::Nothing,
+    }
+}
+
+async fn reload(doc: &DocumentRef, path: &str) -> Result<(), String> {
+    io::reload(doc)
+        .await
+        .map_err(|err| format!("can't reload {path}: {err}"))?;
+    events::dispatch(
+        EditorEvent::FileReloaded,
+        EditorEventData {
+            path: Some(path.to_string()),
+        },
+    )
+    .await;
+    manager::rerender().await
+}
+
+async fn ask(doc: DocumentRef, path: String, on_disk: Option<SystemTime>) {
+    let question =
+        format!("W11: \"{path}\" has changed since editing started. [O]K, (L)oad File: ");
+    prompt::ask(
+        question,
+        "ol",
+        prompt::answer(move |choice| async move {
+            match choice {
+                'l' => reload(&doc, &path).await,
+                _ => {
+                    // keep the own version and don't ask again for this change
+                    doc.write().unwrap().set_disk_mtime(on_disk);
+                    manager::rerender().await
+                }
+            }
+        }),
+    )
+    .await;
+}
+
+/// checks all open files for changes on disk (``:checktime``)
+pub async fn checktime() -> Result<(), String> {
+    let mut asked = false;
+    for doc in Document::all() {
+        let (path, known, modified) = {
+            let doc = doc.read().unwrap();
+            match doc.path() {
+                Some(path) => (path.to_string(), doc.disk_mtime(), doc.is_modified()),
+                None => continue,
+            }
+        };
+        let on_disk = match io::mtime(&path).await {
+            Ok(mtime) => mtime,
+            // deleted files keep their content, they can still be written again
+            Err(_) => continue,
+        };
+        let action = decide(known, on_disk, modified, enabled());
+        if action == Action::Nothing {
+            continue;
+        }
+        // remember the change right away, so the watcher doesn't report it twice
+        doc.write().unwrap().set_disk_mtime(on_disk);
+        events::dispatch(
+            EditorEvent::FileChangedShell,
+            EditorEventData {
+                path: Some(path.clone()),
+            },
+        )
+        .await;
+        match action {
+            Action::Reload => reload(&doc, &path).await?,
+            _ => {
+                ask(doc, path, on_disk).await;
+                asked = true;
+            }
+        }
+    }
+    if asked {
+        manager::redraw_statusline().await?;
+    }
+    Ok(())
+}
+
+/// polls the open files every ``WATCH_INTERVAL``, so changes are noticed even while the editor
+/// keeps the focus
+pub fn watch() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(msg) = checktime().await {
+                logger::log(LogLevel::Error, msg.as_str()).await;
+            }
+        }
+    })
+}
+
+/// turns the focus events of the terminal into editor events and checks the files when the
+/// focus comes back
+pub async fn init() {
+    for event in [Event::FocusGained, Event::FocusLost] {
+        input::subscribe(EventCallback::new(
+            Arc::new(Box::new(|evt: Arc<Mutex<input::EvtData>>| {
+                Box::pin(async move {
+                    let gained = match evt.lock().await.0 {
+                        Event::FocusGained => true,
+                        Event::FocusLost => false,
+                        _ => return,
+                    };
+                    let event = if gained {
+                        EditorEvent::FocusGained
+                    } else {
+                        EditorEvent::FocusLost
+                    };
+                    events::dispatch(event, EditorEventData::default()).await;
+                    if gained {
+                        if let Err(msg) = checktime().await {
+                            logger::log(LogLevel::Error, msg.as_str()).await;
+                        }
+                    }
+                })
+            })),
+            true,
+            InputEvent(event),
+        ))
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decisions() {
+        let old = SystemTime::UNIX_EPOCH;
+        let new = old + Duration::from_secs(1);
+        assert_eq!(decide(Some(old), Some(old), true, true), Action::Nothing);
+        assert_eq!(decide(Some(old), Some(new), false, true), Action::Reload);
+        assert_eq!(decide(Some(old), Some(new), true, true), Action::Ask);
+        assert_eq!(decide(Some(old), Some(new), false, false), Action::Ask);
+        assert_eq!(decide(None, Some(new), false, true), Action::Nothing);
+    }
+}
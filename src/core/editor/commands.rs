@@ -0,0 +1,642 @@
+//! # Ex commands
+//! Commands typed on the command line, e.g. ``:vsplit`` or ``:3resize +2``. Like in vim, every
+//! command can be abbreviated down to a minimum length, which is given in the name pattern while
+//! registering (``"sp[lit]"`` accepts ``sp``, ``spl``, ... ``split``). Plugins add their own
+//! commands with ``register``.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use tokio::sync::RwLock;
+
+use super::autoread;
+use super::document::{Document, DocumentRef};
+use super::fileformat::{Encoding, FileFormat, LineEnding};
+use super::keymap;
+use super::registers::{self, ClipboardProvider};
+use super::session;
+use crate::core::input;
+use crate::core::io;
+use crate::core::render::gutter::{LineNumbers, SignColumn};
+use crate::core::render::manager::{self, MasterPosition, SplitDirection};
+
+pub type CommandFunctionType = Arc<
+    Box<
+        dyn Fn(CommandArgs) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>>
+            + Send
+            + Sync,
+    >,
+>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandArgs {
+    /// number in front of the command name (``:3split``)
+    pub count: Option<u32>,
+    /// ``!`` right after the command name
+    pub bang: bool,
+    /// the command was prefixed by ``:vertical``
+    pub vertical: bool,
+    pub args: Vec<String>,
+    /// everything after the command name, for commands that take text (``:normal``)
+    pub raw: String,
+}
+
+struct Command {
+    name: String,
+    min_len: usize,
+    callback: CommandFunctionType,
+}
+
+static COMMANDS: Lazy<RwLock<Vec<Command>>> = Lazy::new(|| RwLock::new(builtin_commands()));
+
+/// splits a name pattern like ``"sp[lit]"`` into the full name and the minimal abbreviation length
+fn parse_pattern(pattern: &str) -> (String, usize) {
+    match pattern.find('[') {
+        Some(idx) => (pattern.replace(['[', ']'], ""), idx),
+        None => (pattern.to_string(), pattern.len()),
+    }
+}
+
+fn new_command(pattern: &str, callback: CommandFunctionType) -> Command {
+    let (name, min_len) = parse_pattern(pattern);
+    Command {
+        name,
+        min_len,
+        callback,
+    }
+}
+
+/// registers a new command. Already registered commands with the same name get replaced
+pub async fn register(pattern: &str, callback: CommandFunctionType) {
+    let command = new_command(pattern, callback);
+    let mut commands = COMMANDS.write().await;
+    commands.retain(|cmd| cmd.name != command.name);
+    commands.push(command);
+}
+
+fn find<'a>(commands: &'a [Command], name: &str) -> Option<&'a Command> {
+    commands.iter().find(|cmd| cmd.name == name).or_else(|| {
+        commands
+            .iter()
+            .find(|cmd| name.len() >= cmd.min_len && cmd.name.starts_with(name))
+    })
+}
+
+/// splits a command line into the command name and its arguments
+pub fn parse(cmdline: &str) -> Result<(String, CommandArgs), String> {
+    let mut args = CommandArgs::default();
+    let mut rest = cmdline.trim_start().trim_start_matches(':').trim_start();
+    for modifier in ["vertical ", "vert "] {
+        if let Some(stripped) = rest.strip_prefix(modifier) {
+            args.vertical = true;
+            rest = stripped.trim_start();
+        }
+    }
+    let count_len = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if count_len > 0 {
+        args.count = Some(rest[..count_len].parse().map_err(|_| "count too large")?);
+        rest = &rest[count_len..];
+    }
+    let name_len = rest.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    if name_len == 0 {
+        return Err(format!("not an editor command: {cmdline}"));
+    }
+    let name = rest[..name_len].to_string();
+    rest = &rest[name_len..];
+    if let Some(stripped) = rest.strip_prefix('!') {
+        args.bang = true;
+        rest = stripped;
+    }
+    args.args = rest.split_whitespace().map(|arg| arg.to_string()).collect();
+    args.raw = rest.trim_start().to_string();
+    Ok((name, args))
+}
+
+/// parses and runs a command line (without the leading ``:``)
+pub async fn execute(cmdline: &str) -> Result<(), String> {
+    let (name, args) = parse(cmdline)?;
+    let callback = {
+        let commands = COMMANDS.read().await;
+        match find(&commands, &name) {
+            Some(cmd) => Arc::clone(&cmd.callback),
+            None => return Err(format!("not an editor command: {name}")),
+        }
+    };
+    (callback)(args).await
+}
+
+/// wraps an async fn into the callback type
+pub fn callback<F, Fut>(f: F) -> CommandFunctionType
+where
+    F: Fn(CommandArgs) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    Arc::new(Box::new(move |args| Box::pin(f(args))))
+}
+
+async fn split(args: CommandArgs, dir: SplitDirection) -> Result<(), String> {
+    manager::split_focused(dir).await?;
+    if let Some(size) = args.count {
+        resize_to(dir, size).await?;
+    }
+    Ok(())
+}
+
+async fn resize_to(dir: SplitDirection, size: u32) -> Result<(), String> {
+    let current = {
+        let focused = manager::focused().await?;
+        let buf = focused.deref().await;
+        buf.rect().extent(dir)
+    };
+    manager::resize_focused(dir, size as i16 - current as i16).await
+}
+
+async fn resize(args: CommandArgs) -> Result<(), String> {
+    let dir = if args.vertical {
+        SplitDirection::Vertical
+    } else {
+        SplitDirection::Horizontal
+    };
+    let arg = args.args.first().map(|arg| arg.as_str()).unwrap_or("");
+    let num = |num: &str| {
+        num.parse::<i16>()
+            .map_err(|_| format!("invalid argument: {arg}"))
+    };
+    if let Some(delta) = arg.strip_prefix('+') {
+        manager::resize_focused(dir, num(delta)?).await
+    } else if let Some(delta) = arg.strip_prefix('-') {
+        manager::resize_focused(dir, -num(delta)?).await
+    } else if !arg.is_empty() {
+        resize_to(dir, num(arg)? as u32).await
+    } else {
+        // like vim, no argument means as large as possible
+        resize_to(dir, args.count.unwrap_or(u16::MAX as u32 / 2)).await
+    }
+}
+
+async fn layout(args: CommandArgs) -> Result<(), String> {
+    match args.args.first() {
+        Some(name) => manager::set_layout(name).await,
+        None => Err(format!(
+            "argument required, one of: {}",
+            manager::LAYOUT_NAMES.join(", ")
+        )),
+    }
+}
+
+/// ``:tabnext`` goes to the next tab, ``:tabnext 3`` and ``:3tabnext`` to the third one
+async fn tabnext(args: CommandArgs) -> Result<(), String> {
+    let number = match args.args.first() {
+        Some(arg) => Some(
+            arg.parse()
+                .map_err(|_| format!("invalid argument: {arg}"))?,
+        ),
+        None => args.count,
+    };
+    match number {
+        Some(number) => manager::goto_tab(number).await,
+        None => manager::cycle_tab(1).await,
+    }
+}
+
+/// opens the focused document in a new tab, right after the current one
+async fn tabnew(_: CommandArgs) -> Result<(), String> {
+    // every new window on a TabLayout gets its own tab, make sure we're on one
+    manager::cycle_tab(0).await?;
+    manager::split_focused(SplitDirection::Horizontal).await
+}
+
+/// configures the MasterLayout of the focused window:
+/// - ``:master left|right|top|bottom`` moves the master area
+/// - ``:master grow [N]``/``:master shrink [N]`` resizes it by N percent of the screen (default 5)
+/// - ``:master count N|+N|-N`` sets how many windows share the master area
+/// - ``:master rotate [N]`` rotates the windows through the master slot, backwards with ``!``
+/// - ``:master make`` moves the focused window into the master area
+async fn master(args: CommandArgs) -> Result<(), String> {
+    let sub = args.args.first().map(|arg| arg.as_str()).unwrap_or("");
+    let arg = args.args.get(1).map(|arg| arg.as_str());
+    let num = |default: i32| match arg {
+        Some(arg) => arg
+            .trim_start_matches('+')
+            .parse::<i32>()
+            .map_err(|_| format!("invalid argument: {arg}")),
+        None => Ok(default),
+    };
+    if let Some(position) = MasterPosition::from_name(sub) {
+        return manager::update_focused_master(move |master, _| {
+            master.set_position(position);
+            Ok(())
+        })
+        .await;
+    }
+    match sub {
+        "grow" | "shrink" => {
+            let sign = if sub == "grow" { 1. } else { -1. };
+            let delta = sign * num(5)? as f32 / 100.;
+            manager::update_focused_master(move |master, _| {
+                master.change_ratio(delta);
+                Ok(())
+            })
+            .await
+        }
+        "count" => {
+            let relative = arg.is_some_and(|arg| arg.starts_with(['+', '-']));
+            let count = num(1)?;
+            manager::update_focused_master(move |master, _| {
+                let count = if relative {
+                    master.master_count() as i32 + count
+                } else {
+                    count
+                };
+                master.set_master_count(count.max(0) as usize);
+                Ok(())
+            })
+            .await
+        }
+        "rotate" => {
+            let count = if args.bang { -num(1)? } else { num(1)? };
+            manager::update_focused_master(move |master, _| {
+                master.rotate(count);
+                Ok(())
+            })
+            .await
+        }
+        "make" => manager::update_focused_master(|master, id| master.change_master(id)).await,
+        _ => Err(format!("unknown argument: {sub}")),
+    }
+}
+
+/// the document of the focused window
+async fn focused_document() -> Result<DocumentRef, String> {
+    let focused = manager::focused().await?;
+    let doc = Arc::clone(focused.deref().await.document());
+    Ok(doc)
+}
+
+/// writes ``doc`` to ``file`` (its own file if ``None``). Without ``force``, readonly documents
+/// and existing files other than the own one are refused
+async fn write_document(doc: &DocumentRef, file: Option<&str>, force: bool) -> Result<(), String> {
+    let (path, readonly) = {
+        let doc = doc.read().unwrap();
+        (doc.path().map(|path| path.to_string()), doc.is_readonly())
+    };
+    let target = match (file, &path) {
+        (Some(file), _) => file.to_string(),
+        (None, Some(path)) => path.clone(),
+        (None, None) => return Err("no file name".to_string()),
+    };
+    let own_file = path
+        .as_deref()
+        .is_some_and(|path| io::same_file(path, &target));
+    if !force && own_file && readonly {
+        return Err("readonly option is set (add ! to override)".to_string());
+    }
+    if !force && !own_file && tokio::fs::try_exists(&target).await.unwrap_or(false) {
+        return Err(format!("{target} exists (add ! to override)"));
+    }
+    // like in vim, a document without a file gets the one it is written to
+    if path.is_none() {
+        doc.write().unwrap().set_path(Some(target.clone()));
+    }
+    io::write_document(doc, &target)
+        .await
+        .map_err(|err| format!("can't write {target}: {err}"))
+}
+
+/// ``:w[rite][!] [file]``
+async fn write(args: CommandArgs) -> Result<(), String> {
+    let doc = focused_document().await?;
+    write_document(&doc, args.args.first().map(|file| file.as_str()), args.bang).await
+}
+
+/// ``:wa[ll][!]`` writes every modified document, that has a file
+async fn write_all(args: CommandArgs) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for doc in Document::all() {
+        let unsaved = {
+            let doc = doc.read().unwrap();
+            doc.is_modified() && doc.path().is_some()
+        };
+        if unsaved {
+            if let Err(err) = write_document(&doc, None, args.bang).await {
+                errors.push(err);
+            }
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join(", ")),
+    }
+}
+
+/// ``:q[uit][!]`` closes the focused window, the editor if it is the last one. Changes to a
+/// document, that isn't shown in another window, have to be written first, unless with ``!``
+async fn quit(args: CommandArgs) -> Result<(), String> {
+    let doc = focused_document().await?;
+    let windows = manager::window_cursors().await;
+    let shown = windows
+        .iter()
+        .filter(|(other, _)| Arc::ptr_eq(other, &doc))
+        .count();
+    if !args.bang && shown <= 1 && doc.read().unwrap().is_modified() {
+        return Err("no write since last change (add ! to override)".to_string());
+    }
+    if windows.len() <= 1 {
+        input::quit();
+        return Ok(());
+    }
+    manager::close_focused().await
+}
+
+/// ``:qa[ll][!]`` exits the editor, unless a document has unwritten changes
+async fn quit_all(args: CommandArgs) -> Result<(), String> {
+    if !args.bang {
+        let modified = Document::all().into_iter().find_map(|doc| {
+            let doc = doc.read().unwrap();
+            doc.is_modified()
+                .then(|| doc.path().unwrap_or("[No Name]").to_string())
+        });
+        if let Some(name) = modified {
+            return Err(format!(
+                "no write since last change for {name} (add ! to override)"
+            ));
+        }
+    }
+    input::quit();
+    Ok(())
+}
+
+/// ``:wq`` writes and quits, ``:x[it]`` only writes if there are changes
+async fn write_quit(args: CommandArgs, only_changes: bool) -> Result<(), String> {
+    let doc = focused_document().await?;
+    if !only_changes || doc.read().unwrap().is_modified() {
+        write_document(&doc, args.args.first().map(|file| file.as_str()), args.bang).await?;
+    }
+    quit(args).await
+}
+
+/// ``:so[urce] {file}`` runs the commands in ``file``, one per line. Empty lines and the ones
+/// starting with ``"`` are skipped, the first failing command stops it
+async fn source(args: CommandArgs) -> Result<(), String> {
+    let file = args.args.first().ok_or("argument required")?;
+    let text = tokio::fs::read_to_string(file)
+        .await
+        .map_err(|err| format!("can't read {file}: {err}"))?;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('"') {
+            continue;
+        }
+        // boxed, because the file might source another one
+        Box::pin(execute(line))
+            .await
+            .map_err(|err| format!("{file}:{}: {err}", idx + 1))?;
+        if input::quit_requested() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// what ``option`` does to the line numbers, ``number`` and ``relativenumber`` combine like in vim
+fn set_numbers(numbers: LineNumbers, option: &str) -> Option<LineNumbers> {
+    use LineNumbers::*;
+    Some(match (option, numbers) {
+        ("number" | "nu", Relative | Hybrid) => Hybrid,
+        ("number" | "nu", _) => Absolute,
+        ("relativenumber" | "rnu", Absolute | Hybrid) => Hybrid,
+        ("relativenumber" | "rnu", _) => Relative,
+        ("nonumber" | "nonu", Relative | Hybrid) => Relative,
+        ("nonumber" | "nonu", _) => Off,
+        ("norelativenumber" | "nornu", Absolute | Hybrid) => Absolute,
+        ("norelativenumber" | "nornu", _) => Off,
+        _ => return None,
+    })
+}
+
+/// what ``option`` changes about the format a document is written in, ``None`` if it is no
+/// option of the format
+fn set_format(format: &mut FileFormat, option: &str) -> Option<Result<(), String>> {
+    match option.split_once('=') {
+        Some(("fileformat" | "ff", name)) => match LineEnding::from_name(name) {
+            Some(line_ending) => format.line_ending = line_ending,
+            None => return Some(Err(format!("invalid file format: {name}"))),
+        },
+        Some(("fileencoding" | "fenc", name)) => match Encoding::from_name(name) {
+            Some(encoding) => format.encoding = encoding,
+            None => return Some(Err(format!("unknown encoding: {name}"))),
+        },
+        _ => match option {
+            "endofline" | "eol" => format.final_newline = true,
+            "noendofline" | "noeol" => format.final_newline = false,
+            _ => return None,
+        },
+    }
+    Some(Ok(()))
+}
+
+/// sets options of the focused window: ``number``, ``relativenumber`` (and their ``no`` versions)
+/// and ``signcolumn=yes|no|auto``, of its document: ``fileformat=unix|dos|mac``,
+/// ``fileencoding=...`` and ``[no]endofline``, which take effect when it is written, as well as
+/// the global ``autoread``, ``multilinefind``, ``clipboard=osc52|none`` and
+/// ``clipboardcmd=copy[;paste]``
+async fn set(args: CommandArgs) -> Result<(), String> {
+    if args.args.is_empty() {
+        return Err("argument required".to_string());
+    }
+    let doc = focused_document().await.ok();
+    for option in args.args.iter() {
+        if let Some(doc) = doc.as_ref() {
+            let mut format = doc.read().unwrap().format();
+            if let Some(res) = set_format(&mut format, option) {
+                res?;
+                doc.write().unwrap().set_format(format);
+                continue;
+            }
+        }
+        match option.split_once('=') {
+            Some(("clipboard" | "cb", "osc52")) => {
+                registers::set_clipboard_provider(ClipboardProvider::Osc52).await
+            }
+            Some(("clipboard" | "cb", "none")) => {
+                registers::set_clipboard_provider(ClipboardProvider::None).await
+            }
+            Some(("clipboardcmd", cmds)) => {
+                let provider = ClipboardProvider::from_commands(cmds)
+                    .ok_or_else(|| format!("invalid clipboard command: {cmds}"))?;
+                registers::set_clipboard_provider(provider).await
+            }
+            _ => {}
+        }
+        match option.as_str() {
+            "autoread" | "ar" => autoread::set_enabled(true),
+            "noautoread" | "noar" => autoread::set_enabled(false),
+            "multilinefind" | "mlf" => keymap::set_multiline_find(true),
+            "nomultilinefind" | "nomlf" => keymap::set_multiline_find(false),
+            _ => {}
+        }
+    }
+    manager::update_focused_gutter(|gutter| {
+        for option in args.args.iter() {
+            if let Some(numbers) = set_numbers(gutter.numbers, option) {
+                gutter.numbers = numbers;
+                continue;
+            }
+            gutter.signs = match option.split_once('=') {
+                Some(("signcolumn" | "scl", "yes")) => SignColumn::Yes,
+                Some(("signcolumn" | "scl", "no")) => SignColumn::No,
+                Some(("signcolumn" | "scl", "auto")) => SignColumn::Auto,
+                // unknown options are ignored, like the rest of vim's options
+                _ => continue,
+            };
+        }
+    })
+    .await
+}
+
+fn builtin_commands() -> Vec<Command> {
+    vec![
+        new_command(
+            "sp[lit]",
+            callback(|args: CommandArgs| {
+                let dir = if args.vertical {
+                    SplitDirection::Vertical
+                } else {
+                    SplitDirection::Horizontal
+                };
+                split(args, dir)
+            }),
+        ),
+        new_command(
+            "vs[plit]",
+            callback(|args| split(args, SplitDirection::Vertical)),
+        ),
+        new_command(
+            "clo[se]",
+            callback(|_| async { manager::close_focused().await }),
+        ),
+        new_command("res[ize]", callback(resize)),
+        new_command("lay[out]", callback(layout)),
+        new_command("tabn[ext]", callback(tabnext)),
+        new_command(
+            "tabp[revious]",
+            callback(|args: CommandArgs| manager::cycle_tab(-(args.count.unwrap_or(1) as i64))),
+        ),
+        new_command(
+            "tabN[ext]",
+            callback(|args: CommandArgs| manager::cycle_tab(-(args.count.unwrap_or(1) as i64))),
+        ),
+        new_command("tabnew", callback(tabnew)),
+        new_command("mas[ter]", callback(master)),
+        new_command("se[t]", callback(set)),
+        new_command("checkt[ime]", callback(|_| autoread::checktime())),
+        new_command("w[rite]", callback(write)),
+        new_command("wa[ll]", callback(write_all)),
+        new_command("q[uit]", callback(quit)),
+        new_command("qa[ll]", callback(quit_all)),
+        new_command("wq", callback(|args| write_quit(args, false))),
+        new_command("x[it]", callback(|args| write_quit(args, true))),
+        new_command(
+            "norm[al]",
+            callback(|args: CommandArgs| async move { keymap::normal(&args.raw).await }),
+        ),
+        new_command("so[urce]", callback(source)),
+        new_command(
+            "mks[ession]",
+            callback(|args: CommandArgs| async move {
+                let path = args.args.first().map_or(session::DEFAULT_PATH, |path| path);
+                session::save(path, args.bang).await
+            }),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full() {
+        let (name, args) = parse(":vert 3res! +2 foo").unwrap();
+        assert_eq!(name, "res");
+        assert_eq!(
+            args,
+            CommandArgs {
+                count: Some(3),
+                bang: true,
+                vertical: true,
+                args: vec!["+2".to_string(), "foo".to_string()],
+                raw: "+2 foo".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn number_options_combine() {
+        let numbers = set_numbers(LineNumbers::Off, "nu").unwrap();
+        assert_eq!(numbers, LineNumbers::Absolute);
+        let numbers = set_numbers(numbers, "rnu").unwrap();
+        assert_eq!(numbers, LineNumbers::Hybrid);
+        let numbers = set_numbers(numbers, "nonu").unwrap();
+        assert_eq!(numbers, LineNumbers::Relative);
+        assert_eq!(set_numbers(numbers, "nornu"), Some(LineNumbers::Off));
+        assert_eq!(set_numbers(numbers, "list"), None);
+    }
+
+    #[test]
+    fn format_options() {
+        let mut format = FileFormat::default();
+        assert_eq!(set_format(&mut format, "ff=dos"), Some(Ok(())));
+        assert_eq!(set_format(&mut format, "fenc=latin1"), Some(Ok(())));
+        assert_eq!(set_format(&mut format, "noeol"), Some(Ok(())));
+        assert_eq!(
+            format,
+            FileFormat {
+                encoding: Encoding::Latin1,
+                line_ending: LineEnding::CrLf,
+                final_newline: false,
+            }
+        );
+        assert!(set_format(&mut format, "ff=amiga").unwrap().is_err());
+        assert_eq!(set_format(&mut format, "nu"), None);
+    }
+
+    #[test]
+    fn parse_no_name() {
+        assert!(parse("42").is_err());
+    }
+
+    #[test]
+    fn abbreviations() {
+        let commands = builtin_commands();
+        assert_eq!(find(&commands, "sp").unwrap().name, "split");
+        assert_eq!(find(&commands, "vsplit").unwrap().name, "vsplit");
+        assert!(find(&commands, "s").is_none());
+        assert!(find(&commands, "splitx").is_none());
+        assert_eq!(find(&commands, "tabn").unwrap().name, "tabnext");
+        assert_eq!(find(&commands, "tabnew").unwrap().name, "tabnew");
+        assert_eq!(find(&commands, "tabN").unwrap().name, "tabNext");
+        assert_eq!(find(&commands, "w").unwrap().name, "write");
+        assert_eq!(find(&commands, "wa").unwrap().name, "wall");
+        assert_eq!(find(&commands, "q").unwrap().name, "quit");
+        assert_eq!(find(&commands, "qa").unwrap().name, "qall");
+        assert_eq!(find(&commands, "norm").unwrap().name, "normal");
+        assert!(find(&commands, "no").is_none());
+    }
+
+    #[tokio::test]
+    async fn write_own_file_by_another_name() {
+        let dir = std::env::temp_dir();
+        let name = format!("neoxide-write-{}", std::process::id());
+        let path = dir.join(&name).to_str().unwrap().to_string();
+        std::fs::write(&path, "old\n").unwrap();
+        let doc = Document::new_ref(vec!["new".to_string()], Some(path.clone()));
+        doc.write().unwrap().set_modified(true);
+        let other = dir.join(".").join(&name).to_str().unwrap().to_string();
+        write_document(&doc, Some(&other), false).await.unwrap();
+        assert!(!doc.read().unwrap().is_modified());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,711 @@
+//! # Documents
+//! A document is the text of a file (or a scratch buffer), independent of the windows that show
+//! it. Windows (``render::manager::Buffer``) only hold a ``DocumentRef`` together with their own
+//! cursor and scroll position, so the same document can be shown in several splits at once and an
+//! edit through one of them is immediately visible in all the others.
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::SystemTime;
+
+use super::fileformat::{self, FileFormat};
+use super::marks;
+use super::CursorPosition;
+use crate::core::io;
+
+pub type DocumentId = u32;
+/// The document content is accessed synchronously from the motions, which is why this uses the
+/// std lock instead of the tokio one. Never hold the guard across an ``.await``.
+pub type DocumentRef = Arc<RwLock<Document>>;
+
+static NEXT_DOC_ID: AtomicU32 = AtomicU32::new(0);
+// Weak, so a document gets freed together with its last window
+static DOCUMENTS: Lazy<Mutex<HashMap<DocumentId, Weak<RwLock<Document>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// a marker in the sign column of every window showing the document, e.g. for diagnostics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sign {
+    /// chosen by whoever places the sign, used to remove it again
+    pub id: u32,
+    pub line: u32,
+    /// at most 2 chars are shown
+    pub text: String,
+    /// highlight group of the theme
+    pub group: String,
+    /// if several signs are on one line, the one with the highest priority is shown
+    pub priority: u16,
+}
+
+/// a single change of the text, as it is recorded for undo
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Insert { pos: CursorPosition, text: String },
+    Remove { pos: CursorPosition, text: String },
+}
+
+/// everything, that is undone by a single ``u``
+#[derive(Debug, Clone, Default)]
+struct UndoStep {
+    changes: Vec<Change>,
+    /// where the cursor was before the first change
+    cursor: Option<CursorPosition>,
+}
+
+#[derive(Debug)]
+pub struct Document {
+    id: DocumentId,
+    content: Vec<String>,
+    path: Option<String>,
+    modified: bool,
+    /// like vim's ``'readonly'``, the document may be changed, but not written
+    readonly: bool,
+    signs: Vec<Sign>,
+    undo: Vec<UndoStep>,
+    redo: Vec<UndoStep>,
+    /// how many undo steps there were, when the document was last written or loaded. ``None`` if
+    /// undo and redo can't get back there
+    saved_at: Option<usize>,
+    /// changes are added to the last undo step instead of getting their own one
+    step_open: bool,
+    /// modification time of the file, when it was last read or written by the editor
+    disk_mtime: Option<SystemTime>,
+    /// how the content is stored in the file
+    format: FileFormat,
+    /// set with ``m`` or by the editor itself (``.``, ``[``, ...). They move along, when lines are
+    /// inserted or deleted above them
+    marks: HashMap<char, CursorPosition>,
+    /// positions moved along with the changes during ``track``
+    tracked: Vec<CursorPosition>,
+}
+
+/// converts a char index into a byte index of ``line``, clamping to the end of the line
+pub fn byte_idx(line: &str, x: usize) -> usize {
+    line.char_indices()
+        .nth(x)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+impl Document {
+    pub fn new(content: Vec<String>) -> Self {
+        Document {
+            id: NEXT_DOC_ID.fetch_add(1, Ordering::Relaxed),
+            content,
+            path: None,
+            modified: false,
+            readonly: false,
+            signs: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved_at: Some(0),
+            step_open: false,
+            disk_mtime: None,
+            format: FileFormat::default(),
+            marks: HashMap::new(),
+            tracked: Vec::new(),
+        }
+    }
+
+    /// creates a new document and registers it, so it can be looked up by its path later
+    pub fn new_ref(content: Vec<String>, path: Option<String>) -> DocumentRef {
+        let mut doc = Document::new(content);
+        doc.path = path;
+        let id = doc.id;
+        let doc = Arc::new(RwLock::new(doc));
+        let mut docs = DOCUMENTS.lock().unwrap();
+        docs.retain(|_, doc| doc.strong_count() > 0);
+        docs.insert(id, Arc::downgrade(&doc));
+        doc
+    }
+
+    /// returns the already opened document for ``path``, if there is one. The paths don't have
+    /// to be written the same way, ``notes.md`` and ``./notes.md`` are the same file
+    pub fn find_by_path(path: &str) -> Option<DocumentRef> {
+        let docs: Vec<DocumentRef> = DOCUMENTS
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|doc| doc.upgrade())
+            .collect();
+        docs.into_iter().find(|doc| {
+            doc.read()
+                .unwrap()
+                .path
+                .as_deref()
+                .is_some_and(|other| io::same_file(other, path))
+        })
+    }
+
+    /// all documents that still have at least one window
+    pub fn all() -> Vec<DocumentRef> {
+        DOCUMENTS
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|doc| doc.upgrade())
+            .collect()
+    }
+
+    pub fn id(&self) -> DocumentId {
+        self.id
+    }
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+    pub fn set_path(&mut self, path: Option<String>) {
+        self.path = path;
+    }
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+    /// ``false`` after the document was written or loaded, undoing the changes made since
+    /// makes it unmodified again
+    pub fn set_modified(&mut self, modified: bool) {
+        self.modified = modified;
+        self.saved_at = (!modified).then_some(self.undo.len());
+    }
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+    }
+    pub fn disk_mtime(&self) -> Option<SystemTime> {
+        self.disk_mtime
+    }
+    pub fn set_disk_mtime(&mut self, mtime: Option<SystemTime>) {
+        self.disk_mtime = mtime;
+    }
+    pub fn format(&self) -> FileFormat {
+        self.format
+    }
+    /// changes the format the document is written in, which counts as a change
+    pub fn set_format(&mut self, format: FileFormat) {
+        if format != self.format {
+            self.format = format;
+            self.set_modified(true);
+        }
+    }
+
+    /// places a sign, replacing the one with the same id
+    pub fn place_sign(&mut self, sign: Sign) {
+        self.signs.retain(|other| other.id != sign.id);
+        self.signs.push(sign);
+    }
+    pub fn unplace_sign(&mut self, id: u32) {
+        self.signs.retain(|sign| sign.id != id);
+    }
+    pub fn signs(&self) -> &[Sign] {
+        &self.signs
+    }
+
+    pub fn mark(&self, name: char) -> Option<CursorPosition> {
+        self.marks.get(&name).copied()
+    }
+    pub fn set_mark(&mut self, name: char, pos: CursorPosition) {
+        self.marks.insert(name, pos);
+    }
+    pub fn remove_mark(&mut self, name: char) {
+        self.marks.remove(&name);
+    }
+    pub fn marks(&self) -> impl Iterator<Item = (char, CursorPosition)> + '_ {
+        self.marks.iter().map(|(name, pos)| (*name, *pos))
+    }
+
+    /// sets ``[`` and ``]`` to the first and last char of changed or yanked text, ``end`` being
+    /// exclusive
+    pub fn mark_range(&mut self, start: CursorPosition, end: CursorPosition) {
+        let last = CursorPosition {
+            x: end.x.saturating_sub(1),
+            y: end.y,
+        };
+        self.set_mark('[', start);
+        self.set_mark(']', if end.x > 0 { last } else { end });
+    }
+
+    /// runs ``f`` on the document and moves ``positions`` along with the changes it makes, like
+    /// marks, e.g. the cursors of other windows showing the document. Positions on removed lines
+    /// end up where the removal started, all of them are kept on the content
+    pub fn track<R>(
+        &mut self,
+        positions: &mut [CursorPosition],
+        f: impl FnOnce(&mut Document) -> R,
+    ) -> R {
+        self.tracked = positions.to_vec();
+        let ret = f(self);
+        for (pos, tracked) in positions.iter_mut().zip(std::mem::take(&mut self.tracked)) {
+            *pos = self.clamp(tracked);
+        }
+        ret
+    }
+
+    fn adjust_marks(&mut self, f: impl Fn(CursorPosition) -> Option<CursorPosition>) {
+        self.marks.retain(|_, pos| match f(*pos) {
+            Some(new) => {
+                *pos = new;
+                true
+            }
+            None => false,
+        });
+    }
+
+    pub fn lines(&self) -> &Vec<String> {
+        &self.content
+    }
+    pub fn line(&self, y: usize) -> Option<&String> {
+        self.content.get(y)
+    }
+    pub fn line_count(&self) -> usize {
+        self.content.len()
+    }
+    /// moves ``pos`` onto the content, e.g. for a position saved before the file changed
+    pub fn clamp(&self, pos: CursorPosition) -> CursorPosition {
+        let y = pos.y.min(self.line_count().saturating_sub(1) as u32);
+        let len = self.line(y as usize).map_or(0, |line| line.chars().count()) as u32;
+        CursorPosition {
+            x: pos.x.min(len.saturating_sub(1)),
+            y,
+        }
+    }
+
+    /// replaces the whole content, e.g. when (re)loading a file. The line endings and whether
+    /// there is a final one are taken over into the format. Doesn't mark the document as modified
+    /// and forgets the undo history
+    pub fn set_content(&mut self, content: &str) {
+        let (lines, line_ending, final_newline) = fileformat::split_lines(content);
+        self.content = lines;
+        self.format.line_ending = line_ending;
+        self.format.final_newline = final_newline;
+        self.undo.clear();
+        self.redo.clear();
+        self.step_open = false;
+    }
+
+    /// groups all following changes into one undo step, until ``end_undo_step`` is called (e.g.
+    /// everything typed in one go in insert mode). ``cursor`` is restored, when it gets undone
+    pub fn begin_undo_step(&mut self, cursor: CursorPosition) {
+        self.end_undo_step();
+        self.undo.push(UndoStep {
+            changes: Vec::new(),
+            cursor: Some(cursor),
+        });
+        self.step_open = true;
+    }
+
+    pub fn end_undo_step(&mut self) {
+        if self.step_open && self.undo.last().is_some_and(|step| step.changes.is_empty()) {
+            self.undo.pop();
+        }
+        self.step_open = false;
+    }
+
+    fn record(&mut self, change: Change) {
+        let (start, end) = match &change {
+            Change::Insert { pos, text } => (*pos, end_of(*pos, text)),
+            Change::Remove { pos, .. } => (*pos, *pos),
+        };
+        self.set_mark('.', start);
+        self.mark_range(start, end);
+        self.redo.clear();
+        // the saved state was undone or is changed further
+        if self.saved_at.is_some_and(|saved| {
+            saved > self.undo.len() || (saved == self.undo.len() && self.step_open)
+        }) {
+            self.saved_at = None;
+        }
+        match self.undo.last_mut() {
+            Some(step) if self.step_open => step.changes.push(change),
+            _ => self.undo.push(UndoStep {
+                changes: vec![change],
+                cursor: None,
+            }),
+        }
+    }
+
+    /// reverts the last undo step. Returns where the cursor should go, ``None`` if there was
+    /// nothing to undo
+    pub fn undo(&mut self) -> Option<CursorPosition> {
+        self.end_undo_step();
+        let step = self.undo.pop()?;
+        let mut cursor = step.cursor;
+        for change in step.changes.iter().rev() {
+            cursor = Some(match change {
+                Change::Insert { pos, text } => {
+                    let end = end_of(*pos, text);
+                    self.remove_unrecorded(*pos, end);
+                    *pos
+                }
+                Change::Remove { pos, text } => {
+                    self.insert_unrecorded(*pos, text);
+                    *pos
+                }
+            });
+        }
+        // the cursor from before the change is more helpful than the position of the last change
+        let cursor = step.cursor.or(cursor);
+        self.redo.push(step);
+        self.modified = self.saved_at != Some(self.undo.len());
+        cursor
+    }
+
+    /// applies the last undone step again
+    pub fn redo(&mut self) -> Option<CursorPosition> {
+        self.end_undo_step();
+        let step = self.redo.pop()?;
+        let mut cursor = None;
+        for change in step.changes.iter() {
+            cursor = Some(match change {
+                Change::Insert { pos, text } => {
+                    self.insert_unrecorded(*pos, text);
+                    *pos
+                }
+                Change::Remove { pos, text } => {
+                    self.remove_unrecorded(*pos, end_of(*pos, text));
+                    *pos
+                }
+            });
+        }
+        self.undo.push(step);
+        self.modified = self.saved_at != Some(self.undo.len());
+        cursor
+    }
+
+    /// inserts ``text`` (which may contain newlines) at ``pos`` and returns the position right
+    /// after the inserted text
+    pub fn insert_str(&mut self, pos: CursorPosition, text: &str) -> CursorPosition {
+        let (start, end) = self.insert_unrecorded(pos, text);
+        self.record(Change::Insert {
+            pos: start,
+            text: text.to_string(),
+        });
+        end
+    }
+
+    /// returns the position the text actually got inserted at (``pos`` clamped to the document)
+    /// and the position after it
+    fn insert_unrecorded(
+        &mut self,
+        pos: CursorPosition,
+        text: &str,
+    ) -> (CursorPosition, CursorPosition) {
+        if self.content.is_empty() {
+            self.content.push(String::new());
+        }
+        let y = std::cmp::min(pos.y as usize, self.content.len() - 1);
+        let line = &mut self.content[y];
+        let start = CursorPosition {
+            x: std::cmp::min(pos.x as usize, line.chars().count()) as u32,
+            y: y as u32,
+        };
+        let idx = byte_idx(line, pos.x as usize);
+        let rest = line.split_off(idx);
+        let mut inserted = text.split('\n');
+        // split always yields at least one item
+        let first = inserted.next().unwrap();
+        line.push_str(first);
+        let mut end = CursorPosition {
+            x: start.x + first.chars().count() as u32,
+            y: y as u32,
+        };
+        for (i, new_line) in inserted.enumerate() {
+            self.content.insert(y + i + 1, new_line.to_string());
+            end = CursorPosition {
+                x: new_line.chars().count() as u32,
+                y: (y + i + 1) as u32,
+            };
+        }
+        self.content[end.y as usize].push_str(&rest);
+        self.modified = true;
+        self.adjust_marks(|pos| Some(after_insert(pos, start, end)));
+        for pos in self.tracked.iter_mut() {
+            *pos = after_insert(*pos, start, end);
+        }
+        (start, end)
+    }
+
+    /// removes everything from ``start`` (inclusive) to ``end`` (exclusive), joining lines if the
+    /// range spans several of them. Returns the removed text
+    pub fn remove(&mut self, start: CursorPosition, end: CursorPosition) -> String {
+        let pos = if (start.y, start.x) <= (end.y, end.x) {
+            start
+        } else {
+            end
+        };
+        let removed = self.remove_unrecorded(start, end);
+        if !removed.is_empty() {
+            let x = pos.x.min(
+                self.line(pos.y as usize)
+                    .map_or(0, |line| line.chars().count()) as u32,
+            );
+            self.record(Change::Remove {
+                pos: CursorPosition { x, y: pos.y },
+                text: removed.clone(),
+            });
+        }
+        removed
+    }
+
+    fn remove_unrecorded(&mut self, start: CursorPosition, end: CursorPosition) -> String {
+        let (start, end) = if (start.y, start.x) <= (end.y, end.x) {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        if self.content.is_empty() || start.y as usize >= self.content.len() {
+            return String::new();
+        }
+        let end_y = std::cmp::min(end.y as usize, self.content.len() - 1);
+        let start_y = start.y as usize;
+        let start_idx = byte_idx(&self.content[start_y], start.x as usize);
+        let end_idx = byte_idx(&self.content[end_y], end.x as usize);
+        let removed = if start_y == end_y {
+            self.content[start_y]
+                .drain(start_idx..std::cmp::max(start_idx, end_idx))
+                .collect()
+        } else {
+            let tail = self.content[end_y].split_off(end_idx);
+            let mut removed = self.content[start_y].split_off(start_idx);
+            for line in self.content.drain(start_y + 1..=end_y) {
+                removed.push('\n');
+                removed.push_str(&line);
+            }
+            self.content[start_y].push_str(&tail);
+            removed
+        };
+        self.modified = true;
+        self.adjust_marks(|pos| after_remove(pos, start, end));
+        for pos in self.tracked.iter_mut() {
+            *pos = after_remove(*pos, start, end).unwrap_or(start);
+        }
+        removed
+    }
+}
+
+impl Drop for Document {
+    /// the marks survive the document, ``'A`` opens its file again
+    fn drop(&mut self) {
+        if let Some(path) = self.path.as_deref() {
+            marks::keep_closed(path, self.marks.iter().map(|(name, pos)| (*name, *pos)));
+        }
+    }
+}
+
+/// where a mark at ``pos`` ends up, after text got inserted from ``start`` to ``end``. Like in
+/// vim, only inserted lines move marks, the columns stay the same
+fn after_insert(pos: CursorPosition, start: CursorPosition, end: CursorPosition) -> CursorPosition {
+    if end.y == start.y || (pos.y, pos.x) < (start.y, start.x) {
+        pos
+    } else if pos.y == start.y {
+        // the rest of the line got moved behind the inserted text
+        CursorPosition {
+            x: end.x + pos.x - start.x,
+            y: end.y,
+        }
+    } else {
+        CursorPosition {
+            x: pos.x,
+            y: pos.y + end.y - start.y,
+        }
+    }
+}
+
+/// where a mark at ``pos`` ends up, after the text from ``start`` to ``end`` got removed. Marks on
+/// lines, that were removed completely, are deleted
+fn after_remove(
+    pos: CursorPosition,
+    start: CursorPosition,
+    end: CursorPosition,
+) -> Option<CursorPosition> {
+    if end.y == start.y || (pos.y, pos.x) < (start.y, start.x) {
+        return Some(pos);
+    }
+    if (pos.y, pos.x) < (end.y, end.x) {
+        let whole_line = (start.x == 0 || pos.y > start.y) && end.y > pos.y;
+        return (!whole_line).then_some(start);
+    }
+    Some(if pos.y == end.y {
+        CursorPosition {
+            x: start.x + pos.x - end.x,
+            y: start.y,
+        }
+    } else {
+        CursorPosition {
+            x: pos.x,
+            y: pos.y - (end.y - start.y),
+        }
+    })
+}
+
+/// the position right after ``text``, if it starts at ``pos``
+fn end_of(pos: CursorPosition, text: &str) -> CursorPosition {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => CursorPosition {
+            x: last.chars().count() as u32,
+            y: pos.y + text.matches('\n').count() as u32,
+        },
+        None => CursorPosition {
+            x: pos.x + text.chars().count() as u32,
+            y: pos.y,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc() -> Document {
+        Document::new(vec!["Hello World".to_string(), "second line".to_string()])
+    }
+
+    #[test]
+    fn insert_single_line() {
+        let mut doc = doc();
+        let end = doc.insert_str(CursorPosition { x: 5, y: 0 }, ",");
+        assert_eq!(doc.lines()[0], "Hello, World");
+        assert_eq!(end, CursorPosition { x: 6, y: 0 });
+        assert!(doc.is_modified());
+    }
+
+    #[test]
+    fn insert_multi_line() {
+        let mut doc = doc();
+        let end = doc.insert_str(CursorPosition { x: 6, y: 0 }, "new\nline ");
+        assert_eq!(doc.lines(), &vec!["Hello new", "line World", "second line"]);
+        assert_eq!(end, CursorPosition { x: 5, y: 1 });
+    }
+
+    #[test]
+    fn remove_single_line() {
+        let mut doc = doc();
+        let removed = doc.remove(
+            CursorPosition { x: 5, y: 0 },
+            CursorPosition { x: 11, y: 0 },
+        );
+        assert_eq!(removed, " World");
+        assert_eq!(doc.lines()[0], "Hello");
+    }
+
+    #[test]
+    fn remove_multi_line() {
+        let mut doc = doc();
+        let removed = doc.remove(CursorPosition { x: 6, y: 0 }, CursorPosition { x: 7, y: 1 });
+        assert_eq!(removed, "World\nsecond ");
+        assert_eq!(doc.lines(), &vec!["Hello line"]);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut doc = doc();
+        doc.insert_str(CursorPosition { x: 5, y: 0 }, ",\nnew");
+        doc.remove(CursorPosition { x: 0, y: 2 }, CursorPosition { x: 7, y: 2 });
+        assert_eq!(doc.lines(), &vec!["Hello,", "new World", "line"]);
+        assert_eq!(doc.undo(), Some(CursorPosition { x: 0, y: 2 }));
+        assert_eq!(doc.lines(), &vec!["Hello,", "new World", "second line"]);
+        assert_eq!(doc.undo(), Some(CursorPosition { x: 5, y: 0 }));
+        assert_eq!(doc.lines(), &vec!["Hello World", "second line"]);
+        assert_eq!(doc.undo(), None);
+        doc.redo();
+        assert_eq!(doc.lines(), &vec!["Hello,", "new World", "second line"]);
+    }
+
+    #[test]
+    fn undo_steps_group_changes() {
+        let mut doc = doc();
+        let cursor = CursorPosition { x: 0, y: 1 };
+        doc.begin_undo_step(cursor);
+        let end = doc.insert_str(cursor, "a");
+        doc.insert_str(end, "b");
+        doc.end_undo_step();
+        assert_eq!(doc.lines()[1], "absecond line");
+        assert_eq!(doc.undo(), Some(cursor));
+        assert_eq!(doc.lines()[1], "second line");
+        // an empty step isn't kept
+        doc.begin_undo_step(cursor);
+        doc.end_undo_step();
+        assert_eq!(doc.undo(), None);
+    }
+
+    #[test]
+    fn undo_back_to_the_written_text() {
+        let mut doc = doc();
+        doc.insert_str(CursorPosition { x: 0, y: 0 }, "a");
+        doc.set_modified(false);
+        doc.insert_str(CursorPosition { x: 0, y: 1 }, "b");
+        assert!(doc.is_modified());
+        doc.undo();
+        assert!(!doc.is_modified());
+        doc.redo();
+        assert!(doc.is_modified());
+        doc.undo();
+        doc.undo();
+        assert!(doc.is_modified());
+        // the written text can't be reached again after a change from here
+        doc.insert_str(CursorPosition { x: 0, y: 0 }, "c");
+        doc.undo();
+        assert!(doc.is_modified());
+    }
+
+    #[test]
+    fn marks_follow_inserted_lines() {
+        let mut doc = doc();
+        doc.set_mark('a', CursorPosition { x: 3, y: 1 });
+        doc.set_mark('b', CursorPosition { x: 8, y: 0 });
+        doc.set_mark('c', CursorPosition { x: 2, y: 0 });
+        doc.insert_str(CursorPosition { x: 5, y: 0 }, "\nnew\n");
+        assert_eq!(doc.mark('a'), Some(CursorPosition { x: 3, y: 3 }));
+        assert_eq!(doc.mark('b'), Some(CursorPosition { x: 3, y: 2 }));
+        assert_eq!(doc.mark('c'), Some(CursorPosition { x: 2, y: 0 }));
+        // inserting within a line keeps the columns
+        doc.insert_str(CursorPosition { x: 0, y: 3 }, "xx");
+        assert_eq!(doc.mark('a'), Some(CursorPosition { x: 3, y: 3 }));
+        doc.undo();
+        doc.undo();
+        assert_eq!(doc.mark('a'), Some(CursorPosition { x: 3, y: 1 }));
+    }
+
+    #[test]
+    fn marks_of_deleted_lines_are_removed() {
+        let mut doc = Document::new(vec!["one".into(), "two".into(), "three".into()]);
+        doc.set_mark('a', CursorPosition { x: 1, y: 1 });
+        doc.set_mark('b', CursorPosition { x: 2, y: 2 });
+        doc.set_mark('c', CursorPosition { x: 2, y: 0 });
+        doc.remove(CursorPosition { x: 0, y: 1 }, CursorPosition { x: 0, y: 2 });
+        assert_eq!(doc.mark('a'), None);
+        assert_eq!(doc.mark('b'), Some(CursorPosition { x: 2, y: 1 }));
+        assert_eq!(doc.mark('c'), Some(CursorPosition { x: 2, y: 0 }));
+        // joining lines moves the mark behind the end of the first one
+        doc.remove(CursorPosition { x: 3, y: 0 }, CursorPosition { x: 0, y: 1 });
+        assert_eq!(doc.mark('b'), Some(CursorPosition { x: 5, y: 0 }));
+        assert_eq!(doc.mark('.'), Some(CursorPosition { x: 3, y: 0 }));
+    }
+
+    #[test]
+    fn tracked_positions_follow_changes() {
+        let mut doc = Document::new(vec!["one".into(), "two".into(), "three".into()]);
+        let pos = |x, y| CursorPosition { x, y };
+        let mut positions = [pos(2, 2), pos(1, 1)];
+        doc.track(&mut positions, |doc| {
+            doc.insert_str(pos(0, 0), "new\n");
+        });
+        assert_eq!(positions, [pos(2, 3), pos(1, 2)]);
+        // like ``dG`` on the second line: nothing is left below the first one
+        doc.track(&mut positions, |doc| {
+            doc.remove(pos(3, 0), pos(5, 3));
+        });
+        assert_eq!(positions, [pos(2, 0), pos(2, 0)]);
+    }
+
+    #[test]
+    fn shared_between_refs() {
+        let doc = Document::new_ref(vec!["a".to_string()], Some("shared.txt".to_string()));
+        let other = Document::find_by_path("shared.txt").unwrap();
+        other
+            .write()
+            .unwrap()
+            .insert_str(CursorPosition { x: 1, y: 0 }, "b");
+        assert_eq!(doc.read().unwrap().lines()[0], "ab");
+    }
+}
@@ -0,0 +1,43 @@
+//! # Editor events
+//! Events of the editor itself (as opposed to the raw terminal input of ``input``), similar to
+//! vim's autocommands. Plugins subscribe to them to react e.g. when the terminal loses the focus.
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use strum_macros::EnumCount;
+use tokio::sync::Mutex;
+
+use crate::core::event_handling::{EventCallback, EventHandler, EventIndex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumCount)]
+#[repr(u8)]
+pub enum EditorEvent {
+    /// the terminal (and with it the editor) got the focus
+    FocusGained,
+    FocusLost,
+    /// a file was changed by another program, ``EditorEventData::path`` is the file
+    FileChangedShell,
+    /// a document was loaded from disk again, after it was changed by another program
+    FileReloaded,
+}
+
+impl EventIndex for EditorEvent {}
+
+#[derive(Debug, Clone, Default)]
+pub struct EditorEventData {
+    /// the file the event is about, if any
+    pub path: Option<String>,
+}
+
+static EDITOR_EVH: Lazy<EventHandler<EditorEvent, EditorEventData>> = Lazy::new(EventHandler::new);
+
+pub async fn subscribe(evcb: EventCallback<EditorEvent, EditorEventData>) -> u32 {
+    EDITOR_EVH.subscribe(evcb).await
+}
+pub async fn unsub(event: EditorEvent, id: u32) -> Result<(), &'static str> {
+    EDITOR_EVH.unsubscribe(event, id).await
+}
+
+pub async fn dispatch(event: EditorEvent, data: EditorEventData) {
+    EDITOR_EVH.dispatch(event, Arc::new(Mutex::new(data))).await;
+}
@@ -0,0 +1,308 @@
+//! # File formats
+//! How the text of a document is stored on disk: its encoding, the line endings and whether the
+//! last line ends with one. They are detected when a file is read and used again when it gets
+//! written, so saving a file only changes the lines that were edited. ``:set fileencoding=...``,
+//! ``:set fileformat=...`` and ``:set [no]endofline`` convert it on the next write.
+//!
+//! The encoding is detected in this order: a UTF-8 or UTF-16 byte order mark, valid UTF-8 and
+//! finally Latin-1, which every file is valid in. UTF-16 without a byte order mark is read as
+//! Latin-1, like vim does without ``'fileencodings'``.
+
+/// the encoding of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    /// the name ``:set fileencoding`` takes
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "utf-8-bom" | "utf8-bom" => Encoding::Utf8Bom,
+            "utf-16" | "utf-16le" | "utf16le" => Encoding::Utf16Le,
+            "utf-16be" | "utf16be" => Encoding::Utf16Be,
+            "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+            _ => return None,
+        })
+    }
+}
+
+/// what ends a line in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    /// the name ``:set fileformat`` takes, like in vim
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "unix",
+            LineEnding::CrLf => "dos",
+            LineEnding::Cr => "mac",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "unix" => LineEnding::Lf,
+            "dos" => LineEnding::CrLf,
+            "mac" => LineEnding::Cr,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    /// whether the last line ends with a line ending, like vim's ``'endofline'``
+    pub final_newline: bool,
+}
+
+impl Default for FileFormat {
+    /// the format of new files
+    fn default() -> Self {
+        FileFormat {
+            encoding: Encoding::default(),
+            line_ending: LineEnding::default(),
+            final_newline: true,
+        }
+    }
+}
+
+impl FileFormat {
+    /// whether this is the format of new files, which isn't worth mentioning
+    pub fn is_default(&self) -> bool {
+        *self == FileFormat::default()
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+/// decodes the content of a file and returns the encoding it was in. Never fails, since anything
+/// can be read as Latin-1
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    let utf16 = if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        decode_utf16(rest, u16::from_le_bytes).map(|text| (text, Encoding::Utf16Le))
+    } else if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        decode_utf16(rest, u16::from_be_bytes).map(|text| (text, Encoding::Utf16Be))
+    } else {
+        None
+    };
+    if let Some(ret) = utf16 {
+        return ret;
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        if let Ok(text) = std::str::from_utf8(rest) {
+            return (text.to_string(), Encoding::Utf8Bom);
+        }
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), Encoding::Utf8),
+        Err(_) => (
+            bytes.iter().map(|byte| *byte as char).collect(),
+            Encoding::Latin1,
+        ),
+    }
+}
+
+/// encodes ``text`` for writing it to a file. Fails, if a char can't be represented in
+/// ``encoding``
+pub fn encode(text: &str, encoding: Encoding) -> Result<Vec<u8>, String> {
+    Ok(match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        Encoding::Utf16Le => UTF16LE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        Encoding::Utf16Be => UTF16BE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+        Encoding::Latin1 => text
+            .chars()
+            .map(|chr| u8::try_from(chr).map_err(|_| format!("{chr} can't be written as latin1")))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// the line ending of ``text``, decided like vim does: dos if every LF follows a CR, mac if
+/// there are CRs but no LF at all, unix otherwise
+fn detect_line_ending(text: &str) -> LineEnding {
+    let lf = text.matches('\n').count();
+    if lf == 0 {
+        return match text.contains('\r') {
+            true => LineEnding::Cr,
+            false => LineEnding::Lf,
+        };
+    }
+    match text.matches("\r\n").count() == lf {
+        true => LineEnding::CrLf,
+        false => LineEnding::Lf,
+    }
+}
+
+/// splits ``text`` into lines at its line ending. Returns them with the line ending and whether
+/// the text ends with one. Other line break chars stay in the lines, like vim shows a stray CR as
+/// ``^M``, so writing the lines again gives back exactly ``text``
+pub fn split_lines(text: &str) -> (Vec<String>, LineEnding, bool) {
+    let line_ending = detect_line_ending(text);
+    // an empty file lacks nothing
+    if text.is_empty() {
+        return (Vec::new(), line_ending, true);
+    }
+    let ending = line_ending.as_str();
+    let (text, final_newline) = match text.strip_suffix(ending) {
+        Some(text) => (text, true),
+        None => (text, false),
+    };
+    let lines = text.split(ending).map(|line| line.to_string()).collect();
+    (lines, line_ending, final_newline)
+}
+
+/// the text of a file with ``lines`` in ``format``, before encoding it
+pub fn join_lines(lines: &[String], format: &FileFormat) -> String {
+    let ending = format.line_ending.as_str();
+    let mut text = lines.join(ending);
+    if format.final_newline && !lines.is_empty() {
+        text.push_str(ending);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_encodings() {
+        assert_eq!(decode(b"caf\xC3\xA9"), ("café".to_string(), Encoding::Utf8));
+        assert_eq!(
+            decode(b"\xEF\xBB\xBFcaf\xC3\xA9"),
+            ("café".to_string(), Encoding::Utf8Bom)
+        );
+        assert_eq!(decode(b"caf\xE9"), ("café".to_string(), Encoding::Latin1));
+        assert_eq!(
+            decode(b"\xFF\xFEh\x00\xE9\x00"),
+            ("hé".to_string(), Encoding::Utf16Le)
+        );
+        assert_eq!(
+            decode(b"\xFE\xFF\x00h\x00\xE9"),
+            ("hé".to_string(), Encoding::Utf16Be)
+        );
+        // an odd number of bytes is no UTF-16
+        assert_eq!(decode(b"\xFF\xFEh").1, Encoding::Latin1);
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Latin1,
+        ] {
+            let bytes = encode("café\r\n", encoding).unwrap();
+            assert_eq!(decode(&bytes), ("café\r\n".to_string(), encoding));
+        }
+        assert!(encode("€", Encoding::Latin1).is_err());
+    }
+
+    #[test]
+    fn detects_line_endings() {
+        assert_eq!(
+            split_lines("a\r\nb\r\n"),
+            (lines(&["a", "b"]), LineEnding::CrLf, true)
+        );
+        assert_eq!(
+            split_lines("a\rb"),
+            (lines(&["a", "b"]), LineEnding::Cr, false)
+        );
+        assert_eq!(
+            split_lines("a\rb\nc\n"),
+            (lines(&["a\rb", "c"]), LineEnding::Lf, true)
+        );
+        // mixed: only LF is a line break, the CR stays in the line
+        assert_eq!(
+            split_lines("a\n\nb\r\n"),
+            (lines(&["a", "", "b\r"]), LineEnding::Lf, true)
+        );
+        assert_eq!(
+            split_lines("a\rb\r\n"),
+            (lines(&["a\rb"]), LineEnding::CrLf, true)
+        );
+        assert_eq!(split_lines("a"), (lines(&["a"]), LineEnding::Lf, false));
+        assert_eq!(split_lines(""), (Vec::new(), LineEnding::Lf, true));
+    }
+
+    #[test]
+    fn join_keeps_the_format() {
+        for text in [
+            "a\r\nb\r\n",
+            "a\nb",
+            "a\rb\r",
+            "\n",
+            "a\rb\nc\n",
+            "a\r\nb\n\r",
+        ] {
+            let (lines, line_ending, final_newline) = split_lines(text);
+            let format = FileFormat {
+                encoding: Encoding::Utf8,
+                line_ending,
+                final_newline,
+            };
+            assert_eq!(join_lines(&lines, &format), text);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! # History
+//! Everything run from the command line (and searched for), oldest first. ``<Up>`` and
+//! ``<Down>`` on the command line go through the entries, that start with what was already typed.
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+/// older entries are forgotten
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HistoryKind {
+    Command,
+    Search,
+}
+
+impl HistoryKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoryKind::Command => "cmd",
+            HistoryKind::Search => "search",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cmd" => Some(HistoryKind::Command),
+            "search" => Some(HistoryKind::Search),
+            _ => None,
+        }
+    }
+}
+
+static HISTORY: Lazy<Mutex<HashMap<HistoryKind, Vec<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// adds ``text`` as the newest entry, an equal older one is removed
+fn push(entries: &mut Vec<String>, text: String) {
+    entries.retain(|entry| *entry != text);
+    entries.push(text);
+    if entries.len() > MAX_ENTRIES {
+        entries.remove(0);
+    }
+}
+
+pub async fn add(kind: HistoryKind, text: String) {
+    if text.is_empty() {
+        return;
+    }
+    push(HISTORY.lock().await.entry(kind).or_default(), text);
+}
+
+pub async fn entries(kind: HistoryKind) -> Vec<String> {
+    HISTORY.lock().await.get(&kind).cloned().unwrap_or_default()
+}
+
+/// the index of the next entry starting with ``prefix``, going ``older`` or newer from ``from``
+/// (``None`` being behind the newest entry)
+pub fn step(entries: &[String], from: Option<usize>, prefix: &str, older: bool) -> Option<usize> {
+    let matches = |idx: &usize| entries[*idx].starts_with(prefix);
+    let from = from.unwrap_or(entries.len());
+    if older {
+        (0..from).rev().find(matches)
+    } else {
+        (from + 1..entries.len()).find(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_move_to_the_end() {
+        let mut entries = Vec::new();
+        for text in ["split", "set nu", "split"] {
+            push(&mut entries, text.to_string());
+        }
+        assert_eq!(entries, vec!["set nu", "split"]);
+    }
+
+    #[test]
+    fn step_through_matching_entries() {
+        let entries: Vec<String> = ["set nu", "split", "set cb=osc52", "vsplit"]
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        assert_eq!(step(&entries, None, "se", true), Some(2));
+        assert_eq!(step(&entries, Some(2), "se", true), Some(0));
+        assert_eq!(step(&entries, Some(0), "se", true), None);
+        assert_eq!(step(&entries, Some(0), "se", false), Some(2));
+        assert_eq!(step(&entries, Some(2), "se", false), None);
+        assert_eq!(step(&entries, None, "", false), None);
+    }
+}
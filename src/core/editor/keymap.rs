@@ -0,0 +1,1856 @@
+//! # Keymaps
+//! Maps key sequences, written in vim notation (``"<C-w>h"``), to actions, separately for every
+//! mode. Every key event goes through ``feed_key``, no matter if it was typed by the user or
+//! replayed by the editor, so both behave exactly the same.
+//! A count typed in front of a sequence (``3<C-w>+``) is handed to the action, just like a
+//! register picked with ``"x``.
+//! Operators (``d``, ``c``, ``y``) switch to operator-pending mode, where a motion or a text
+//! object, optionally with a count of its own (``d3w``), tells them which text to work on.
+//! Macros (``q``) record the keys typed by the user into a register, ``@`` feeds them back
+//! through ``feed_key``.
+//! Actions wrapped with ``repeatable`` are changes: their keys (together with everything typed in
+//! visual or insert mode along the way) are recorded and replayed by ``.``.
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use once_cell::sync::Lazy;
+use tokio::sync::{Mutex, RwLock};
+
+use super::commands;
+use super::history::{self, HistoryKind};
+use super::marks;
+use super::mode::{self, Mode};
+use super::motions::{
+    char_class, BeginningWordMotion, CharSearch, EndWordMotion, FirstNonBlankMotion,
+    GotoLineMotion, LeftRightMotion, LineEndMotion, LineStartMotion, MatchingBracketMotion, Motion,
+    MotionDirection, ParagraphMotion, ScreenLine, ScreenMotion, SentenceMotion, UntilWithMotion,
+    UntilWithoutMotion, UpDownMotion,
+};
+use super::prompt;
+use super::registers::{self, Operation, Register, RegisterKind};
+use super::text_objects::{self, ObjectScope, TextRange, OBJECT_KEYS};
+use super::visual::{self, BlockInsert, Operator};
+use super::CursorPosition;
+use crate::core::event_handling::EventCallback;
+use crate::core::input::{self, InputEvent};
+use crate::core::logger::{self, LogLevel};
+use crate::core::render::manager::{self, ContentRef, Direction, SplitDirection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ActionArgs {
+    pub count: Option<u32>,
+    pub register: Option<char>,
+}
+
+impl ActionArgs {
+    /// the count, defaulting to 1 if none was typed
+    pub fn count1(&self) -> u32 {
+        self.count.unwrap_or(1)
+    }
+}
+
+pub type ActionFunctionType = Arc<
+    Box<
+        dyn Fn(ActionArgs) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>>
+            + Send
+            + Sync,
+    >,
+>;
+
+/// wraps an async fn into the action type
+pub fn action<F, Fut>(f: F) -> ActionFunctionType
+where
+    F: Fn(ActionArgs) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    Arc::new(Box::new(move |args| Box::pin(f(args))))
+}
+
+type Keymap = Vec<(Vec<KeyEvent>, ActionFunctionType)>;
+static KEYMAPS: Lazy<RwLock<HashMap<Mode, Keymap>>> = Lazy::new(|| RwLock::new(default_keymaps()));
+
+#[derive(Default)]
+struct KeyState {
+    pending: Vec<KeyEvent>,
+    count: Option<u32>,
+    /// a ``"`` was typed, the next key names the register
+    awaiting_register: bool,
+    register: Option<char>,
+    cmdline: String,
+    /// the history entry shown on the command line and what was typed before going through them
+    history_idx: Option<usize>,
+    history_prefix: String,
+    /// text typed since insert mode was entered, for the ``.`` register
+    inserted: String,
+    /// repeats the inserted text on more lines, when insert mode is left
+    block_insert: Option<BlockInsert>,
+    /// a command, that needs the char typed next (e.g. ``f``)
+    pending_char: Option<(CharCommand, ActionArgs)>,
+    /// repeated by ``;`` and ``,``
+    last_find: Option<Find>,
+    /// the keys of the command, that is currently typed
+    keys: Vec<KeyEvent>,
+    /// the current command is a change, set by ``repeatable`` actions
+    is_change: bool,
+    /// the keys of the last complete change, replayed by ``.``
+    last_change: Vec<KeyEvent>,
+    /// the register a macro is recorded into and the keys typed so far
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// the register ``@@`` plays
+    last_macro: Option<char>,
+    /// the operator, that waits for its motion in operator-pending mode
+    operator: Option<PendingOperator>,
+}
+
+/// how an operator treats the text a motion moves over
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum MotionKind {
+    /// up to the char in front of the new cursor position (``w``)
+    #[default]
+    Exclusive,
+    /// including the char at the new cursor position (``e``, ``f``)
+    Inclusive,
+    /// the whole lines from the old to the new cursor position (``j``, ``G``)
+    Linewise,
+}
+
+/// an operator (``d``, ``c``, ``y``), that waits for its motion or text object
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PendingOperator {
+    op: Operator,
+    /// the count and register typed in front of it, the count multiplied with the one of the motion
+    /// once that is typed
+    args: ActionArgs,
+    /// where the motion starts
+    start: CursorPosition,
+    /// how many of the keys of the command were typed up to the operator
+    keys_len: usize,
+    /// set by the motions, that aren't exclusive
+    kind: MotionKind,
+    /// what text objects and doubled operators (``dd``) apply to, instead of a motion
+    range: Option<TextRange>,
+}
+
+/// commands, that are completed by the char typed after them
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharCommand {
+    /// ``f``, ``t``, ``F`` and ``T``
+    Find {
+        direction: MotionDirection,
+        till: bool,
+    },
+    /// ``q``
+    Record,
+    /// ``@``
+    Play,
+    /// ``m``
+    SetMark,
+    /// ``'`` or `` ` ``
+    JumpToMark { exact: bool },
+}
+
+/// how deep macros may call other macros (or themselves), before they are stopped
+const MAX_MACRO_DEPTH: usize = 100;
+/// how many macros, ``.`` or command lines are playing back keys at the moment. Only the keys fed
+/// while none does are recorded into a macro
+static MACRO_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// a char search started with ``f``, ``t``, ``F`` or ``T``
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Find {
+    chr: char,
+    direction: MotionDirection,
+    /// ``t``/``T``, which stop in front of the char
+    till: bool,
+}
+
+/// whether ``f``, ``t``, ... continue searching on the next lines (``:set multilinefind``)
+static MULTILINE_FIND: AtomicBool = AtomicBool::new(false);
+
+pub fn set_multiline_find(enabled: bool) {
+    MULTILINE_FIND.store(enabled, Ordering::Relaxed);
+}
+static STATE: Lazy<Mutex<KeyState>> = Lazy::new(|| Mutex::new(KeyState::default()));
+
+/// strips everything from a key event, that isn't relevant for matching it against a keymap
+pub fn normalize(key: KeyEvent) -> KeyEvent {
+    let mut modifiers = key.modifiers;
+    if let KeyCode::Char(_) = key.code {
+        // the case of the char already tells if shift was pressed
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    KeyEvent::new(key.code, modifiers)
+}
+
+fn parse_special(name: &str) -> Option<KeyCode> {
+    let code = match name.to_ascii_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "bar" => KeyCode::Char('|'),
+        "del" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('f') | Some('F'), Some(_)) => KeyCode::F(name[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(code)
+}
+
+/// writes keys in the notation ``parse_keys`` understands, keys without a name are left out
+pub fn keys_to_notation(keys: &[KeyEvent]) -> String {
+    let mut ret = String::new();
+    for key in keys.iter().map(|key| normalize(*key)) {
+        let name = match key.code {
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char(c) if key.modifiers.is_empty() => {
+                ret.push(c);
+                continue;
+            }
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Enter => "CR".to_string(),
+            KeyCode::Backspace => "BS".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::Up => "Up".to_string(),
+            KeyCode::Down => "Down".to_string(),
+            KeyCode::Left => "Left".to_string(),
+            KeyCode::Right => "Right".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            _ => continue,
+        };
+        ret.push('<');
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if key.modifiers.contains(modifier) {
+                ret.push_str(prefix);
+            }
+        }
+        ret.push_str(&name);
+        ret.push('>');
+    }
+    ret
+}
+
+/// writes a single char in the notation ``parse_keys`` understands
+fn key_notation(key: char) -> String {
+    match key {
+        '<' => "<lt>".to_string(),
+        key => key.to_string(),
+    }
+}
+
+/// parses keys in vim notation, e.g. ``"<C-w>h"`` or ``"<lt>"``
+pub fn parse_keys(notation: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut ret = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>').filter(|end| *end > 1) {
+                let mut name = &rest[1..end];
+                let mut modifiers = KeyModifiers::empty();
+                while name.len() > 2 && name.as_bytes()[1] == b'-' {
+                    modifiers |= match name.as_bytes()[0].to_ascii_lowercase() {
+                        b'c' => KeyModifiers::CONTROL,
+                        b's' => KeyModifiers::SHIFT,
+                        b'a' | b'm' => KeyModifiers::ALT,
+                        _ => return Err(format!("unknown modifier in {}", &rest[..=end])),
+                    };
+                    name = &name[2..];
+                }
+                let code =
+                    parse_special(name).ok_or_else(|| format!("unknown key {}", &rest[..=end]))?;
+                ret.push(normalize(KeyEvent::new(code, modifiers)));
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        ret.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty()));
+        rest = &rest[c.len_utf8()..];
+    }
+    Ok(ret)
+}
+
+/// maps ``keys`` (vim notation) in ``mode`` to ``action``, replacing an existing mapping
+pub async fn map(mode: Mode, keys: &str, action: ActionFunctionType) -> Result<(), String> {
+    let keys = parse_keys(keys)?;
+    let mut keymaps = KEYMAPS.write().await;
+    let keymap = keymaps.entry(mode).or_default();
+    keymap.retain(|(seq, _)| *seq != keys);
+    keymap.push((keys, action));
+    Ok(())
+}
+
+pub async fn unmap(mode: Mode, keys: &str) -> Result<(), String> {
+    let keys = parse_keys(keys)?;
+    match KEYMAPS.write().await.get_mut(&mode) {
+        Some(keymap) => keymap.retain(|(seq, _)| *seq != keys),
+        None => return Err("no such mapping".to_string()),
+    }
+    Ok(())
+}
+
+/// the text typed on the command line, if the editor is in command line mode
+pub async fn cmdline() -> Option<String> {
+    if mode::current().await != Mode::CommandLine {
+        return None;
+    }
+    Some(STATE.lock().await.cmdline.clone())
+}
+
+enum Lookup {
+    Found(ActionFunctionType),
+    Pending,
+    NotFound,
+}
+
+fn lookup(keymap: &Keymap, pending: &[KeyEvent]) -> Lookup {
+    // a sequence, that is the start of a longer mapping, waits for the rest of it
+    if keymap
+        .iter()
+        .any(|(seq, _)| seq.len() > pending.len() && seq.starts_with(pending))
+    {
+        return Lookup::Pending;
+    }
+    match keymap.iter().find(|(seq, _)| seq == pending) {
+        Some((_, action)) => Lookup::Found(Arc::clone(action)),
+        None => Lookup::NotFound,
+    }
+}
+
+async fn feed_cmdline_key(key: KeyEvent) -> Result<(), String> {
+    let mut state = STATE.lock().await;
+    if !matches!(key.code, KeyCode::Up | KeyCode::Down) {
+        state.history_idx = None;
+    }
+    match key.code {
+        KeyCode::Esc => {
+            state.cmdline.clear();
+            mode::set(Mode::Normal).await;
+        }
+        KeyCode::Enter => {
+            let cmdline = std::mem::take(&mut state.cmdline);
+            mode::set(Mode::Normal).await;
+            drop(state);
+            registers::set_last_cmdline(cmdline.clone()).await;
+            history::add(HistoryKind::Command, cmdline.clone()).await;
+            // the keys of ``:normal`` are already recorded as the command line
+            return played_back(commands::execute(&cmdline)).await;
+        }
+        KeyCode::Up | KeyCode::Down => {
+            let entries = history::entries(HistoryKind::Command).await;
+            if state.history_idx.is_none() {
+                state.history_prefix = state.cmdline.clone();
+            }
+            let older = key.code == KeyCode::Up;
+            match history::step(&entries, state.history_idx, &state.history_prefix, older) {
+                Some(idx) => {
+                    state.history_idx = Some(idx);
+                    state.cmdline = entries[idx].clone();
+                }
+                // going past the newest entry shows what was typed again
+                None if !older => {
+                    state.history_idx = None;
+                    state.cmdline = state.history_prefix.clone();
+                }
+                None => {}
+            }
+        }
+        KeyCode::Backspace => {
+            if state.cmdline.pop().is_none() {
+                mode::set(Mode::Normal).await;
+            }
+        }
+        KeyCode::Char(c) => state.cmdline.push(c),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// processes a single key event, as if it was typed by the user
+pub async fn feed_key(key: KeyEvent) -> Result<(), String> {
+    let key = normalize(key);
+    if MACRO_DEPTH.load(Ordering::Relaxed) == 0 {
+        record_key(key).await;
+    }
+    if prompt::feed_key(key).await? {
+        return Ok(());
+    }
+    let mode = mode::current().await;
+    if mode == Mode::CommandLine {
+        return feed_cmdline_key(key).await;
+    }
+    let mut unmapped = Vec::new();
+    let found = {
+        let mut state = STATE.lock().await;
+        state.keys.push(key);
+        if let Some((command, args)) = state.pending_char.take() {
+            drop(state);
+            // anything but a char cancels the command
+            let res = match key.code {
+                KeyCode::Char(chr) => char_command(command, chr, args).await,
+                _ => {
+                    cancel_operator().await;
+                    Ok(())
+                }
+            };
+            let res = match mode {
+                Mode::OperatorPending => finish_motion(res).await,
+                _ => res,
+            };
+            finish_command().await;
+            return res;
+        }
+        // the register goes in front of the operator
+        if !matches!(mode, Mode::Insert | Mode::OperatorPending)
+            && state.pending.is_empty()
+            && key.modifiers.is_empty()
+        {
+            if state.awaiting_register {
+                state.awaiting_register = false;
+                match key.code {
+                    KeyCode::Char(c) if registers::is_valid(c) => state.register = Some(c),
+                    _ => state.register = None,
+                }
+                return Ok(());
+            }
+            if key.code == KeyCode::Char('"') {
+                state.awaiting_register = true;
+                return Ok(());
+            }
+        }
+        if let KeyCode::Char(c @ '0'..='9') = key.code {
+            // in insert mode, digits are text
+            if mode != Mode::Insert
+                && state.pending.is_empty()
+                && key.modifiers.is_empty()
+                && (c != '0' || state.count.is_some())
+            {
+                let digit = c.to_digit(10).unwrap();
+                state.count = Some(state.count.unwrap_or(0).saturating_mul(10) + digit);
+                return Ok(());
+            }
+        }
+        state.pending.push(key);
+        let keymaps = KEYMAPS.read().await;
+        let res = match keymaps.get(&mode) {
+            Some(keymap) => lookup(keymap, &state.pending),
+            None => Lookup::NotFound,
+        };
+        match res {
+            Lookup::Pending => return Ok(()),
+            Lookup::Found(action) => {
+                let mut args = ActionArgs {
+                    count: state.count.take(),
+                    register: state.register.take(),
+                };
+                // ``2d3w`` deletes 6 words
+                if let Some(pending) = state.operator.as_mut() {
+                    args.count = match (pending.args.count, args.count) {
+                        (Some(a), Some(b)) => Some(a.saturating_mul(b)),
+                        (a, b) => a.or(b),
+                    };
+                    pending.args.count = args.count;
+                }
+                state.pending.clear();
+                Some((action, args))
+            }
+            Lookup::NotFound => {
+                // keys, that turned out not to be a mapping, are typed in insert mode
+                if mode == Mode::Insert {
+                    unmapped = std::mem::take(&mut state.pending);
+                } else {
+                    state.pending.clear();
+                }
+                state.count = None;
+                state.register = None;
+                // e.g. ``d<Esc>``
+                if state.operator.take().is_some() {
+                    mode::set(Mode::Normal).await;
+                }
+                None
+            }
+        }
+    };
+    // the lock is released here, actions are allowed to feed keys themselves
+    let res = match found {
+        Some((action, args)) if mode == Mode::OperatorPending => {
+            let res = (action)(args).await;
+            finish_motion(res).await
+        }
+        Some((action, args)) => (action)(args).await,
+        None => {
+            for key in unmapped {
+                insert_key(key).await?;
+            }
+            Ok(())
+        }
+    };
+    finish_command().await;
+    res
+}
+
+/// called after every complete command. A change is remembered for ``.``, unless it goes on in
+/// visual, insert or operator-pending mode
+async fn finish_command() {
+    let mode = mode::current().await;
+    if matches!(mode, Mode::Insert | Mode::OperatorPending) || mode.is_visual() {
+        return;
+    }
+    let mut state = STATE.lock().await;
+    if std::mem::take(&mut state.is_change) {
+        state.last_change = std::mem::take(&mut state.keys);
+    } else {
+        state.keys.clear();
+    }
+}
+
+/// marks an action as a change, that ``.`` repeats. Plugins wrap their mappings with it to make
+/// them repeatable as well
+pub fn repeatable(action: ActionFunctionType) -> ActionFunctionType {
+    Arc::new(Box::new(move |args| {
+        let action = Arc::clone(&action);
+        Box::pin(async move {
+            let res = (action)(args).await;
+            STATE.lock().await.is_change = true;
+            res
+        })
+    }))
+}
+
+/// records the cursor position in the jump list, before ``action`` moves it away. Plugins wrap
+/// their own jumps with it, so ``<C-o>`` comes back
+pub fn jump(action: ActionFunctionType) -> ActionFunctionType {
+    Arc::new(Box::new(move |args| {
+        let action = Arc::clone(&action);
+        Box::pin(async move {
+            marks::push_jump().await?;
+            (action)(args).await
+        })
+    }))
+}
+
+/// tells the operator, that waits for ``action``, to include the char the motion stops on
+/// (``de``) or whole lines (``dj``). Motions are exclusive otherwise
+fn motion_kind(kind: MotionKind, action: ActionFunctionType) -> ActionFunctionType {
+    Arc::new(Box::new(move |args| {
+        let action = Arc::clone(&action);
+        Box::pin(async move {
+            if let Some(pending) = STATE.lock().await.operator.as_mut() {
+                pending.kind = kind;
+            }
+            (action)(args).await
+        })
+    }))
+}
+
+/// puts ``count`` in front of a recorded command instead of the count it was typed with
+fn replace_count(keys: &[KeyEvent], count: u32) -> Vec<KeyEvent> {
+    let mut register = Vec::new();
+    let mut i = 0;
+    // the count and the register can be typed in any order, e.g. ``"a3p`` or ``3"ap``
+    while i < keys.len() {
+        match keys[i].code {
+            KeyCode::Char('0'..='9') => i += 1,
+            KeyCode::Char('"') if i + 1 < keys.len() => {
+                register.extend_from_slice(&keys[i..i + 2]);
+                i += 2;
+            }
+            _ => break,
+        }
+    }
+    count
+        .to_string()
+        .chars()
+        .map(|c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty()))
+        .chain(register)
+        .chain(keys[i..].iter().copied())
+        .collect()
+}
+
+/// replays the last change (``.``). A count replaces the one of the change
+async fn repeat_change(args: ActionArgs) -> Result<(), String> {
+    let keys = {
+        let mut state = STATE.lock().await;
+        // the ``.`` itself isn't part of the change, the replayed keys are recorded instead
+        state.keys.clear();
+        state.last_change.clone()
+    };
+    let keys = match args.count {
+        Some(count) => replace_count(&keys, count),
+        None => keys,
+    };
+    played_back(async {
+        for key in keys {
+            feed_key(key).await?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// what typing ``key`` in insert mode inserts
+fn typed_text(key: KeyEvent) -> Option<String> {
+    if key
+        .modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char(c) => Some(c.to_string()),
+        KeyCode::Enter => Some("\n".to_string()),
+        KeyCode::Tab => Some("\t".to_string()),
+        _ => None,
+    }
+}
+
+/// types a key, that isn't mapped in insert mode
+async fn insert_key(key: KeyEvent) -> Result<(), String> {
+    if key.code == KeyCode::Backspace && key.modifiers.is_empty() {
+        STATE.lock().await.inserted.pop();
+        return manager::edit_focused(|doc, cursor| {
+            let start = if cursor.x > 0 {
+                CursorPosition {
+                    x: cursor.x - 1,
+                    y: cursor.y,
+                }
+            } else if cursor.y > 0 {
+                // joins the line with the previous one
+                let y = cursor.y - 1;
+                let len = doc.line(y as usize).map_or(0, |line| line.chars().count());
+                CursorPosition { x: len as u32, y }
+            } else {
+                return cursor;
+            };
+            doc.remove(start, cursor);
+            start
+        })
+        .await;
+    }
+    match typed_text(key) {
+        Some(text) => {
+            STATE.lock().await.inserted.push_str(&text);
+            manager::edit_focused(|doc, cursor| doc.insert_str(cursor, &text)).await
+        }
+        None => Ok(()),
+    }
+}
+
+/// switches to insert mode. ``append`` starts inserting after the cursor (``a``) instead of
+/// before it (``i``)
+async fn start_insert(append: bool) -> Result<(), String> {
+    let pos = {
+        let focused = manager::focused().await?;
+        let buf = focused.deref().await;
+        let cursor = buf.cursor_position();
+        let len = buf
+            .document()
+            .read()
+            .unwrap()
+            .line(cursor.y as usize)
+            .map_or(0, |line| line.chars().count());
+        CursorPosition {
+            x: if append {
+                std::cmp::min(cursor.x as usize + 1, len) as u32
+            } else {
+                cursor.x
+            },
+            y: cursor.y,
+        }
+    };
+    start_insert_at(pos, None).await
+}
+
+/// switches to insert mode with the cursor at ``pos``. With a ``block``, the typed text is
+/// repeated on its lines afterwards (``I`` and ``A`` in visual block mode)
+pub(crate) async fn start_insert_at(
+    pos: CursorPosition,
+    block: Option<BlockInsert>,
+) -> Result<(), String> {
+    {
+        let mut state = STATE.lock().await;
+        state.inserted.clear();
+        state.block_insert = block;
+    }
+    mode::set(Mode::Insert).await;
+    manager::edit_focused(|doc, cursor| {
+        // everything typed until insert mode is left is undone at once
+        doc.begin_undo_step(cursor);
+        pos
+    })
+    .await
+}
+
+async fn stop_insert() -> Result<(), String> {
+    let (inserted, block) = {
+        let mut state = STATE.lock().await;
+        (
+            std::mem::take(&mut state.inserted),
+            state.block_insert.take(),
+        )
+    };
+    mode::set(Mode::Normal).await;
+    manager::edit_focused(|doc, cursor| {
+        if let Some(block) = block {
+            visual::replicate(doc, &block, &inserted);
+        }
+        doc.end_undo_step();
+        doc.set_mark('^', cursor);
+        // like in vim, the cursor moves back onto the last inserted char
+        CursorPosition {
+            x: cursor.x.saturating_sub(1),
+            y: cursor.y,
+        }
+    })
+    .await?;
+    registers::set_last_inserted(inserted).await;
+    Ok(())
+}
+
+/// undoes (or redoes) ``count`` steps
+async fn undo(redo: bool, count: u32) -> Result<(), String> {
+    manager::edit_focused(|doc, mut cursor| {
+        for _ in 0..count {
+            match if redo { doc.redo() } else { doc.undo() } {
+                Some(pos) => cursor = pos,
+                None => break,
+            }
+        }
+        cursor
+    })
+    .await
+}
+
+/// puts the register picked in ``args`` after (``p``) or before (``P``) the cursor
+async fn put(args: ActionArgs, after: bool) -> Result<(), String> {
+    let reg = registers::get(args.register)
+        .await
+        .ok_or_else(|| format!("register {} is empty", args.register.unwrap_or('"')))?;
+    let count = args.count1();
+    manager::edit_focused(|doc, cursor| {
+        // a put is undone at once, no matter how many lines it touched
+        doc.begin_undo_step(cursor);
+        let cursor = registers::put(doc, cursor, &reg, after, count);
+        doc.end_undo_step();
+        cursor
+    })
+    .await
+}
+
+/// deletes ``count`` chars under and after the cursor (``x``)
+async fn delete_chars(args: ActionArgs) -> Result<(), String> {
+    let mut removed = String::new();
+    manager::edit_focused(|doc, cursor| {
+        let len = doc
+            .line(cursor.y as usize)
+            .map_or(0, |line| line.chars().count()) as u32;
+        let end = CursorPosition {
+            x: std::cmp::min(cursor.x + args.count1(), len),
+            y: cursor.y,
+        };
+        removed = doc.remove(cursor, end);
+        CursorPosition {
+            x: cursor
+                .x
+                .min(len.saturating_sub(removed.chars().count() as u32 + 1)),
+            y: cursor.y,
+        }
+    })
+    .await?;
+    if removed.is_empty() {
+        return Ok(());
+    }
+    let reg = Register::new(removed, RegisterKind::Charwise);
+    registers::store(args.register, Operation::Delete, reg).await
+}
+
+/// ``d``, ``c`` and ``y`` wait for a motion or text object in operator-pending mode
+async fn start_operator(args: ActionArgs, op: Operator) -> Result<(), String> {
+    let start = manager::focused().await?.deref().await.cursor_position();
+    {
+        let mut state = STATE.lock().await;
+        let keys_len = state.keys.len();
+        state.operator = Some(PendingOperator {
+            op,
+            args,
+            start,
+            keys_len,
+            kind: MotionKind::default(),
+            range: None,
+        });
+    }
+    mode::set(Mode::OperatorPending).await;
+    Ok(())
+}
+
+/// drops the operator, that waits for a motion, e.g. after a failed ``dfx``
+async fn cancel_operator() {
+    if STATE.lock().await.operator.take().is_some() {
+        mode::set(Mode::Normal).await;
+    }
+}
+
+/// sets the text the operator applies to, instead of a motion. ``None`` cancels it (``di(``
+/// outside of parentheses)
+async fn set_operand(range: Option<TextRange>) {
+    let mut state = STATE.lock().await;
+    match (range, state.operator.as_mut()) {
+        (Some(range), Some(pending)) => pending.range = Some(range),
+        _ => state.operator = None,
+    }
+}
+
+/// the ``count`` lines starting at the cursor line
+async fn line_range(count: u32) -> Result<TextRange, String> {
+    let focused = manager::focused().await?;
+    let buf = focused.deref().await;
+    let y = buf.cursor_position().y;
+    let doc = buf.document();
+    let doc = doc.read().unwrap();
+    let last = (y + count.max(1) - 1).min(doc.line_count().saturating_sub(1) as u32);
+    let len = doc
+        .line(last as usize)
+        .map_or(0, |line| line.chars().count() as u32);
+    Ok(TextRange {
+        start: CursorPosition { x: 0, y },
+        end: CursorPosition { x: len, y: last },
+        linewise: true,
+    })
+}
+
+/// an operator typed twice (``dd``, ``cc``, ``yy``) applies to ``count`` lines
+async fn operator_lines(args: ActionArgs, op: Operator) -> Result<(), String> {
+    let doubled = STATE
+        .lock()
+        .await
+        .operator
+        .is_some_and(|pending| pending.op == op);
+    let range = match doubled {
+        true => Some(line_range(args.count1()).await?),
+        false => None,
+    };
+    set_operand(range).await;
+    Ok(())
+}
+
+/// ``w`` after an operator. Like in vim, a word at the end of a line ends the text there instead
+/// of at the start of the next line, and ``cw`` on a word changes only up to its end, like ``ce``
+async fn word_operand(args: ActionArgs) -> Result<(), String> {
+    let change = STATE
+        .lock()
+        .await
+        .operator
+        .is_some_and(|pending| pending.op == Operator::Change);
+    let range = {
+        let focused = manager::focused().await?;
+        let buf = focused.deref().await;
+        let start = buf.cursor_position();
+        let line = |y: u32| -> Vec<char> {
+            buf.content()
+                .get(y as usize)
+                .map_or(Vec::new(), |line| line.chars().collect())
+        };
+        let class = |pos: CursorPosition, x: u32| {
+            line(pos.y).get(x as usize).map(|c| char_class(*c, false))
+        };
+        let on_word = class(start, start.x).is_some_and(|class| class != 0);
+        let end = if change && on_word {
+            // a word ending under the cursor is the first one
+            let mut end = start;
+            for i in 0..args.count1() {
+                if i > 0 || class(end, end.x + 1) == class(end, end.x) {
+                    end =
+                        EndWordMotion.get_new_cursor_position(&buf, &end, MotionDirection::Foward);
+                }
+            }
+            CursorPosition {
+                x: end.x + 1,
+                y: end.y,
+            }
+        } else {
+            let (mut prev, mut end) = (start, start);
+            for _ in 0..args.count1() {
+                prev = end;
+                end = BeginningWordMotion.get_new_cursor_position(
+                    &buf,
+                    &end,
+                    MotionDirection::Foward,
+                );
+            }
+            let word_start = line(end.y).is_empty()
+                || (class(end, end.x) != Some(0)
+                    && (end.x == 0 || class(end, end.x - 1) != class(end, end.x)));
+            if end.y > prev.y {
+                CursorPosition {
+                    x: line(prev.y).len() as u32,
+                    y: prev.y,
+                }
+            } else if !word_start {
+                // there is no next word, the text goes to the end of the document
+                CursorPosition {
+                    x: line(end.y).len() as u32,
+                    y: end.y,
+                }
+            } else {
+                end
+            }
+        };
+        (end != start).then_some(TextRange {
+            start,
+            end,
+            linewise: false,
+        })
+    };
+    set_operand(range).await;
+    Ok(())
+}
+
+/// the text the cursor moved over since the operator was typed, as ``kind`` demands
+async fn motion_range(
+    start: CursorPosition,
+    kind: MotionKind,
+) -> Result<Option<TextRange>, String> {
+    let focused = manager::focused().await?;
+    let buf = focused.deref().await;
+    let (start, end) = visual::ordered(start, buf.cursor_position());
+    let doc = buf.document();
+    let doc = doc.read().unwrap();
+    let len = |y: u32| {
+        doc.line(y as usize)
+            .map_or(0, |line| line.chars().count() as u32)
+    };
+    let lines = |first: u32, last: u32| TextRange {
+        start: CursorPosition { x: 0, y: first },
+        end: CursorPosition {
+            x: len(last),
+            y: last,
+        },
+        linewise: true,
+    };
+    let chars = |end: CursorPosition| TextRange {
+        start,
+        end,
+        linewise: false,
+    };
+    Ok(Some(match kind {
+        MotionKind::Linewise => lines(start.y, end.y),
+        MotionKind::Inclusive => chars(CursorPosition {
+            x: (end.x + 1).min(len(end.y)),
+            y: end.y,
+        }),
+        MotionKind::Exclusive if start == end => return Ok(None),
+        // like in vim, a motion to the start of a later line doesn't take the line break in
+        // front of it, and the whole lines if it started in front of the text of its line (``d}``)
+        MotionKind::Exclusive if end.x == 0 && end.y > start.y => {
+            if start.x <= registers::first_non_blank(&doc, start.y).x {
+                lines(start.y, end.y - 1)
+            } else {
+                chars(CursorPosition {
+                    x: len(end.y - 1),
+                    y: end.y - 1,
+                })
+            }
+        }
+        MotionKind::Exclusive => chars(end),
+    }))
+}
+
+/// called after every command in operator-pending mode with its result: applies the operator
+/// once its motion or text object is complete
+async fn finish_motion(res: Result<(), String>) -> Result<(), String> {
+    if res.is_err() {
+        cancel_operator().await;
+        return res;
+    }
+    let pending = {
+        let mut state = STATE.lock().await;
+        // ``df`` waits for the char
+        if state.pending_char.is_some() {
+            return Ok(());
+        }
+        state.operator.take()
+    };
+    mode::set(Mode::Normal).await;
+    let Some(pending) = pending else {
+        return Ok(());
+    };
+    let range = match pending.range {
+        Some(range) => Some(range),
+        None => motion_range(pending.start, pending.kind).await?,
+    };
+    let Some(range) = range else {
+        return Ok(());
+    };
+    text_objects::apply(pending.args, pending.op, range).await?;
+    if pending.op == Operator::Yank {
+        return Ok(());
+    }
+    let mut state = STATE.lock().await;
+    state.is_change = true;
+    // the count typed after the operator goes in front of it, so a count given to ``.`` replaces
+    // all of it
+    let motion = state.keys.split_off(pending.keys_len);
+    let digits = match motion.first().map(|key| key.code) {
+        Some(KeyCode::Char('1'..='9')) => motion
+            .iter()
+            .take_while(|key| matches!(key.code, KeyCode::Char('0'..='9')))
+            .count(),
+        _ => 0,
+    };
+    if let (true, Some(count)) = (digits > 0, pending.args.count) {
+        state.keys = replace_count(&state.keys, count);
+    }
+    state.keys.extend_from_slice(&motion[digits..]);
+    Ok(())
+}
+
+/// subscribes to the input events, so typed keys reach ``feed_key``
+pub async fn init() {
+    input::subscribe(EventCallback::new(
+        Arc::new(Box::new(|evt: Arc<Mutex<input::EvtData>>| {
+            Box::pin(async move {
+                let key = match evt.lock().await.0 {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                    _ => return,
+                };
+                if let Err(msg) = feed_key(key).await {
+                    logger::log(LogLevel::Error, msg.as_str()).await;
+                }
+                // mode, cursor position or the command line might have changed
+                if let Err(msg) = manager::redraw_statusline().await {
+                    logger::log(LogLevel::Error, msg.as_str()).await;
+                }
+            })
+        })),
+        true,
+        InputEvent(Event::Key(KeyEvent::new(
+            KeyCode::Char(' '), // doesn't matter which char goes here
+            KeyModifiers::empty(),
+        ))),
+    ))
+    .await;
+}
+
+async fn move_cursor(
+    motion: impl Motion,
+    direction: MotionDirection,
+    count: u32,
+) -> Result<(), String> {
+    let scrolled = {
+        let focused = manager::focused().await?;
+        let mut buf = focused.deref().await;
+        let mut pos = buf.cursor_position();
+        for _ in 0..count {
+            pos = motion.get_new_cursor_position(&buf, &pos, direction);
+        }
+        let scroll = buf.scroll();
+        buf.set_cursor_pos(pos);
+        scroll != buf.scroll()
+    };
+    // the selection follows the cursor
+    if scrolled || mode::current().await.is_visual() {
+        manager::rerender().await?;
+    }
+    Ok(())
+}
+
+/// searches for the char of ``find`` ``count`` times. ``repeat`` is set for ``;`` and ``,``.
+/// If it isn't found that often, the cursor stays and an operator waiting for it is cancelled
+async fn find_char(find: Find, count: u32, repeat: bool) -> Result<(), String> {
+    let search = CharSearch {
+        chr: find.chr,
+        multiline: MULTILINE_FIND.load(Ordering::Relaxed),
+        // ``t`` would find the char right in front of it again
+        skip_adjacent: repeat || count > 1,
+    };
+    let found = {
+        let focused = manager::focused().await?;
+        let buf = focused.deref().await;
+        let mut pos = buf.cursor_position();
+        (0..count).all(|_| {
+            let next = UntilWithMotion(search).get_new_cursor_position(&buf, &pos, find.direction);
+            std::mem::replace(&mut pos, next) != next
+        })
+    };
+    if !found {
+        cancel_operator().await;
+        return Ok(());
+    }
+    if find.till {
+        move_cursor(UntilWithoutMotion(search), find.direction, count).await
+    } else {
+        move_cursor(UntilWithMotion(search), find.direction, count).await
+    }
+}
+
+/// waits for the char ``command`` needs
+fn wait_for_char(command: CharCommand) -> ActionFunctionType {
+    action(move |args| async move {
+        STATE.lock().await.pending_char = Some((command, args));
+        Ok(())
+    })
+}
+
+async fn char_command(command: CharCommand, chr: char, args: ActionArgs) -> Result<(), String> {
+    match command {
+        CharCommand::Find { direction, till } => {
+            let find = Find {
+                chr,
+                direction,
+                till,
+            };
+            STATE.lock().await.last_find = Some(find);
+            find_char(find, args.count1(), false).await
+        }
+        CharCommand::Record => {
+            if !(chr.is_ascii_alphanumeric() || chr == '"') {
+                return Err(format!("can't record into register {chr}"));
+            }
+            STATE.lock().await.recording = Some((chr, Vec::new()));
+            Ok(())
+        }
+        CharCommand::Play => play_macro(chr, args.count1()).await,
+        CharCommand::SetMark => marks::set(chr).await,
+        CharCommand::JumpToMark { exact } => marks::jump_to(chr, exact).await,
+    }
+}
+
+/// adds text, that got into the document in insert mode without being typed (a paste), to what
+/// was inserted. This way ``.``, the ``.`` register and a recorded macro include it
+pub(crate) async fn record_inserted(text: &str) {
+    let keys = text.chars().map(|chr| match chr {
+        '\n' => KeyEvent::new(KeyCode::Enter, KeyModifiers::empty()),
+        chr => KeyEvent::new(KeyCode::Char(chr), KeyModifiers::empty()),
+    });
+    let mut state = STATE.lock().await;
+    state.inserted.push_str(text);
+    state.keys.extend(keys.clone());
+    if let Some((_, recorded)) = state.recording.as_mut() {
+        recorded.extend(keys);
+    }
+}
+
+/// the register a macro is recorded into, if one is recorded
+pub async fn recording() -> Option<char> {
+    STATE.lock().await.recording.as_ref().map(|(reg, _)| *reg)
+}
+
+/// adds a key, that wasn't played back, to the macro, that is recorded
+async fn record_key(key: KeyEvent) {
+    if let Some((_, keys)) = STATE.lock().await.recording.as_mut() {
+        keys.push(key);
+    }
+}
+
+/// runs ``f``, which plays back keys. Those aren't recorded into a macro, only the keys, that
+/// started the playback (``@a``, ``.``)
+async fn played_back<T>(f: impl Future<Output = T>) -> T {
+    MACRO_DEPTH.fetch_add(1, Ordering::Relaxed);
+    let res = f.await;
+    MACRO_DEPTH.fetch_sub(1, Ordering::Relaxed);
+    res
+}
+
+/// ``q`` stops recording, or starts it once the register is typed
+async fn toggle_recording(args: ActionArgs) -> Result<(), String> {
+    let recorded = STATE.lock().await.recording.take();
+    let Some((reg, mut keys)) = recorded else {
+        return (wait_for_char(CharCommand::Record))(args).await;
+    };
+    // the ``q``, that stopped the recording
+    if keys.last() == Some(&KeyEvent::new(KeyCode::Char('q'), KeyModifiers::empty())) {
+        keys.pop();
+    }
+    let text = keys_to_notation(&keys);
+    registers::store(
+        Some(reg),
+        Operation::Yank,
+        Register::new(text, RegisterKind::Charwise),
+    )
+    .await
+}
+
+/// feeds the keys stored in register ``reg`` ``count`` times. ``@`` plays the last macro again,
+/// ``:`` the last command line
+async fn play_macro(reg: char, count: u32) -> Result<(), String> {
+    let reg = match reg {
+        '@' => STATE
+            .lock()
+            .await
+            .last_macro
+            .ok_or("no previously used register")?,
+        reg => reg,
+    };
+    STATE.lock().await.last_macro = Some(reg);
+    let text = registers::get(Some(reg))
+        .await
+        .ok_or_else(|| format!("register {reg} is empty"))?
+        .text;
+    if reg == ':' {
+        for _ in 0..count {
+            commands::execute(&text).await?;
+        }
+        return Ok(());
+    }
+    let keys = parse_keys(&text.replace('\n', "<CR>"))?;
+    if MACRO_DEPTH.load(Ordering::Relaxed) >= MAX_MACRO_DEPTH {
+        return Err("macro calls itself too often".to_string());
+    }
+    // played on a task of its own, so a macro playing macros doesn't grow the stack
+    tokio::spawn(play_keys(keys, count))
+        .await
+        .map_err(|err| format!("playing register {reg} failed: {err}"))?
+}
+
+/// feeds ``keys`` ``count`` times, until one fails. The future is boxed, since it's spawned from
+/// within ``feed_key`` itself
+fn play_keys(
+    keys: Vec<KeyEvent>,
+    count: u32,
+) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> {
+    Box::pin(played_back(async move {
+        for _ in 0..count {
+            for key in keys.iter() {
+                // like in vim, a failing command stops the macro
+                feed_key(*key).await?;
+            }
+        }
+        Ok(())
+    }))
+}
+
+/// whether typed keys wait for the rest of a command, e.g. an operator for its motion
+async fn unfinished() -> bool {
+    let state = STATE.lock().await;
+    !state.pending.is_empty()
+        || state.pending_char.is_some()
+        || state.operator.is_some()
+        || state.awaiting_register
+        || state.count.is_some()
+        || state.register.is_some()
+}
+
+/// types ``keys`` (written like the keys of a mapping, e.g. ``ihello<Esc>``) in normal mode, like
+/// vim's ``:normal``. A command, that is left unfinished (insert mode, an operator without a
+/// motion, ...), is ended like with ``<Esc>``
+pub async fn normal(keys: &str) -> Result<(), String> {
+    let keys = parse_keys(keys)?;
+    if mode::current().await != Mode::Normal {
+        return Err("only possible in normal mode".to_string());
+    }
+    let mut res = Ok(());
+    for key in keys {
+        // boxed, because the keys may run ``:normal`` again
+        res = Box::pin(feed_key(key)).await;
+        if res.is_err() {
+            break;
+        }
+    }
+    if mode::current().await != Mode::Normal || unfinished().await {
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::empty());
+        Box::pin(feed_key(esc)).await?;
+    }
+    res
+}
+
+/// repeats the last char search, in the opposite direction for ``,``
+async fn repeat_find(reverse: bool, count: u32) -> Result<(), String> {
+    let Some(mut find) = STATE.lock().await.last_find else {
+        return Ok(());
+    };
+    if reverse {
+        find.direction = match find.direction {
+            MotionDirection::Foward => MotionDirection::Backward,
+            MotionDirection::Backward => MotionDirection::Foward,
+        };
+    }
+    find_char(find, count, true).await
+}
+
+/// the motions, that are mapped in normal and visual mode
+fn motions() -> Vec<(&'static str, ActionFunctionType)> {
+    use MotionDirection::{Backward, Foward};
+    let inclusive = |action| motion_kind(MotionKind::Inclusive, action);
+    let linewise = |action| motion_kind(MotionKind::Linewise, action);
+    let screen = |line| {
+        linewise(jump(action(move |_| {
+            move_cursor(ScreenMotion(line), Foward, 1)
+        })))
+    };
+    let find = |direction, till| inclusive(wait_for_char(CharCommand::Find { direction, till }));
+    vec![
+        (
+            "h",
+            action(|args| move_cursor(LeftRightMotion, Backward, args.count1())),
+        ),
+        (
+            "l",
+            action(|args| move_cursor(LeftRightMotion, Foward, args.count1())),
+        ),
+        (
+            "j",
+            linewise(action(|args| {
+                move_cursor(UpDownMotion, Foward, args.count1())
+            })),
+        ),
+        (
+            "k",
+            linewise(action(|args| {
+                move_cursor(UpDownMotion, Backward, args.count1())
+            })),
+        ),
+        (
+            "w",
+            action(|args| move_cursor(BeginningWordMotion, Foward, args.count1())),
+        ),
+        (
+            "b",
+            action(|args| move_cursor(BeginningWordMotion, Backward, args.count1())),
+        ),
+        (
+            "e",
+            inclusive(action(|args| {
+                move_cursor(EndWordMotion, Foward, args.count1())
+            })),
+        ),
+        (
+            "ge",
+            inclusive(action(|args| {
+                move_cursor(EndWordMotion, Backward, args.count1())
+            })),
+        ),
+        ("0", action(|_| move_cursor(LineStartMotion, Backward, 1))),
+        (
+            "^",
+            action(|_| move_cursor(FirstNonBlankMotion, Backward, 1)),
+        ),
+        (
+            "$",
+            inclusive(action(|args| async move {
+                // a count goes down to the end of a later line
+                move_cursor(UpDownMotion, Foward, args.count1() - 1).await?;
+                move_cursor(LineEndMotion, Foward, 1).await
+            })),
+        ),
+        (
+            "gg",
+            linewise(jump(action(|args| {
+                move_cursor(GotoLineMotion(args.count), Backward, 1)
+            }))),
+        ),
+        (
+            "G",
+            linewise(jump(action(|args| {
+                move_cursor(GotoLineMotion(args.count), Foward, 1)
+            }))),
+        ),
+        (
+            "}",
+            jump(action(|args| {
+                move_cursor(ParagraphMotion, Foward, args.count1())
+            })),
+        ),
+        (
+            "{",
+            jump(action(|args| {
+                move_cursor(ParagraphMotion, Backward, args.count1())
+            })),
+        ),
+        (
+            ")",
+            jump(action(|args| {
+                move_cursor(SentenceMotion, Foward, args.count1())
+            })),
+        ),
+        (
+            "(",
+            jump(action(|args| {
+                move_cursor(SentenceMotion, Backward, args.count1())
+            })),
+        ),
+        (
+            "%",
+            inclusive(jump(action(|_| {
+                move_cursor(MatchingBracketMotion, Foward, 1)
+            }))),
+        ),
+        ("f", find(Foward, false)),
+        ("F", find(Backward, false)),
+        ("t", find(Foward, true)),
+        ("T", find(Backward, true)),
+        (
+            ";",
+            inclusive(action(|args| repeat_find(false, args.count1()))),
+        ),
+        (
+            ",",
+            inclusive(action(|args| repeat_find(true, args.count1()))),
+        ),
+        ("H", screen(ScreenLine::Top)),
+        ("M", screen(ScreenLine::Middle)),
+        ("L", screen(ScreenLine::Bottom)),
+        (
+            "'",
+            linewise(wait_for_char(CharCommand::JumpToMark { exact: false })),
+        ),
+        ("`", wait_for_char(CharCommand::JumpToMark { exact: true })),
+    ]
+}
+
+/// ``make(key, scope)`` for all text objects, mapped to ``i``/``a`` followed by their key
+fn text_object_maps(
+    make: impl Fn(char, ObjectScope) -> ActionFunctionType,
+) -> Vec<(String, ActionFunctionType)> {
+    let mut maps = Vec::new();
+    for (scope_key, scope) in [("i", ObjectScope::Inner), ("a", ObjectScope::Around)] {
+        for key in OBJECT_KEYS.chars() {
+            maps.push((
+                format!("{scope_key}{}", key_notation(key)),
+                make(key, scope),
+            ));
+        }
+    }
+    maps
+}
+
+fn default_keymaps() -> HashMap<Mode, Keymap> {
+    let mut normal = motions();
+    normal.extend([
+        (
+            "gt",
+            action(|args| async move {
+                match args.count {
+                    Some(number) => manager::goto_tab(number).await,
+                    None => manager::cycle_tab(1).await,
+                }
+            }),
+        ),
+        (
+            "gT",
+            action(|args| manager::cycle_tab(-(args.count1() as i64))),
+        ),
+        ("i", repeatable(action(|_| start_insert(false)))),
+        ("a", repeatable(action(|_| start_insert(true)))),
+        ("u", action(|args| undo(false, args.count1()))),
+        ("p", repeatable(action(|args| put(args, true)))),
+        ("P", repeatable(action(|args| put(args, false)))),
+        ("d", action(|args| start_operator(args, Operator::Delete))),
+        ("c", action(|args| start_operator(args, Operator::Change))),
+        ("y", action(|args| start_operator(args, Operator::Yank))),
+        (
+            "Y",
+            action(|args| async move {
+                let range = line_range(args.count1()).await?;
+                text_objects::apply(args, Operator::Yank, range).await
+            }),
+        ),
+        ("m", wait_for_char(CharCommand::SetMark)),
+        (".", action(repeat_change)),
+        ("q", action(toggle_recording)),
+        ("@", wait_for_char(CharCommand::Play)),
+        ("x", repeatable(action(delete_chars))),
+        ("v", action(|_| visual::toggle(RegisterKind::Charwise))),
+        ("V", action(|_| visual::toggle(RegisterKind::Linewise))),
+        ("<C-v>", action(|_| visual::toggle(RegisterKind::Blockwise))),
+        ("<C-r>", action(|args| undo(true, args.count1()))),
+        ("<C-o>", action(|args| marks::walk(args.count1(), false))),
+        // terminals send <C-i> as <Tab>
+        ("<Tab>", action(|args| marks::walk(args.count1(), true))),
+        ("<C-i>", action(|args| marks::walk(args.count1(), true))),
+        (
+            ":",
+            action(|_| async {
+                STATE.lock().await.cmdline.clear();
+                mode::set(Mode::CommandLine).await;
+                Ok(())
+            }),
+        ),
+    ]);
+
+    // window commands, all of them are prefixed with <C-w>
+    let mut window: Vec<(&str, ActionFunctionType)> = Vec::new();
+    let split = |dir| action(move |_| manager::split_focused(dir));
+    let focus = |dir| action(move |args: ActionArgs| manager::focus_direction(dir, args.count1()));
+    let rotate = |sign: i32| {
+        action(move |args: ActionArgs| {
+            manager::update_focused_master(move |master, _| {
+                master.rotate(sign * args.count1() as i32);
+                Ok(())
+            })
+        })
+    };
+    let resize = |dir, sign: i16| {
+        action(move |args: ActionArgs| manager::resize_focused(dir, sign * args.count1() as i16))
+    };
+    for (keys, dir) in [
+        ("s", SplitDirection::Horizontal),
+        ("S", SplitDirection::Horizontal),
+        ("<C-s>", SplitDirection::Horizontal),
+        ("v", SplitDirection::Vertical),
+        ("<C-v>", SplitDirection::Vertical),
+    ] {
+        window.push((keys, split(dir)));
+    }
+    for (keys, dir) in [
+        ("h", Direction::Left),
+        ("<Left>", Direction::Left),
+        ("<C-h>", Direction::Left),
+        ("j", Direction::Down),
+        ("<Down>", Direction::Down),
+        ("<C-j>", Direction::Down),
+        ("k", Direction::Up),
+        ("<Up>", Direction::Up),
+        ("<C-k>", Direction::Up),
+        ("l", Direction::Right),
+        ("<Right>", Direction::Right),
+        ("<C-l>", Direction::Right),
+    ] {
+        window.push((keys, focus(dir)));
+    }
+    window.extend([
+        ("+", resize(SplitDirection::Horizontal, 1)),
+        ("-", resize(SplitDirection::Horizontal, -1)),
+        (">", resize(SplitDirection::Vertical, 1)),
+        ("<lt>", resize(SplitDirection::Vertical, -1)),
+        ("=", action(|_| manager::equalize_focused())),
+        ("c", action(|_| manager::close_focused())),
+        ("q", action(|_| manager::close_focused())),
+        ("x", action(|args| manager::swap_focused(args.count1()))),
+        ("z", action(|_| manager::toggle_zoom())),
+        // only for MasterLayouts
+        ("r", rotate(1)),
+        ("<C-r>", rotate(1)),
+        ("R", rotate(-1)),
+        (
+            "m",
+            action(|_| manager::update_focused_master(|master, id| master.change_master(id))),
+        ),
+    ]);
+    let normal = normal
+        .into_iter()
+        .map(|(keys, action)| (keys.to_string(), action))
+        .chain(
+            window
+                .into_iter()
+                .map(|(keys, action)| (format!("<C-w>{keys}"), action)),
+        )
+        .map(|(keys, action)| {
+            (
+                parse_keys(&keys).expect("BUG: invalid default mapping"),
+                action,
+            )
+        })
+        .collect();
+
+    let mut visual = motions();
+    visual.extend([
+        ("v", action(|_| visual::toggle(RegisterKind::Charwise))),
+        ("V", action(|_| visual::toggle(RegisterKind::Linewise))),
+        ("<C-v>", action(|_| visual::toggle(RegisterKind::Blockwise))),
+        ("o", action(|_| visual::swap_ends())),
+        ("m", wait_for_char(CharCommand::SetMark)),
+        ("y", action(|args| visual::operate(args, Operator::Yank))),
+        (
+            "d",
+            repeatable(action(|args| visual::operate(args, Operator::Delete))),
+        ),
+        (
+            "x",
+            repeatable(action(|args| visual::operate(args, Operator::Delete))),
+        ),
+        (
+            "c",
+            repeatable(action(|args| visual::operate(args, Operator::Change))),
+        ),
+        (
+            "s",
+            repeatable(action(|args| visual::operate(args, Operator::Change))),
+        ),
+        ("<Esc>", action(|_| visual::leave())),
+    ]);
+    let visual: Keymap = visual
+        .into_iter()
+        .map(|(keys, action)| (keys.to_string(), action))
+        .chain(text_object_maps(|key, scope| {
+            action(move |args| text_objects::select(key, scope, args.count1()))
+        }))
+        .map(|(keys, action)| {
+            (
+                parse_keys(&keys).expect("BUG: invalid default mapping"),
+                action,
+            )
+        })
+        .collect();
+    // only a block has a left and right edge on every line
+    let mut visual_block = visual.clone();
+    visual_block.extend([
+        (
+            parse_keys("I").expect("BUG: invalid default mapping"),
+            repeatable(action(|_| visual::block_insert(false))),
+        ),
+        (
+            parse_keys("A").expect("BUG: invalid default mapping"),
+            repeatable(action(|_| visual::block_insert(true))),
+        ),
+    ]);
+
+    // the motions and text objects, that complete an operator
+    let mut operator_pending = motions();
+    operator_pending.retain(|(keys, _)| *keys != "w");
+    operator_pending.extend([
+        ("w", action(word_operand)),
+        ("d", action(|args| operator_lines(args, Operator::Delete))),
+        ("c", action(|args| operator_lines(args, Operator::Change))),
+        ("y", action(|args| operator_lines(args, Operator::Yank))),
+    ]);
+    let operator_pending: Keymap = operator_pending
+        .into_iter()
+        .map(|(keys, action)| (keys.to_string(), action))
+        .chain(text_object_maps(|key, scope| {
+            action(move |args| async move {
+                let range = text_objects::focused_range(key, scope, args.count1()).await?;
+                set_operand(range).await;
+                Ok(())
+            })
+        }))
+        .map(|(keys, action)| {
+            (
+                parse_keys(&keys).expect("BUG: invalid default mapping"),
+                action,
+            )
+        })
+        .collect();
+
+    let insert = vec![(
+        parse_keys("<Esc>").expect("BUG: invalid default mapping"),
+        action(|_| stop_insert()),
+    )];
+
+    let mut keymaps = HashMap::new();
+    keymaps.insert(Mode::Normal, normal);
+    keymaps.insert(Mode::Insert, insert);
+    keymaps.insert(Mode::Visual, visual.clone());
+    keymaps.insert(Mode::VisualLine, visual);
+    keymaps.insert(Mode::VisualBlock, visual_block);
+    keymaps.insert(Mode::OperatorPending, operator_pending);
+    keymaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::headless;
+
+    #[test]
+    fn parse_plain_and_special() {
+        assert_eq!(
+            parse_keys("<C-w>h<lt>").unwrap(),
+            vec![
+                KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                KeyEvent::new(KeyCode::Char('h'), KeyModifiers::empty()),
+                KeyEvent::new(KeyCode::Char('<'), KeyModifiers::empty()),
+            ]
+        );
+        assert_eq!(
+            parse_keys("<Esc><F12><C-S-Up>").unwrap(),
+            vec![
+                KeyEvent::new(KeyCode::Esc, KeyModifiers::empty()),
+                KeyEvent::new(KeyCode::F(12), KeyModifiers::empty()),
+                KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lone_angle_bracket() {
+        assert_eq!(
+            parse_keys("<>").unwrap(),
+            vec![
+                KeyEvent::new(KeyCode::Char('<'), KeyModifiers::empty()),
+                KeyEvent::new(KeyCode::Char('>'), KeyModifiers::empty()),
+            ]
+        );
+        assert!(parse_keys("<nope>").is_err());
+    }
+
+    #[test]
+    fn shift_is_ignored_for_chars() {
+        assert_eq!(
+            normalize(KeyEvent::new(KeyCode::Char('S'), KeyModifiers::SHIFT)),
+            parse_keys("S").unwrap()[0]
+        );
+    }
+
+    #[test]
+    fn typing() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(
+            typed_text(key(KeyCode::Char('x'), KeyModifiers::empty())),
+            Some("x".to_string())
+        );
+        assert_eq!(
+            typed_text(key(KeyCode::Enter, KeyModifiers::empty())),
+            Some("\n".to_string())
+        );
+        assert_eq!(
+            typed_text(key(KeyCode::Char('w'), KeyModifiers::CONTROL)),
+            None
+        );
+        assert_eq!(typed_text(key(KeyCode::Up, KeyModifiers::empty())), None);
+    }
+
+    #[test]
+    fn notation_round_trip() {
+        let keys = parse_keys("a <lt>x<C-w>h<Esc><CR><F5><A-x><S-Up>").unwrap();
+        let notation = keys_to_notation(&keys);
+        assert_eq!(notation, "a <lt>x<C-w>h<Esc><CR><F5><A-x><S-Up>");
+        assert_eq!(parse_keys(&notation).unwrap(), keys);
+    }
+
+    #[test]
+    fn dot_count_replaces_the_original() {
+        let keys = |notation| parse_keys(notation).unwrap();
+        assert_eq!(replace_count(&keys("3dd"), 12), keys("12dd"));
+        assert_eq!(replace_count(&keys("\"a2p"), 5), keys("5\"ap"));
+        assert_eq!(replace_count(&keys("ihi<Esc>"), 2), keys("2ihi<Esc>"));
+    }
+
+    #[test]
+    fn prefix_waits_for_more_keys() {
+        let keymaps = default_keymaps();
+        let normal = keymaps.get(&Mode::Normal).unwrap();
+        let ctrl_w = parse_keys("<C-w>").unwrap();
+        assert!(matches!(lookup(normal, &ctrl_w), Lookup::Pending));
+        let ctrl_w_v = parse_keys("<C-w>v").unwrap();
+        assert!(matches!(lookup(normal, &ctrl_w_v), Lookup::Found(..)));
+        let nothing = parse_keys("<C-w>y").unwrap();
+        assert!(matches!(lookup(normal, &nothing), Lookup::NotFound));
+    }
+
+    fn lines(doc: &crate::core::editor::document::DocumentRef) -> Vec<String> {
+        doc.read().unwrap().lines().clone()
+    }
+
+    #[tokio::test]
+    async fn dot_repeats_operator_and_motion() {
+        let (doc, _window) = headless::test_window(&["a b c d e f g h i"]).await;
+        normal("dw").await.unwrap();
+        assert_eq!(lines(&doc), vec!["b c d e f g h i"]);
+        normal(".").await.unwrap();
+        assert_eq!(lines(&doc), vec!["c d e f g h i"]);
+        normal("2dw").await.unwrap();
+        assert_eq!(lines(&doc), vec!["e f g h i"]);
+        normal(".").await.unwrap();
+        assert_eq!(lines(&doc), vec!["g h i"]);
+        // the count of ``.`` replaces the one typed after the operator as well
+        normal("d2w").await.unwrap();
+        assert_eq!(lines(&doc), vec!["i"]);
+        normal("u0").await.unwrap();
+        normal("1.").await.unwrap();
+        assert_eq!(lines(&doc), vec!["h i"]);
+    }
+
+    #[tokio::test]
+    async fn dot_repeats_a_changed_word() {
+        let (doc, _window) = headless::test_window(&["one two", "three four"]).await;
+        // ``cw`` keeps the white space after the word
+        normal("cwfoo<Esc>").await.unwrap();
+        assert_eq!(lines(&doc), vec!["foo two", "three four"]);
+        normal("j0.").await.unwrap();
+        assert_eq!(lines(&doc), vec!["foo two", "foo four"]);
+        assert_eq!(mode::current().await, Mode::Normal);
+    }
+
+    #[tokio::test]
+    async fn motion_kinds() {
+        let (doc, _window) = headless::test_window(&["a,b,c", "x", "y", "last word"]).await;
+        normal("df,").await.unwrap();
+        assert_eq!(lines(&doc), vec!["b,c", "x", "y", "last word"]);
+        // a char, that isn't there, cancels the operator
+        normal("dfz").await.unwrap();
+        assert_eq!(lines(&doc), vec!["b,c", "x", "y", "last word"]);
+        assert_eq!(mode::current().await, Mode::Normal);
+        normal(".").await.unwrap();
+        assert_eq!(lines(&doc), vec!["c", "x", "y", "last word"]);
+        normal("jdj").await.unwrap();
+        assert_eq!(lines(&doc), vec!["c", "last word"]);
+        // the last word of a line ends at the line end
+        normal("wdw").await.unwrap();
+        assert_eq!(lines(&doc), vec!["c", "last "]);
+        normal("ggyyjp").await.unwrap();
+        assert_eq!(lines(&doc), vec!["c", "last ", "c"]);
+    }
+
+    #[tokio::test]
+    async fn text_object_counts() {
+        let (doc, _window) = headless::test_window(&["a b c d e f g h"]).await;
+        normal("2daw").await.unwrap();
+        assert_eq!(lines(&doc), vec!["c d e f g h"]);
+        normal("d2aw").await.unwrap();
+        assert_eq!(lines(&doc), vec!["e f g h"]);
+        normal(".").await.unwrap();
+        assert_eq!(lines(&doc), vec!["g h"]);
+        normal("u03.").await.unwrap();
+        assert_eq!(lines(&doc), vec!["h"]);
+    }
+
+    async fn register_text(reg: char) -> String {
+        registers::get(Some(reg)).await.unwrap().text
+    }
+
+    #[tokio::test]
+    async fn record_and_play_macros() {
+        let (doc, _window) = headless::test_window(&["1", "2", "3", "4", "5", "6", "7"]).await;
+        normal("qa$a!<Esc>jq").await.unwrap();
+        assert_eq!(register_text('a').await, "$a!<Esc>j");
+        normal("@a").await.unwrap();
+        normal("3@a").await.unwrap();
+        normal("@@").await.unwrap();
+        assert_eq!(lines(&doc), vec!["1!", "2!", "3!", "4!", "5!", "6!", "7"]);
+        // the keys played back by ``@a`` aren't recorded a second time
+        normal("qb@aq").await.unwrap();
+        assert_eq!(register_text('b').await, "@a");
+        assert_eq!(lines(&doc)[6], "7!");
+    }
+
+    #[tokio::test]
+    async fn recursive_macro_is_stopped() {
+        let line = "x".repeat(MAX_MACRO_DEPTH + 50);
+        let (doc, _window) = headless::test_window(&[&line]).await;
+        let reg = Register::new("x@r", RegisterKind::Charwise);
+        registers::store(Some('r'), Operation::Yank, reg)
+            .await
+            .unwrap();
+        assert!(normal("@r").await.is_err());
+        assert_eq!(lines(&doc)[0].len(), 50);
+        assert_eq!(MACRO_DEPTH.load(Ordering::Relaxed), 0);
+    }
+}
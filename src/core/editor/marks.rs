@@ -0,0 +1,432 @@
+//! # Marks and the jump list
+//! Lowercase marks (``ma``) belong to a document, uppercase ones (``mA``) are global and take the
+//! cursor into their document. Both are stored in the ``Document``, so they move along when lines
+//! are inserted or deleted above them. When a document is closed, its marks are kept aside until
+//! its file is opened again. The special marks are set by the editor itself:
+//! - ``'`` (or `` ` ``) the position before the latest jump
+//! - ``.`` the last change
+//! - ``^`` where insert mode was left
+//! - ``[`` and ``]`` the first and last char of the last change or yank
+//! - ``<`` and ``>`` the start and end of the last visual selection
+//!
+//! Jumps (``G``, ``%``, ``'a``, ...) are recorded in the jump list, which is walked through with
+//! ``<C-o>`` and ``<C-i>``, switching the document of the focused window where needed.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+use super::document::{Document, DocumentId, DocumentRef};
+use super::registers;
+use super::CursorPosition;
+use crate::core::io;
+use crate::core::render::manager;
+
+/// older jumps are forgotten
+const MAX_JUMPS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Jump {
+    /// ``None`` for jumps read from the state file
+    doc: Option<DocumentId>,
+    /// opens the file again, if the document was closed in the meantime
+    path: Option<String>,
+    pos: CursorPosition,
+}
+
+impl Jump {
+    fn same_line(&self, other: &Jump) -> bool {
+        let same_doc = match (self.doc, other.doc) {
+            (Some(doc), Some(other)) if doc == other => true,
+            _ => self.path.is_some() && self.path == other.path,
+        };
+        same_doc && self.pos.y == other.pos.y
+    }
+}
+
+#[derive(Debug, Default)]
+struct JumpList {
+    entries: Vec<Jump>,
+    /// the entry ``<C-o>`` and ``<C-i>`` are at, ``entries.len()`` if they weren't used since the
+    /// last jump
+    idx: usize,
+}
+
+impl JumpList {
+    /// adds a jump at the end, replacing an older one on the same line
+    fn push(&mut self, jump: Jump) {
+        self.entries.retain(|other| !other.same_line(&jump));
+        self.entries.push(jump);
+        if self.entries.len() > MAX_JUMPS {
+            self.entries.remove(0);
+        }
+        self.idx = self.entries.len();
+    }
+
+    /// goes back ``count`` jumps. ``current`` is added first, so ``<C-i>`` can return to it
+    fn older(&mut self, current: Jump, count: usize) -> Option<Jump> {
+        if self.idx >= self.entries.len() {
+            self.push(current);
+            self.idx = self.entries.len() - 1;
+        }
+        self.idx = self.idx.checked_sub(count)?;
+        self.entries.get(self.idx).cloned()
+    }
+
+    fn newer(&mut self, count: usize) -> Option<Jump> {
+        let idx = self.idx + count;
+        let jump = self.entries.get(idx)?.clone();
+        self.idx = idx;
+        Some(jump)
+    }
+}
+
+type Marks = Vec<(char, CursorPosition)>;
+
+static JUMPS: Lazy<tokio::sync::Mutex<JumpList>> =
+    Lazy::new(|| tokio::sync::Mutex::new(JumpList::default()));
+// the marks use the std lock, since documents hand theirs over when they are dropped
+/// global marks, whose document isn't open: read from the state file or set in a closed document
+static FILE_MARKS: Lazy<Mutex<HashMap<char, (String, CursorPosition)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// the other marks of closed documents, by the absolute path of their file
+static CLOSED_MARKS: Lazy<Mutex<HashMap<String, Marks>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn is_valid(name: char) -> bool {
+    name.is_ascii_alphabetic() || "'`.^[]<>".contains(name)
+}
+
+/// the document and cursor of the focused window
+async fn current() -> Result<(DocumentRef, CursorPosition), String> {
+    let focused = manager::focused().await?;
+    let buf = focused.deref().await;
+    Ok((Arc::clone(buf.document()), buf.cursor_position()))
+}
+
+fn jump_in(doc: &DocumentRef, pos: CursorPosition) -> Jump {
+    let doc = doc.read().unwrap();
+    Jump {
+        doc: Some(doc.id()),
+        path: doc.path().map(|path| path.to_string()),
+        pos,
+    }
+}
+
+/// sets mark ``name`` at the cursor (``m``)
+pub async fn set(name: char) -> Result<(), String> {
+    if !is_valid(name) {
+        return Err(format!("invalid mark: {name}"));
+    }
+    let name = if name == '`' { '\'' } else { name };
+    let (doc, pos) = current().await?;
+    if name.is_ascii_uppercase() {
+        // a global mark is only in one document at a time
+        for other in Document::all() {
+            other.write().unwrap().remove_mark(name);
+        }
+        FILE_MARKS.lock().unwrap().remove(&name);
+    }
+    doc.write().unwrap().set_mark(name, pos);
+    Ok(())
+}
+
+/// the document mark ``name`` is in and its position
+fn find(name: char, doc: &DocumentRef) -> Option<(DocumentRef, CursorPosition)> {
+    if name.is_ascii_uppercase() {
+        return Document::all().into_iter().find_map(|doc| {
+            let pos = doc.read().unwrap().mark(name)?;
+            Some((doc, pos))
+        });
+    }
+    let pos = doc.read().unwrap().mark(name)?;
+    Some((Arc::clone(doc), pos))
+}
+
+/// jumps to mark ``name``, either to its exact position (`` ` ``) or to the first non-blank of
+/// its line (``'``)
+pub async fn jump_to(name: char, exact: bool) -> Result<(), String> {
+    let name = if name == '`' { '\'' } else { name };
+    let (doc, _) = current().await?;
+    // looked up before ``push_jump`` overwrites ``'``
+    let (target, pos) = match find(name, &doc) {
+        Some(found) => found,
+        None => {
+            let (path, pos) = FILE_MARKS
+                .lock()
+                .unwrap()
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| format!("mark not set: {name}"))?;
+            (open(&path).await?, pos)
+        }
+    };
+    push_jump().await?;
+    go(target, pos, exact).await
+}
+
+/// records the cursor position in the jump list and as ``'``, before jumping away
+pub async fn push_jump() -> Result<(), String> {
+    let (doc, pos) = current().await?;
+    doc.write().unwrap().set_mark('\'', pos);
+    JUMPS.lock().await.push(jump_in(&doc, pos));
+    Ok(())
+}
+
+/// goes ``count`` entries back (``<C-o>``) or forward (``<C-i>``) in the jump list
+pub async fn walk(count: u32, forward: bool) -> Result<(), String> {
+    let (doc, pos) = current().await?;
+    let jump = {
+        let mut jumps = JUMPS.lock().await;
+        if forward {
+            jumps.newer(count as usize)
+        } else {
+            jumps.older(jump_in(&doc, pos), count as usize)
+        }
+    };
+    // like in vim, nothing happens at the ends of the list
+    let Some(jump) = jump else {
+        return Ok(());
+    };
+    let target = document_for(&jump).await?;
+    go(target, jump.pos, true).await
+}
+
+/// finds the document of ``jump``, reading its file again if it was closed
+async fn document_for(jump: &Jump) -> Result<DocumentRef, String> {
+    if let Some(doc) = Document::all()
+        .into_iter()
+        .find(|doc| Some(doc.read().unwrap().id()) == jump.doc)
+    {
+        return Ok(doc);
+    }
+    open(jump.path.as_deref().ok_or("the document was closed")?).await
+}
+
+/// the document of ``path``, which is read if it isn't open
+async fn open(path: &str) -> Result<DocumentRef, String> {
+    io::open_document(path)
+        .await
+        .map(|(doc, _)| doc)
+        .map_err(|err| format!("can't open {path}: {err}"))
+}
+
+/// all global marks with the file they are in, for the state file
+pub async fn global_marks() -> Vec<(char, String, CursorPosition)> {
+    let mut ret: Vec<_> = FILE_MARKS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, (path, pos))| (*name, path.clone(), *pos))
+        .collect();
+    for doc in Document::all() {
+        let doc = doc.read().unwrap();
+        let Some(path) = doc.path() else {
+            continue;
+        };
+        ret.extend(
+            doc.marks()
+                .filter(|(name, _)| name.is_ascii_uppercase())
+                .map(|(name, pos)| (name, path.to_string(), pos)),
+        );
+    }
+    ret.sort_by_key(|(name, _, _)| *name);
+    ret
+}
+
+/// sets a global mark read from the state file. It's kept aside until its file is opened
+pub async fn restore_global(name: char, path: String, pos: CursorPosition) {
+    match Document::find_by_path(&path) {
+        Some(doc) => doc.write().unwrap().set_mark(name, pos),
+        None => {
+            FILE_MARKS.lock().unwrap().insert(name, (path, pos));
+        }
+    }
+}
+
+/// keeps the marks of a document, that is closed, until its file is opened again. Called when
+/// the document is dropped
+pub(super) fn keep_closed(path: &str, marks: impl Iterator<Item = (char, CursorPosition)>) {
+    let path = io::absolute_path(path);
+    let (global, local): (Vec<_>, Vec<_>) = marks
+        .filter(|(name, _)| name.is_ascii_alphabetic() || "\"^.".contains(*name))
+        .partition(|(name, _)| name.is_ascii_uppercase());
+    let mut file_marks = FILE_MARKS.lock().unwrap();
+    for (name, pos) in global {
+        file_marks.insert(name, (path.clone(), pos));
+    }
+    if !local.is_empty() {
+        CLOSED_MARKS.lock().unwrap().insert(path, local);
+    }
+}
+
+/// the marks of closed documents, that aren't global, with the absolute path of their file
+pub fn closed_marks() -> Vec<(String, char, CursorPosition)> {
+    let mut ret: Vec<_> = CLOSED_MARKS
+        .lock()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, marks)| marks.iter().map(|(name, pos)| (path.clone(), *name, *pos)))
+        .collect();
+    ret.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    ret
+}
+
+/// moves the marks kept aside for the file of ``doc`` into it, once it was opened
+pub async fn adopt_file_marks(doc: &DocumentRef) {
+    let Some(path) = doc.read().unwrap().path().map(|path| path.to_string()) else {
+        return;
+    };
+    let closed = CLOSED_MARKS
+        .lock()
+        .unwrap()
+        .remove(&io::absolute_path(&path));
+    let mut marks = FILE_MARKS.lock().unwrap();
+    let mut doc = doc.write().unwrap();
+    for (name, pos) in closed.into_iter().flatten() {
+        doc.set_mark(name, pos);
+    }
+    marks.retain(|name, (mark_path, pos)| {
+        if !io::same_file(mark_path, &path) {
+            return true;
+        }
+        doc.set_mark(*name, *pos);
+        false
+    });
+}
+
+/// the jump list, oldest first. Jumps in documents without a file are left out
+pub async fn jumps() -> Vec<(String, CursorPosition)> {
+    JUMPS
+        .lock()
+        .await
+        .entries
+        .iter()
+        .filter_map(|jump| Some((jump.path.clone()?, jump.pos)))
+        .collect()
+}
+
+/// adds a jump read from the state file
+pub async fn restore_jump(path: String, pos: CursorPosition) {
+    JUMPS.lock().await.push(Jump {
+        doc: None,
+        path: Some(path),
+        pos,
+    });
+}
+
+/// moves the cursor of the focused window to ``pos`` in ``doc``, which is shown in the window
+/// first if needed. The position is clamped, since the text might have changed since
+async fn go(doc: DocumentRef, pos: CursorPosition, exact: bool) -> Result<(), String> {
+    let pos = {
+        let doc = doc.read().unwrap();
+        let y = pos.y.min(doc.line_count().saturating_sub(1) as u32);
+        if exact {
+            let len = doc.line(y as usize).map_or(0, |line| line.chars().count()) as u32;
+            CursorPosition {
+                x: pos.x.min(len.saturating_sub(1)),
+                y,
+            }
+        } else {
+            registers::first_non_blank(&doc, y)
+        }
+    };
+    let (current, _) = current().await?;
+    if Arc::ptr_eq(&current, &doc) {
+        manager::edit_focused(|_, _| pos).await
+    } else {
+        manager::show_document(doc, pos).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::editor::keymap;
+    use crate::core::headless;
+
+    fn jump(doc: DocumentId, y: u32) -> Jump {
+        Jump {
+            doc: Some(doc),
+            path: None,
+            pos: CursorPosition { x: 0, y },
+        }
+    }
+
+    #[test]
+    fn jumps_on_the_same_line_are_replaced() {
+        let mut jumps = JumpList::default();
+        jumps.push(jump(0, 1));
+        jumps.push(jump(0, 5));
+        jumps.push(jump(1, 1));
+        jumps.push(jump(0, 1));
+        assert_eq!(jumps.entries, vec![jump(0, 5), jump(1, 1), jump(0, 1)]);
+        assert_eq!(jumps.idx, 3);
+    }
+
+    #[test]
+    fn walk_back_and_forth() {
+        let mut jumps = JumpList::default();
+        jumps.push(jump(0, 1));
+        jumps.push(jump(1, 2));
+        assert_eq!(jumps.older(jump(1, 9), 1), Some(jump(1, 2)));
+        assert_eq!(jumps.older(jump(1, 2), 1), Some(jump(0, 1)));
+        assert_eq!(jumps.older(jump(0, 1), 1), None);
+        assert_eq!(jumps.newer(2), Some(jump(1, 9)));
+        assert_eq!(jumps.newer(1), None);
+        // a new jump goes to the end again
+        jumps.older(jump(1, 9), 2);
+        jumps.push(jump(0, 7));
+        assert_eq!(jumps.older(jump(0, 3), 1), Some(jump(0, 7)));
+    }
+
+    #[test]
+    fn restored_jumps_match_by_path() {
+        let mut jumps = JumpList::default();
+        let restored = Jump {
+            doc: None,
+            path: Some("a.txt".to_string()),
+            pos: CursorPosition { x: 0, y: 4 },
+        };
+        jumps.push(restored.clone());
+        jumps.push(jump(0, 4));
+        jumps.push(Jump {
+            doc: Some(1),
+            pos: CursorPosition { x: 3, y: 4 },
+            ..restored
+        });
+        assert_eq!(jumps.entries.len(), 2);
+        assert_eq!(jumps.entries[0], jump(0, 4));
+    }
+
+    #[tokio::test]
+    async fn marks_survive_their_document() {
+        let (_, _window) = headless::test_window(&[""]).await;
+        let dir = std::env::temp_dir();
+        let path = |name: &str| {
+            let path = dir.join(format!("neoxide-marks-{name}-{}", std::process::id()));
+            path.to_str().unwrap().to_string()
+        };
+        let (a, b) = (path("a"), path("b"));
+        std::fs::write(&a, "one\ntwo\nthree\n").unwrap();
+        std::fs::write(&b, "other\n").unwrap();
+        let (doc, _) = io::open_document(&a).await.unwrap();
+        manager::show_document(doc, CursorPosition { x: 0, y: 0 })
+            .await
+            .unwrap();
+        keymap::normal("jmAjlma").await.unwrap();
+        let (doc, _) = io::open_document(&b).await.unwrap();
+        manager::show_document(doc, CursorPosition { x: 0, y: 0 })
+            .await
+            .unwrap();
+        // the window held the last reference
+        assert!(Document::find_by_path(&a).is_none());
+        keymap::normal("'A").await.unwrap();
+        let (doc, pos) = current().await.unwrap();
+        assert_eq!(doc.read().unwrap().path(), Some(a.as_str()));
+        assert_eq!(pos, CursorPosition { x: 0, y: 1 });
+        keymap::normal("`a").await.unwrap();
+        assert_eq!(current().await.unwrap().1, CursorPosition { x: 1, y: 2 });
+        std::fs::remove_file(&a).unwrap();
+        std::fs::remove_file(&b).unwrap();
+    }
+}
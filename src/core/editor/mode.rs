@@ -0,0 +1,58 @@
+//! The mode the editor is in. It decides which keymaps are active
+use once_cell::sync::Lazy;
+use tokio::sync::RwLock;
+
+use crate::core::logger::{self, LogLevel};
+use crate::core::render::cursor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Normal,
+    Insert,
+    CommandLine,
+    Visual,
+    VisualLine,
+    VisualBlock,
+    /// an operator (``d``, ``c``, ``y``) waits for its motion or text object
+    OperatorPending,
+}
+
+impl Mode {
+    /// the name shown to the user, e.g. in the status line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::CommandLine => "COMMAND",
+            Mode::Visual => "VISUAL",
+            Mode::VisualLine => "VISUAL LINE",
+            Mode::VisualBlock => "VISUAL BLOCK",
+            Mode::OperatorPending => "O-PENDING",
+        }
+    }
+
+    /// any of the three visual modes
+    pub fn is_visual(&self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine | Mode::VisualBlock)
+    }
+}
+
+static MODE: Lazy<RwLock<Mode>> = Lazy::new(|| RwLock::new(Mode::Normal));
+
+pub async fn current() -> Mode {
+    *MODE.read().await
+}
+
+pub async fn set(mode: Mode) {
+    *MODE.write().await = mode;
+    // there is no cursor to change, while the editor is not running in the terminal
+    if crossterm::terminal::is_raw_mode_enabled().unwrap_or(false) {
+        if let Err(err) = cursor::apply(mode).await {
+            logger::log(
+                LogLevel::Warning,
+                format!("Could not change the cursor shape: {}", err).as_str(),
+            )
+            .await;
+        }
+    }
+}
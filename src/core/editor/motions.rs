@@ -1,9 +1,9 @@
 use crate::core::{
     editor::CursorPosition,
-    render::manager::{BufferDims, ContentRef},
+    render::manager::{wrapped_rows, BufferDims, ContentRef},
 };
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MotionDirection {
     Foward,
     Backward,
@@ -22,8 +22,24 @@ pub struct LeftRightMotion; // h e.g.
 pub struct UpDownMotion; // k e.g.
 pub struct BeginningWordMotion; // w e.g.
 pub struct EndWordMotion; // e e.g.
-pub struct UntilWithMotion(char); // f e.g.
-pub struct UntilWithoutMotion(char); // t e.g.
+pub struct UntilWithMotion(pub CharSearch); // f e.g.
+pub struct UntilWithoutMotion(pub CharSearch); // t e.g.
+pub struct LineStartMotion; // 0 e.g.
+pub struct FirstNonBlankMotion; // ^ e.g.
+pub struct LineEndMotion; // $ e.g.
+pub struct GotoLineMotion(pub Option<u32>); // gg and G e.g., with the 1-based line number
+pub struct ParagraphMotion; // { and } e.g.
+pub struct SentenceMotion; // ( and ) e.g.
+pub struct MatchingBracketMotion; // % e.g.
+pub struct ScreenMotion(pub ScreenLine); // H e.g.
+
+/// the line of the window ``ScreenMotion`` goes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenLine {
+    Top,
+    Middle,
+    Bottom,
+}
 
 impl Motion for LeftRightMotion {
     fn get_new_cursor_position(
@@ -32,11 +48,10 @@ impl Motion for LeftRightMotion {
         cursor_position: &CursorPosition,
         direction: MotionDirection,
     ) -> CursorPosition {
-        let text_len = buf.get_text_len() as u32;
         let start_of_text: u32 = 0;
-        let line_len = std::cmp::min(get_line_len(buf, cursor_position.y as usize), text_len);
-        if (direction == MotionDirection::Foward && cursor_position.x == line_len as u32 - 1)
-            || (direction == MotionDirection::Backward && cursor_position.x == start_of_text.into())
+        let line_len = get_line_len(&buf, cursor_position.y as usize);
+        if (direction == MotionDirection::Foward && cursor_position.x >= line_len.saturating_sub(1))
+            || (direction == MotionDirection::Backward && cursor_position.x == start_of_text)
         {
             return CursorPosition {
                 x: cursor_position.x,
@@ -54,32 +69,21 @@ impl Motion for LeftRightMotion {
     }
 }
 
+// cursor positions are document positions, wrapping is only a concern of the renderer
 fn get_lines<T>(buf: &T) -> u32
 where
     T: BufferDims + ContentRef,
 {
-    let mut ret = 0;
-    for line in buf.content().iter() {
-        ret += line.len() as u32 / buf.get_text_len() as u32 + 1;
-    }
-    ret
+    buf.content().len() as u32
 }
-fn get_line_len(buf: impl BufferDims + ContentRef, y: usize) -> u32 {
-    let mut line_idx = 0;
-    let mut vec_idx = 0;
-    let mut start_idx = 0;
-    let content = buf.content();
-    let text_len = buf.get_text_len() as usize - 2; // no idea why -2 is there
-    while line_idx < y {
-        if content[vec_idx].len() - start_idx <= text_len {
-            start_idx = 0;
-            vec_idx += 1;
-        } else {
-            start_idx += text_len;
-        }
-        line_idx += 1;
-    }
-    (content[vec_idx].len() - start_idx) as u32
+fn get_line_len<T>(buf: &T, y: usize) -> u32
+where
+    T: BufferDims + ContentRef,
+{
+    buf.content()
+        .get(y)
+        .map(|line| line.chars().count() as u32)
+        .unwrap_or(0)
 }
 
 impl Motion for UpDownMotion {
@@ -90,8 +94,7 @@ impl Motion for UpDownMotion {
         direction: MotionDirection,
     ) -> CursorPosition {
         let len = get_lines(&buf);
-        if (direction == MotionDirection::Foward
-            && cursor_position.y == std::cmp::min(buf.height(), len as u16) as u32 - 1)
+        if (direction == MotionDirection::Foward && cursor_position.y >= len.saturating_sub(1))
             || (direction == MotionDirection::Backward && cursor_position.y == 0)
         {
             return CursorPosition {
@@ -104,10 +107,10 @@ impl Motion for UpDownMotion {
                 MotionDirection::Foward => 1,
                 MotionDirection::Backward => -1,
             }) as usize;
-        let new_line_len: u32 = get_line_len(buf, new_y); //buf.content().get(new_y).unwrap().len() as u32;
-        if new_line_len - 1 <= cursor_position.x {
+        let new_line_len: u32 = get_line_len(&buf, new_y);
+        if new_line_len.saturating_sub(1) <= cursor_position.x {
             CursorPosition {
-                x: new_line_len - 1,
+                x: new_line_len.saturating_sub(1),
                 y: new_y as u32,
             }
         } else {
@@ -119,40 +122,80 @@ impl Motion for UpDownMotion {
     }
 }
 
+/// the char ``f``, ``t``, ``F`` and ``T`` look for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharSearch {
+    pub chr: char,
+    /// continues on the following (or previous) lines, if the char isn't on the cursor's line
+    pub multiline: bool,
+    /// makes ``t``/``T`` skip a match right next to the cursor, so repeating them with ``;``
+    /// doesn't get stuck in front of it
+    pub skip_adjacent: bool,
+}
+
+impl CharSearch {
+    /// a search within the cursor's line, like vim does it
+    pub fn new(chr: char) -> Self {
+        CharSearch {
+            chr,
+            multiline: false,
+            skip_adjacent: false,
+        }
+    }
+}
+
+/// the position of the next ``search.chr`` in ``direction`` (or of the char right before it, if
+/// not ``with_search_result``). The cursor stays, if there is none
 fn get_char_search(
-    chr: char,
+    search: &CharSearch,
     content: &[String],
     cursor_position: &CursorPosition,
     direction: MotionDirection,
     with_search_result: bool,
-) -> u32 {
-    let line = content.get(cursor_position.y as usize).unwrap();
-    let search_area = match direction {
-        MotionDirection::Foward => &line[cursor_position.x as usize..],
-        MotionDirection::Backward => &line[..cursor_position.x as usize],
-    };
-    // currently writing this code in a car in turkmenistan :(
-    let callback = |curr_chr| curr_chr == chr;
-    let mut it = search_area.chars().into_iter();
-    let search_result = match direction {
-        MotionDirection::Foward => it.position(callback),
-        MotionDirection::Backward => it.rev().position(callback),
+) -> CursorPosition {
+    let y = cursor_position.y as usize;
+    let lines: Box<dyn Iterator<Item = usize>> = match (direction, search.multiline) {
+        (_, false) => Box::new(y..y + 1),
+        (MotionDirection::Foward, true) => Box::new(y..content.len()),
+        (MotionDirection::Backward, true) => Box::new((0..=y).rev()),
     };
-    if let Some(position) = search_result {
-        let position = match direction {
-            MotionDirection::Foward => position + cursor_position.x as usize,
-            MotionDirection::Backward => cursor_position.x as usize - position - 1,
-        };
-        if with_search_result {
-            return position as u32;
-        } else {
-            return match direction {
-                MotionDirection::Foward => position as u32 - 1,
-                MotionDirection::Backward => position as u32 + 1,
-            };
+    // all positions in search order, starting next to the cursor
+    let positions = lines
+        .filter_map(|y| content.get(y).map(|line| (y, line)))
+        .flat_map(|(y, line)| {
+            let mut chars: Vec<(usize, char)> = line.chars().enumerate().collect();
+            if direction == MotionDirection::Backward {
+                chars.reverse();
+            }
+            chars.into_iter().map(move |(x, c)| {
+                (
+                    CursorPosition {
+                        x: x as u32,
+                        y: y as u32,
+                    },
+                    c,
+                )
+            })
+        })
+        .filter(|(pos, _)| match direction {
+            MotionDirection::Foward => (pos.y, pos.x) > (cursor_position.y, cursor_position.x),
+            MotionDirection::Backward => (pos.y, pos.x) < (cursor_position.y, cursor_position.x),
+        });
+    let mut previous = *cursor_position;
+    for (pos, c) in positions {
+        if c == search.chr {
+            if with_search_result {
+                return pos;
+            }
+            if previous != *cursor_position || !search.skip_adjacent {
+                // the position in front of the match, which is the cursor itself for an adjacent
+                // match
+                return previous;
+            }
         }
+        previous = pos;
     }
-    cursor_position.x
+    *cursor_position
 }
 
 impl Motion for UntilWithMotion {
@@ -161,30 +204,426 @@ impl Motion for UntilWithMotion {
         buf: impl BufferDims + ContentRef,
         cursor_position: &CursorPosition,
         direction: MotionDirection,
+    ) -> CursorPosition {
+        get_char_search(&self.0, &buf.content(), cursor_position, direction, true)
+    }
+}
+
+impl Motion for UntilWithoutMotion {
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        direction: MotionDirection,
+    ) -> CursorPosition {
+        get_char_search(&self.0, &buf.content(), cursor_position, direction, false)
+    }
+}
+
+/// the class of a char for word motions and objects: white space, keyword chars and other
+/// punctuation. A WORD (``big``) is everything but white space
+pub(super) fn char_class(c: char, big: bool) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if big || c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+impl Motion for BeginningWordMotion {
+    /// goes to the start of the next (previous) word. Like in vim, an empty line counts as a word
+    /// and the last char of the document is as far as ``w`` gets
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        direction: MotionDirection,
+    ) -> CursorPosition {
+        let flat = Flat::new(&buf.content());
+        let chars = &flat.chars;
+        let len = chars.len();
+        let class = |i: usize| char_class(chars[i], false);
+        let empty_line =
+            |i: usize| (i == 0 || chars[i - 1] == '\n') && chars.get(i).is_none_or(|c| *c == '\n');
+        let mut i = flat.index(cursor_position);
+        match direction {
+            MotionDirection::Foward => {
+                // the rest of the word, then the white space in front of the next one
+                if i < len && class(i) != 0 {
+                    let word = class(i);
+                    while i < len && class(i) == word {
+                        i += 1;
+                    }
+                }
+                while i < len && class(i) == 0 {
+                    i += 1;
+                    if i < len && chars[i] == '\n' && chars[i - 1] == '\n' {
+                        break;
+                    }
+                }
+                if i == len && len > 0 && chars[len - 1] != '\n' {
+                    i -= 1;
+                }
+            }
+            MotionDirection::Backward => {
+                if i == 0 {
+                    return *cursor_position;
+                }
+                i -= 1;
+                while i > 0 && class(i) == 0 && !empty_line(i) {
+                    i -= 1;
+                }
+                if class(i) != 0 {
+                    let word = class(i);
+                    while i > 0 && class(i - 1) == word {
+                        i -= 1;
+                    }
+                }
+            }
+        }
+        flat.pos(i)
+    }
+}
+
+impl Motion for EndWordMotion {
+    /// goes to the end of the next (``e``) or previous (``ge``) word
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        direction: MotionDirection,
+    ) -> CursorPosition {
+        let flat = Flat::new(&buf.content());
+        let chars = &flat.chars;
+        let len = chars.len();
+        let class = |i: usize| char_class(chars[i], false);
+        let empty_line =
+            |i: usize| (i == 0 || chars[i - 1] == '\n') && chars.get(i).is_none_or(|c| *c == '\n');
+        let mut i = flat.index(cursor_position);
+        match direction {
+            MotionDirection::Foward => {
+                i += 1;
+                while i < len && class(i) == 0 {
+                    i += 1;
+                }
+                if i >= len {
+                    return *cursor_position;
+                }
+                let word = class(i);
+                while i + 1 < len && class(i + 1) == word {
+                    i += 1;
+                }
+            }
+            MotionDirection::Backward => {
+                if i < len && class(i) != 0 {
+                    let word = class(i);
+                    while i > 0 && class(i - 1) == word {
+                        i -= 1;
+                    }
+                }
+                if i == 0 {
+                    return flat.pos(0);
+                }
+                i -= 1;
+                while i > 0 && class(i) == 0 && !empty_line(i) {
+                    i -= 1;
+                }
+            }
+        }
+        flat.pos(i)
+    }
+}
+
+fn first_non_blank<T>(buf: &T, y: u32) -> CursorPosition
+where
+    T: BufferDims + ContentRef,
+{
+    let x = buf.content().get(y as usize).map_or(0, |line| {
+        line.chars().take_while(|c| c.is_whitespace()).count()
+    }) as u32;
+    CursorPosition {
+        x: std::cmp::min(x, get_line_len(buf, y as usize).saturating_sub(1)),
+        y,
+    }
+}
+
+fn is_blank<T>(buf: &T, y: u32) -> bool
+where
+    T: BufferDims + ContentRef,
+{
+    buf.content()
+        .get(y as usize)
+        .is_none_or(|line| line.trim().is_empty())
+}
+
+impl Motion for LineStartMotion {
+    fn get_new_cursor_position(
+        &self,
+        _buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        _direction: MotionDirection,
     ) -> CursorPosition {
         CursorPosition {
-            x: get_char_search(self.0, buf.content(), cursor_position, direction, true),
+            x: 0,
             y: cursor_position.y,
         }
     }
 }
 
-impl Motion for UntilWithoutMotion {
+impl Motion for FirstNonBlankMotion {
     fn get_new_cursor_position(
         &self,
         buf: impl BufferDims + ContentRef,
         cursor_position: &CursorPosition,
-        direction: MotionDirection,
+        _direction: MotionDirection,
+    ) -> CursorPosition {
+        first_non_blank(&buf, cursor_position.y)
+    }
+}
+
+impl Motion for LineEndMotion {
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        _direction: MotionDirection,
     ) -> CursorPosition {
         CursorPosition {
-            x: get_char_search(self.0, buf.content(), cursor_position, direction, false),
+            x: get_line_len(&buf, cursor_position.y as usize).saturating_sub(1),
             y: cursor_position.y,
         }
     }
 }
 
+impl Motion for GotoLineMotion {
+    /// without a line number, ``Foward`` goes to the last line (``G``) and ``Backward`` to the
+    /// first one (``gg``)
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        _cursor_position: &CursorPosition,
+        direction: MotionDirection,
+    ) -> CursorPosition {
+        let last = get_lines(&buf).saturating_sub(1);
+        let y = match (self.0, direction) {
+            (Some(line), _) => std::cmp::min(line.saturating_sub(1), last),
+            (None, MotionDirection::Foward) => last,
+            (None, MotionDirection::Backward) => 0,
+        };
+        first_non_blank(&buf, y)
+    }
+}
+
+impl Motion for ParagraphMotion {
+    /// goes to the next (previous) blank line after the current paragraph, or to the end (start)
+    /// of the document if there is none
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        direction: MotionDirection,
+    ) -> CursorPosition {
+        let last = get_lines(&buf).saturating_sub(1);
+        let mut lines: Box<dyn Iterator<Item = u32>> = match direction {
+            MotionDirection::Foward => Box::new(cursor_position.y + 1..=last),
+            MotionDirection::Backward => Box::new((0..cursor_position.y).rev()),
+        };
+        // blank lines right next to the cursor don't end the paragraph
+        let found = lines
+            .by_ref()
+            .skip_while(|&y| is_blank(&buf, y))
+            .find(|&y| is_blank(&buf, y));
+        match (found, direction) {
+            (Some(y), _) => CursorPosition { x: 0, y },
+            (None, MotionDirection::Foward) => CursorPosition {
+                x: get_line_len(&buf, last as usize).saturating_sub(1),
+                y: last,
+            },
+            (None, MotionDirection::Backward) => CursorPosition { x: 0, y: 0 },
+        }
+    }
+}
+
+/// where sentences start: after '.', '!' or '?' followed by white space, empty lines and the
+/// first char after them
+fn sentence_starts(lines: &[String]) -> Vec<CursorPosition> {
+    let mut starts = Vec::new();
+    let mut pending = true;
+    for (y, line) in lines.iter().enumerate() {
+        let y = y as u32;
+        if line.is_empty() {
+            starts.push(CursorPosition { x: 0, y });
+            pending = true;
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        for (x, c) in chars.iter().enumerate() {
+            if pending && !c.is_whitespace() {
+                starts.push(CursorPosition { x: x as u32, y });
+                pending = false;
+            }
+            // the end of the line counts as white space
+            if matches!(c, '.' | '!' | '?') && chars.get(x + 1).is_none_or(|c| c.is_whitespace()) {
+                pending = true;
+            }
+        }
+    }
+    starts
+}
+
+impl Motion for SentenceMotion {
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        direction: MotionDirection,
+    ) -> CursorPosition {
+        let starts = sentence_starts(&buf.content());
+        let cursor = (cursor_position.y, cursor_position.x);
+        let found = match direction {
+            MotionDirection::Foward => starts.into_iter().find(|pos| (pos.y, pos.x) > cursor),
+            MotionDirection::Backward => {
+                starts.into_iter().rev().find(|pos| (pos.y, pos.x) < cursor)
+            }
+        };
+        match (found, direction) {
+            (Some(pos), _) => pos,
+            (None, MotionDirection::Foward) => {
+                let last = get_lines(&buf).saturating_sub(1);
+                CursorPosition {
+                    x: get_line_len(&buf, last as usize).saturating_sub(1),
+                    y: last,
+                }
+            }
+            (None, MotionDirection::Backward) => CursorPosition { x: 0, y: 0 },
+        }
+    }
+}
+
+impl Motion for MatchingBracketMotion {
+    /// jumps from the first bracket under or after the cursor on its line to the matching one
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        cursor_position: &CursorPosition,
+        _direction: MotionDirection,
+    ) -> CursorPosition {
+        const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+        let flat = Flat::new(&buf.content());
+        let chars = &flat.chars;
+        let mut idx = flat.index(cursor_position);
+        let bracket = loop {
+            match chars.get(idx) {
+                None | Some('\n') => return *cursor_position,
+                Some(c) if PAIRS.iter().any(|(open, close)| c == open || c == close) => break *c,
+                _ => idx += 1,
+            }
+        };
+        let (open, close, forward) = match PAIRS.iter().find(|(open, _)| *open == bracket) {
+            Some(&(open, close)) => (open, close, true),
+            None => {
+                let &(open, close) = PAIRS.iter().find(|(_, close)| *close == bracket).unwrap();
+                (close, open, false)
+            }
+        };
+        let mut depth = 0;
+        let mut i = idx;
+        loop {
+            if chars[i] == open {
+                depth += 1;
+            } else if chars[i] == close {
+                depth -= 1;
+                if depth == 0 {
+                    return flat.pos(i);
+                }
+            }
+            match forward {
+                true if i + 1 < chars.len() => i += 1,
+                false if i > 0 => i -= 1,
+                // unmatched
+                _ => return *cursor_position,
+            }
+        }
+    }
+}
+
+impl Motion for ScreenMotion {
+    fn get_new_cursor_position(
+        &self,
+        buf: impl BufferDims + ContentRef,
+        _cursor_position: &CursorPosition,
+        _direction: MotionDirection,
+    ) -> CursorPosition {
+        let top = buf.scroll();
+        // the lines, that fit into the window, long lines take up several screen lines
+        let mut bottom = top;
+        {
+            let content = buf.content();
+            let mut rows = 0;
+            for (y, line) in (top..).zip(content.iter().skip(top as usize)) {
+                rows += wrapped_rows(line, buf.get_text_len());
+                if rows > buf.get_text_height() as u32 && y > top {
+                    break;
+                }
+                bottom = y;
+            }
+        }
+        let y = match self.0 {
+            ScreenLine::Top => top,
+            ScreenLine::Middle => top + (bottom - top) / 2,
+            ScreenLine::Bottom => bottom,
+        };
+        first_non_blank(&buf, y)
+    }
+}
+
+/// the document as one sequence of chars (lines joined with ``\n``), to search across lines
+pub(super) struct Flat {
+    pub chars: Vec<char>,
+    line_starts: Vec<usize>,
+}
+
+impl Flat {
+    pub fn new(lines: &[String]) -> Self {
+        let mut chars = Vec::new();
+        let mut line_starts = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                chars.push('\n');
+            }
+            line_starts.push(chars.len());
+            chars.extend(line.chars());
+        }
+        Flat { chars, line_starts }
+    }
+
+    pub fn index(&self, pos: &CursorPosition) -> usize {
+        let y = std::cmp::min(pos.y as usize, self.line_starts.len().saturating_sub(1));
+        let start = self.line_starts.get(y).copied().unwrap_or(0);
+        let end = self
+            .line_starts
+            .get(y + 1)
+            .map_or(self.chars.len(), |next| next - 1);
+        std::cmp::min(start + pos.x as usize, end)
+    }
+
+    pub fn pos(&self, idx: usize) -> CursorPosition {
+        let y = self
+            .line_starts
+            .partition_point(|&start| start <= idx)
+            .saturating_sub(1);
+        CursorPosition {
+            x: (idx - self.line_starts.get(y).copied().unwrap_or(0)) as u32,
+            y: y as u32,
+        }
+    }
+}
+
 mod test {
     use crate::core::render::manager::BufferBorder;
+    use std::ops::Deref;
 
     use super::*;
 
@@ -195,9 +634,10 @@ mod test {
         offy: u16,
         border: BufferBorder,
         content: Vec<String>,
+        scroll: u32,
     }
     impl ContentRef for TestBuffer {
-        fn content(&self) -> &Vec<String> {
+        fn content(&self) -> impl Deref<Target = Vec<String>> + '_ {
             &self.content
         }
     }
@@ -229,6 +669,12 @@ mod test {
         fn get_text_len(&self) -> u16 {
             self.width - self.lpad() - self.rpad()
         }
+        fn get_text_height(&self) -> u16 {
+            self.height - self.tpad() - self.dpad()
+        }
+        fn scroll(&self) -> u32 {
+            self.scroll
+        }
     }
 
     fn get_content() -> TestBuffer {
@@ -244,13 +690,12 @@ mod test {
                 "Another line".to_string(),
                 "Guess what another line".to_string(),
             ],
+            scroll: 0,
         }
     }
 
     #[cfg(test)]
     mod left_right {
-        use futures::executor::block_on;
-
         use super::*;
 
         #[test]
@@ -259,11 +704,7 @@ mod test {
             let motion = LeftRightMotion;
             let cursor_position = CursorPosition { x: 4, y: 2 };
             assert_eq!(
-                block_on(motion.get_new_cursor_position(
-                    content,
-                    &cursor_position,
-                    MotionDirection::Foward
-                )),
+                motion.get_new_cursor_position(content, &cursor_position, MotionDirection::Foward),
                 CursorPosition { x: 5, y: 2 }
             );
         }
@@ -274,11 +715,11 @@ mod test {
             let motion = LeftRightMotion;
             let cursor_position = CursorPosition { x: 4, y: 2 };
             assert_eq!(
-                block_on(motion.get_new_cursor_position(
+                motion.get_new_cursor_position(
                     content,
                     &cursor_position,
                     MotionDirection::Backward
-                )),
+                ),
                 CursorPosition { x: 3, y: 2 }
             );
         }
@@ -289,11 +730,11 @@ mod test {
             let motion = LeftRightMotion;
             let cursor_position = CursorPosition { x: 0, y: 2 };
             assert_eq!(
-                block_on(motion.get_new_cursor_position(
+                motion.get_new_cursor_position(
                     content,
                     &cursor_position,
                     MotionDirection::Backward
-                )),
+                ),
                 CursorPosition { x: 0, y: 2 }
             );
         }
@@ -317,15 +758,48 @@ mod test {
         }
     }
 
+    #[cfg(test)]
+    mod up_down {
+        use super::*;
+
+        #[test]
+        fn past_window_height() {
+            let mut content = get_content();
+            content.content.push("fifth line".to_string());
+            let cursor_position = CursorPosition { x: 2, y: 3 };
+            assert_eq!(
+                UpDownMotion.get_new_cursor_position(
+                    content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 2, y: 4 }
+            );
+        }
+
+        #[test]
+        fn clamp_to_shorter_line() {
+            let content = get_content();
+            let cursor_position = CursorPosition { x: 18, y: 1 };
+            assert_eq!(
+                UpDownMotion.get_new_cursor_position(
+                    content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 11, y: 2 }
+            );
+        }
+    }
+
     #[cfg(test)]
     mod until {
         use super::*;
-        use futures::executor::block_on;
 
         #[test]
         fn with_foward_normal() {
             let content = get_content();
-            let motion = UntilWithMotion('l');
+            let motion = UntilWithMotion(CharSearch::new('l'));
             let cursor_position = CursorPosition { x: 3, y: 0 };
             assert_eq!(
                 motion.get_new_cursor_position(content, &cursor_position, MotionDirection::Foward),
@@ -336,7 +810,7 @@ mod test {
         #[test]
         fn with_backward_normal() {
             let content = get_content();
-            let motion = UntilWithMotion('i');
+            let motion = UntilWithMotion(CharSearch::new('i'));
             let cursor_position = CursorPosition { x: 10, y: 0 };
             assert_eq!(
                 motion.get_new_cursor_position(
@@ -347,5 +821,423 @@ mod test {
                 CursorPosition { x: 5, y: 0 }
             )
         }
+
+        #[test]
+        fn without_adjacent_match() {
+            let content = with_lines(&["ab", "xy"]);
+            let search = CharSearch::new('b');
+            let cursor_position = CursorPosition { x: 0, y: 0 };
+            // used to underflow
+            assert_eq!(
+                UntilWithoutMotion(search).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                cursor_position
+            );
+            let search = CharSearch {
+                skip_adjacent: true,
+                ..CharSearch::new('a')
+            };
+            let cursor_position = CursorPosition { x: 1, y: 0 };
+            assert_eq!(
+                UntilWithoutMotion(search).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                cursor_position
+            );
+        }
+
+        #[test]
+        fn skip_adjacent_match() {
+            let content = with_lines(&["a-b-b"]);
+            let search = CharSearch {
+                skip_adjacent: true,
+                ..CharSearch::new('b')
+            };
+            let cursor_position = CursorPosition { x: 1, y: 0 };
+            assert_eq!(
+                UntilWithoutMotion(search).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 3, y: 0 }
+            );
+        }
+
+        #[test]
+        fn empty_and_missing_lines() {
+            let content = with_lines(&["", "äöü"]);
+            for y in [0, 5] {
+                let cursor_position = CursorPosition { x: 3, y };
+                assert_eq!(
+                    UntilWithMotion(CharSearch::new('x')).get_new_cursor_position(
+                        &content,
+                        &cursor_position,
+                        MotionDirection::Backward
+                    ),
+                    cursor_position
+                );
+            }
+            // multibyte chars are no problem
+            let cursor_position = CursorPosition { x: 0, y: 1 };
+            assert_eq!(
+                UntilWithMotion(CharSearch::new('ü')).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 2, y: 1 }
+            );
+        }
+
+        #[test]
+        fn multiline() {
+            let content = get_content();
+            let search = CharSearch {
+                multiline: true,
+                ..CharSearch::new('A')
+            };
+            let cursor_position = CursorPosition { x: 3, y: 0 };
+            assert_eq!(
+                UntilWithMotion(search).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 0, y: 2 }
+            );
+            // till stops at the end of the line before
+            assert_eq!(
+                UntilWithoutMotion(search).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 18, y: 1 }
+            );
+            let cursor_position = CursorPosition { x: 3, y: 3 };
+            assert_eq!(
+                UntilWithMotion(search).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 0, y: 2 }
+            );
+            assert_eq!(
+                UntilWithMotion(CharSearch::new('A')).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                cursor_position
+            );
+        }
+    }
+
+    #[cfg(test)]
+    fn with_lines(lines: &[&str]) -> TestBuffer {
+        let mut content = get_content();
+        content.content = lines.iter().map(|line| line.to_string()).collect();
+        content
+    }
+
+    #[cfg(test)]
+    mod line {
+        use super::*;
+
+        #[test]
+        fn start_and_end() {
+            let cursor_position = CursorPosition { x: 2, y: 1 };
+            assert_eq!(
+                LineEndMotion.get_new_cursor_position(
+                    get_content(),
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 18, y: 1 }
+            );
+            assert_eq!(
+                LineStartMotion.get_new_cursor_position(
+                    get_content(),
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 0, y: 1 }
+            );
+        }
+
+        #[test]
+        fn first_non_blank() {
+            let content = with_lines(&["   indented", "    "]);
+            let cursor_position = CursorPosition { x: 8, y: 0 };
+            assert_eq!(
+                FirstNonBlankMotion.get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 3, y: 0 }
+            );
+            // a blank line has no non blank char, the cursor goes to its end
+            let cursor_position = CursorPosition { x: 0, y: 1 };
+            assert_eq!(
+                FirstNonBlankMotion.get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 3, y: 1 }
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod goto_line {
+        use super::*;
+
+        #[test]
+        fn first_and_last() {
+            let content = get_content();
+            let cursor_position = CursorPosition { x: 4, y: 2 };
+            assert_eq!(
+                GotoLineMotion(None).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 0, y: 3 }
+            );
+            assert_eq!(
+                GotoLineMotion(None).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 0, y: 0 }
+            );
+        }
+
+        #[test]
+        fn with_count() {
+            let content = get_content();
+            let cursor_position = CursorPosition { x: 4, y: 0 };
+            assert_eq!(
+                GotoLineMotion(Some(3)).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 0, y: 2 }
+            );
+            assert_eq!(
+                GotoLineMotion(Some(99)).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward
+                ),
+                CursorPosition { x: 0, y: 3 }
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod paragraph {
+        use super::*;
+
+        #[test]
+        fn foward() {
+            let content = with_lines(&["a", "b", "", "c", "", "", "d"]);
+            let mut cursor_position = CursorPosition { x: 0, y: 0 };
+            for expected in [2, 4, 6] {
+                cursor_position = ParagraphMotion.get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward,
+                );
+                assert_eq!(cursor_position, CursorPosition { x: 0, y: expected });
+            }
+        }
+
+        #[test]
+        fn backward() {
+            let content = with_lines(&["a", "b", "", "c", "", "", "d"]);
+            let mut cursor_position = CursorPosition { x: 0, y: 6 };
+            for expected in [2, 0] {
+                cursor_position = ParagraphMotion.get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Backward,
+                );
+                assert_eq!(cursor_position, CursorPosition { x: 0, y: expected });
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod sentence {
+        use super::*;
+
+        #[test]
+        fn foward_and_backward() {
+            let content = with_lines(&["One. Two is", "longer! Three.", "", "Four"]);
+            let motion = |x, y, direction| {
+                SentenceMotion.get_new_cursor_position(
+                    &content,
+                    &CursorPosition { x, y },
+                    direction,
+                )
+            };
+            assert_eq!(
+                motion(1, 0, MotionDirection::Foward),
+                CursorPosition { x: 5, y: 0 }
+            );
+            assert_eq!(
+                motion(5, 0, MotionDirection::Foward),
+                CursorPosition { x: 8, y: 1 }
+            );
+            assert_eq!(
+                motion(9, 1, MotionDirection::Foward),
+                CursorPosition { x: 0, y: 2 }
+            );
+            assert_eq!(
+                motion(0, 3, MotionDirection::Foward),
+                CursorPosition { x: 3, y: 3 }
+            );
+            assert_eq!(
+                motion(6, 0, MotionDirection::Backward),
+                CursorPosition { x: 5, y: 0 }
+            );
+            assert_eq!(
+                motion(5, 0, MotionDirection::Backward),
+                CursorPosition { x: 0, y: 0 }
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod matching_bracket {
+        use super::*;
+
+        #[test]
+        fn nested_and_multiline() {
+            let content = with_lines(&["if (a[1]) {", "  x", "}"]);
+            let motion = |x, y| {
+                MatchingBracketMotion.get_new_cursor_position(
+                    &content,
+                    &CursorPosition { x, y },
+                    MotionDirection::Foward,
+                )
+            };
+            // the first bracket after the cursor is used
+            assert_eq!(motion(0, 0), CursorPosition { x: 8, y: 0 });
+            assert_eq!(motion(10, 0), CursorPosition { x: 0, y: 2 });
+            assert_eq!(motion(0, 2), CursorPosition { x: 10, y: 0 });
+            assert_eq!(motion(7, 0), CursorPosition { x: 5, y: 0 });
+            assert_eq!(motion(0, 1), CursorPosition { x: 0, y: 1 });
+        }
+
+        #[test]
+        fn unmatched() {
+            let content = with_lines(&["(("]);
+            let cursor_position = CursorPosition { x: 0, y: 0 };
+            assert_eq!(
+                MatchingBracketMotion.get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                cursor_position
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod word {
+        use super::*;
+        use MotionDirection::{Backward, Foward};
+
+        const fn pos(x: u32, y: u32) -> CursorPosition {
+            CursorPosition { x, y }
+        }
+
+        #[test]
+        fn beginning() {
+            let content = with_lines(&["foo.bar baz", "", "  qux"]);
+            let motion = |x, y, direction| {
+                BeginningWordMotion.get_new_cursor_position(&content, &pos(x, y), direction)
+            };
+            assert_eq!(motion(0, 0, Foward), pos(3, 0));
+            assert_eq!(motion(3, 0, Foward), pos(4, 0));
+            assert_eq!(motion(4, 0, Foward), pos(8, 0));
+            // an empty line counts as a word
+            assert_eq!(motion(8, 0, Foward), pos(0, 1));
+            assert_eq!(motion(0, 1, Foward), pos(2, 2));
+            // there is no next word, the last char is as far as it gets
+            assert_eq!(motion(2, 2, Foward), pos(4, 2));
+            assert_eq!(motion(2, 2, Backward), pos(0, 1));
+            assert_eq!(motion(0, 1, Backward), pos(8, 0));
+            assert_eq!(motion(4, 0, Backward), pos(3, 0));
+            assert_eq!(motion(0, 0, Backward), pos(0, 0));
+        }
+
+        #[test]
+        fn end() {
+            let content = with_lines(&["foo.bar baz", "", "  qux"]);
+            let motion = |x, y, direction| {
+                EndWordMotion.get_new_cursor_position(&content, &pos(x, y), direction)
+            };
+            assert_eq!(motion(0, 0, Foward), pos(2, 0));
+            assert_eq!(motion(2, 0, Foward), pos(3, 0));
+            assert_eq!(motion(3, 0, Foward), pos(6, 0));
+            assert_eq!(motion(6, 0, Foward), pos(10, 0));
+            assert_eq!(motion(10, 0, Foward), pos(4, 2));
+            assert_eq!(motion(4, 2, Foward), pos(4, 2));
+            assert_eq!(motion(4, 2, Backward), pos(0, 1));
+            assert_eq!(motion(0, 1, Backward), pos(10, 0));
+            assert_eq!(motion(5, 0, Backward), pos(3, 0));
+            assert_eq!(motion(3, 0, Backward), pos(2, 0));
+        }
+    }
+
+    #[cfg(test)]
+    mod screen {
+        use super::*;
+
+        #[test]
+        fn wrapped_last_line_doesnt_fit() {
+            let content = get_content();
+            let cursor_position = CursorPosition { x: 4, y: 1 };
+            let motion = |line| {
+                ScreenMotion(line).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward,
+                )
+            };
+            assert_eq!(motion(ScreenLine::Top), CursorPosition { x: 0, y: 0 });
+            assert_eq!(motion(ScreenLine::Middle), CursorPosition { x: 0, y: 1 });
+            assert_eq!(motion(ScreenLine::Bottom), CursorPosition { x: 0, y: 2 });
+        }
+
+        #[test]
+        fn scrolled() {
+            let mut content = get_content();
+            content.scroll = 1;
+            let cursor_position = CursorPosition { x: 4, y: 1 };
+            assert_eq!(
+                ScreenMotion(ScreenLine::Middle).get_new_cursor_position(
+                    &content,
+                    &cursor_position,
+                    MotionDirection::Foward
+                ),
+                CursorPosition { x: 0, y: 2 }
+            );
+        }
     }
 }
@@ -0,0 +1,192 @@
+//! # Mouse
+//! Maps mouse events to the window under the pointer: a click focuses the window and moves the
+//! cursor, dragging selects text (visual mode), the wheel scrolls and dragging the border of a
+//! window resizes it. Only does something if ``InputConfig::mouse_capture`` is enabled.
+use std::sync::Arc;
+
+use crossterm::event::{Event, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+use super::mode::{self, Mode};
+use super::registers::RegisterKind;
+use super::visual::{self, Selection};
+use super::CursorPosition;
+use crate::core::event_handling::EventCallback;
+use crate::core::input::{self, InputEvent};
+use crate::core::logger::{self, LogLevel};
+use crate::core::render::manager::{self, Area, BufferRef, Direction, SplitDirection};
+
+/// lines scrolled per step of the mouse wheel
+pub const SCROLL_LINES: i32 = 3;
+
+#[derive(Debug)]
+enum Drag {
+    /// selecting text in ``window``, starting at ``anchor``
+    Select {
+        window: BufferRef,
+        anchor: CursorPosition,
+    },
+    /// moving the ``side`` border of ``window``, the pointer was last at ``last``
+    Border {
+        window: BufferRef,
+        side: Direction,
+        last: (u16, u16),
+    },
+}
+
+static DRAG: Lazy<Mutex<Option<Drag>>> = Lazy::new(|| Mutex::new(None));
+
+/// how far and in which direction the window has to grow, if its ``side`` border is dragged
+/// from ``from`` to ``to``
+fn border_resize(side: Direction, from: (u16, u16), to: (u16, u16)) -> (SplitDirection, i16) {
+    let dx = to.0 as i16 - from.0 as i16;
+    let dy = to.1 as i16 - from.1 as i16;
+    match side {
+        Direction::Right => (SplitDirection::Vertical, dx),
+        Direction::Left => (SplitDirection::Vertical, -dx),
+        Direction::Down => (SplitDirection::Horizontal, dy),
+        Direction::Up => (SplitDirection::Horizontal, -dy),
+    }
+}
+
+async fn leave_visual() -> Result<(), String> {
+    if mode::current().await.is_visual() {
+        visual::leave().await?;
+    }
+    Ok(())
+}
+
+async fn click(x: u16, y: u16) -> Result<(), String> {
+    leave_visual().await?;
+    let Some((window, area)) = manager::window_at(x, y).await else {
+        return Ok(());
+    };
+    match area {
+        Area::Border(side) => {
+            *DRAG.lock().await = Some(Drag::Border {
+                window,
+                side,
+                last: (x, y),
+            });
+            Ok(())
+        }
+        Area::Text(pos) => {
+            manager::update_window(&window, |buf| buf.set_cursor_pos(pos)).await?;
+            manager::focus_window(&window).await?;
+            *DRAG.lock().await = Some(Drag::Select {
+                window,
+                anchor: pos,
+            });
+            Ok(())
+        }
+    }
+}
+
+async fn drag(x: u16, y: u16) -> Result<(), String> {
+    let mut drag = DRAG.lock().await;
+    match drag.as_mut() {
+        Some(Drag::Select { window, anchor }) => {
+            let anchor = *anchor;
+            manager::update_window(window, |buf| {
+                // the selection stays in the window it was started in
+                let pos = buf.doc_position_at(x, y);
+                buf.set_cursor_pos(pos);
+            })
+            .await?;
+            drop(drag);
+            if mode::current().await != Mode::Visual {
+                mode::set(Mode::Visual).await;
+            }
+            manager::set_visual(Some(Selection {
+                anchor,
+                kind: RegisterKind::Charwise,
+            }))
+            .await
+        }
+        Some(Drag::Border { window, side, last }) => {
+            let (dir, delta) = border_resize(*side, *last, (x, y));
+            if delta == 0 {
+                return Ok(());
+            }
+            *last = (x, y);
+            let window = window.clone();
+            drop(drag);
+            manager::resize_window(&window, dir, delta).await
+        }
+        None => Ok(()),
+    }
+}
+
+async fn scroll(x: u16, y: u16, delta: i32) -> Result<(), String> {
+    let Some((window, _)) = manager::window_at(x, y).await else {
+        return Ok(());
+    };
+    manager::update_window(&window, |buf| buf.scroll_by(delta)).await?;
+    manager::rerender().await
+}
+
+/// processes a single mouse event
+pub async fn handle(event: MouseEvent) -> Result<(), String> {
+    let (x, y) = (event.column, event.row);
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => click(x, y).await,
+        MouseEventKind::Drag(MouseButton::Left) => drag(x, y).await,
+        MouseEventKind::Up(MouseButton::Left) => {
+            *DRAG.lock().await = None;
+            Ok(())
+        }
+        MouseEventKind::ScrollDown => scroll(x, y, SCROLL_LINES).await,
+        MouseEventKind::ScrollUp => scroll(x, y, -SCROLL_LINES).await,
+        _ => Ok(()),
+    }
+}
+
+/// subscribes to the input events, so mouse events reach ``handle``
+pub async fn init() {
+    input::subscribe(EventCallback::new(
+        Arc::new(Box::new(|evt: Arc<Mutex<input::EvtData>>| {
+            Box::pin(async move {
+                let event = match evt.lock().await.0 {
+                    Event::Mouse(event) => event,
+                    _ => return,
+                };
+                if let Err(msg) = handle(event).await {
+                    logger::log(LogLevel::Error, msg.as_str()).await;
+                }
+                if let Err(msg) = manager::redraw_statusline().await {
+                    logger::log(LogLevel::Error, msg.as_str()).await;
+                }
+            })
+        })),
+        true,
+        InputEvent(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Moved, // doesn't matter which event goes here
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::empty(),
+        })),
+    ))
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dragging_borders() {
+        assert_eq!(
+            border_resize(Direction::Right, (10, 5), (13, 7)),
+            (SplitDirection::Vertical, 3)
+        );
+        assert_eq!(
+            border_resize(Direction::Left, (10, 5), (13, 7)),
+            (SplitDirection::Vertical, -3)
+        );
+        assert_eq!(
+            border_resize(Direction::Up, (10, 5), (13, 7)),
+            (SplitDirection::Horizontal, -2)
+        );
+    }
+}
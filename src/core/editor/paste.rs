@@ -0,0 +1,141 @@
+//! # Paste
+//! Text pasted into the terminal (with ``InputConfig::bracketed_paste`` enabled) arrives as one
+//! event instead of single key presses. It is inserted in one go, without going through the
+//! keymaps, and can be undone with a single ``u``. In insert mode, it still counts as inserted
+//! text for ``.`` and the ``.`` register.
+use std::sync::Arc;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use tokio::sync::Mutex;
+
+use super::keymap;
+use super::mode::{self, Mode};
+use super::CursorPosition;
+use crate::core::event_handling::EventCallback;
+use crate::core::input::{self, InputEvent};
+use crate::core::logger::{self, LogLevel};
+use crate::core::render::manager;
+
+/// converts windows (``\r\n``) and old mac (``\r``) line endings to ``\n``
+pub fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// inserts ``text`` at the cursor in insert mode and after it in normal mode (like ``p``)
+pub async fn paste(text: &str) -> Result<(), String> {
+    let text = normalize_line_endings(text);
+    if text.is_empty() {
+        return Ok(());
+    }
+    let mode = mode::current().await;
+    match mode {
+        Mode::Normal | Mode::Insert => {}
+        // only the first line fits on the command line
+        Mode::CommandLine => {
+            for c in text.lines().next().unwrap_or_default().chars() {
+                keymap::feed_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty())).await?;
+            }
+            return Ok(());
+        }
+        Mode::Visual | Mode::VisualLine | Mode::VisualBlock | Mode::OperatorPending => {
+            return Ok(())
+        }
+    }
+    manager::edit_focused(|doc, cursor| {
+        let len = doc
+            .line(cursor.y as usize)
+            .map_or(0, |line| line.chars().count()) as u32;
+        let pos = match mode {
+            Mode::Normal => CursorPosition {
+                x: std::cmp::min(cursor.x + 1, len),
+                y: cursor.y,
+            },
+            _ => cursor,
+        };
+        // the paste is its own undo step, even in the middle of typing something
+        doc.begin_undo_step(cursor);
+        let end = doc.insert_str(pos, &text);
+        doc.end_undo_step();
+        match mode {
+            Mode::Insert => {
+                doc.begin_undo_step(end);
+                end
+            }
+            // the cursor ends up on the last pasted char
+            _ => CursorPosition {
+                x: end.x.saturating_sub(1),
+                y: end.y,
+            },
+        }
+    })
+    .await?;
+    if mode == Mode::Insert {
+        keymap::record_inserted(&text).await;
+    }
+    Ok(())
+}
+
+/// subscribes to the input events, so pasted text reaches ``paste``
+pub async fn init() {
+    input::subscribe(EventCallback::new(
+        Arc::new(Box::new(|evt: Arc<Mutex<input::EvtData>>| {
+            Box::pin(async move {
+                let text = match &evt.lock().await.0 {
+                    Event::Paste(text) => text.clone(),
+                    _ => return,
+                };
+                if let Err(msg) = paste(&text).await {
+                    logger::log(LogLevel::Error, msg.as_str()).await;
+                }
+                if let Err(msg) = manager::redraw_statusline().await {
+                    logger::log(LogLevel::Error, msg.as_str()).await;
+                }
+            })
+        })),
+        true,
+        InputEvent(Event::Paste(String::new())),
+    ))
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::editor::registers;
+    use crate::core::headless;
+
+    #[test]
+    fn line_endings() {
+        assert_eq!(normalize_line_endings("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert_eq!(normalize_line_endings("\r\n\r\n"), "\n\n");
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::empty())
+    }
+
+    #[tokio::test]
+    async fn undone_at_once() {
+        let (doc, _window) = headless::test_window(&["ab"]).await;
+        paste("xy\r\nz").await.unwrap();
+        assert_eq!(doc.read().unwrap().lines(), &vec!["axy", "zb"]);
+        // on the last pasted char
+        let cursor = manager::window_cursors().await.last().unwrap().1;
+        assert_eq!(cursor, CursorPosition { x: 0, y: 1 });
+        keymap::normal("u").await.unwrap();
+        assert_eq!(doc.read().unwrap().lines(), &vec!["ab"]);
+    }
+
+    #[tokio::test]
+    async fn repeated_with_the_insert() {
+        let (doc, _window) = headless::test_window(&["ab"]).await;
+        keymap::feed_key(key(KeyCode::Char('i'))).await.unwrap();
+        paste("xy").await.unwrap();
+        keymap::feed_key(key(KeyCode::Esc)).await.unwrap();
+        assert_eq!(doc.read().unwrap().lines(), &vec!["xyab"]);
+        keymap::normal(".").await.unwrap();
+        assert_eq!(doc.read().unwrap().lines(), &vec!["xxyyab"]);
+        let inserted = registers::get(Some('.')).await.unwrap().text;
+        assert_eq!(inserted, "xy");
+    }
+}
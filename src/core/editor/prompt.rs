@@ -0,0 +1,93 @@
+//! # Prompts
+//! Questions, that are answered with a single key, like vim's "[O]K, (L)oad File". While a
+//! question is open, it is shown instead of the status line and every key goes to it instead of
+//! the keymaps. Several questions are asked one after the other.
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+pub type AnswerFunctionType =
+    Box<dyn FnOnce(char) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send>;
+
+struct Prompt {
+    question: String,
+    /// the keys, that answer the question. The first one is the default, used for ``<Esc>``
+    choices: Vec<char>,
+    answer: AnswerFunctionType,
+}
+
+static PROMPTS: Lazy<Mutex<VecDeque<Prompt>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// wraps an async fn into the answer type
+pub fn answer<F, Fut>(f: F) -> AnswerFunctionType
+where
+    F: FnOnce(char) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    Box::new(move |c| Box::pin(f(c)))
+}
+
+/// queues a question. ``answer`` gets the lowercase choice the user picked
+pub async fn ask(question: String, choices: &str, answer: AnswerFunctionType) {
+    PROMPTS.lock().await.push_back(Prompt {
+        question,
+        choices: choices.to_lowercase().chars().collect(),
+        answer,
+    });
+}
+
+/// the question, that is currently asked
+pub async fn question() -> Option<String> {
+    PROMPTS
+        .lock()
+        .await
+        .front()
+        .map(|prompt| prompt.question.clone())
+}
+
+/// which choice ``key`` picks, if any
+fn choice(choices: &[char], key: KeyEvent) -> Option<char> {
+    match key.code {
+        KeyCode::Esc => choices.first().copied(),
+        KeyCode::Char(c) => Some(c.to_ascii_lowercase()).filter(|c| choices.contains(c)),
+        _ => None,
+    }
+}
+
+/// hands ``key`` to the current question. Returns false if there is no question, so the key
+/// should be handled as usual
+pub async fn feed_key(key: KeyEvent) -> Result<bool, String> {
+    let prompt = {
+        let mut prompts = PROMPTS.lock().await;
+        let Some(prompt) = prompts.front() else {
+            return Ok(false);
+        };
+        match choice(&prompt.choices, key) {
+            Some(c) => (prompts.pop_front().unwrap(), c),
+            // other keys are swallowed, until the question is answered
+            None => return Ok(true),
+        }
+    };
+    let (prompt, c) = prompt;
+    (prompt.answer)(c).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    #[test]
+    fn choices() {
+        let choices = ['o', 'l'];
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+        assert_eq!(choice(&choices, key(KeyCode::Char('L'))), Some('l'));
+        assert_eq!(choice(&choices, key(KeyCode::Esc)), Some('o'));
+        assert_eq!(choice(&choices, key(KeyCode::Char('x'))), None);
+    }
+}
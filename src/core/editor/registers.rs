@@ -0,0 +1,636 @@
+//! # Registers
+//! Storage for yanked and deleted text, like vim's registers:
+//! - ``"`` (unnamed) is the register, that was written last
+//! - ``0`` holds the last yank, ``1`` - ``9`` the last deletes of whole lines, ``-`` the last
+//!   delete within a line
+//! - ``a`` - ``z`` are written by the user, ``A`` - ``Z`` append to them
+//! - ``_`` (black hole) throws everything away
+//! - ``.`` (last inserted text), ``:`` (last command line) and ``%`` (file name) are read only
+//! - ``+`` and ``*`` are the system clipboard, see ``ClipboardProvider``
+use std::collections::HashMap;
+use std::io::{stdout, Write};
+use std::process::Stdio;
+
+use once_cell::sync::Lazy;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::{Mutex, RwLock};
+
+use super::document::Document;
+use super::CursorPosition;
+use crate::core::render::manager;
+use crate::core::terminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    Charwise,
+    /// whole lines, the text always ends with a newline
+    Linewise,
+    /// a rectangle, every line of the text is one row of it
+    Blockwise,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+impl Register {
+    pub fn new(text: impl Into<String>, kind: RegisterKind) -> Self {
+        let mut text = text.into();
+        if kind == RegisterKind::Linewise && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Register { text, kind }
+    }
+
+    /// appends ``other``, like writing to an uppercase register. Appending lines to a charwise
+    /// register turns it into lines
+    fn append(&mut self, other: Register) {
+        match (self.kind, other.kind) {
+            (RegisterKind::Linewise, _) | (_, RegisterKind::Linewise) => {
+                if !self.text.ends_with('\n') {
+                    self.text.push('\n');
+                }
+                self.text.push_str(&other.text);
+                *self = Register::new(std::mem::take(&mut self.text), RegisterKind::Linewise);
+            }
+            (RegisterKind::Blockwise, _) | (_, RegisterKind::Blockwise) => {
+                self.text.push('\n');
+                self.text.push_str(&other.text);
+            }
+            _ => self.text.push_str(&other.text),
+        }
+    }
+}
+
+/// why something is written to a register, yanks and deletes end up in different places
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Yank,
+    Delete,
+}
+
+/// how the ``+`` and ``*`` registers reach the system clipboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardProvider {
+    /// only kept inside of the editor
+    None,
+    /// the OSC 52 escape sequence, which lets the terminal set the clipboard (works over ssh too).
+    /// Terminals don't allow reading the clipboard this way, so pasting uses the last copy
+    Osc52,
+    /// external programs, e.g. ``["xclip", "-selection", "clipboard"]``. The text is written to
+    /// the stdin of ``copy`` and read from the stdout of ``paste``
+    Command {
+        copy: Vec<String>,
+        paste: Option<Vec<String>>,
+    },
+}
+
+#[derive(Debug, Default)]
+struct Registers {
+    /// the register ``"`` refers to
+    unnamed: Option<char>,
+    /// ``0`` - ``9``, ``a`` - ``z``, ``-``, ``+`` and ``*``
+    stored: HashMap<char, Register>,
+    last_inserted: String,
+    last_cmdline: String,
+}
+
+static REGISTERS: Lazy<Mutex<Registers>> = Lazy::new(|| Mutex::new(Registers::default()));
+static CLIPBOARD: Lazy<RwLock<ClipboardProvider>> =
+    Lazy::new(|| RwLock::new(ClipboardProvider::Osc52));
+
+impl ClipboardProvider {
+    /// parses ``copy[;paste]``, where the arguments of the programs are separated by commas (there
+    /// are no spaces in an option), e.g. ``xclip,-i;xclip,-o``
+    pub fn from_commands(option: &str) -> Option<Self> {
+        let argv = |cmd: &str| -> Option<Vec<String>> {
+            let argv: Vec<String> = cmd
+                .split(',')
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| arg.to_string())
+                .collect();
+            (!argv.is_empty()).then_some(argv)
+        };
+        let (copy, paste) = match option.split_once(';') {
+            Some((copy, paste)) => (copy, argv(paste)),
+            None => (option, None),
+        };
+        Some(ClipboardProvider::Command {
+            copy: argv(copy)?,
+            paste,
+        })
+    }
+}
+
+pub async fn set_clipboard_provider(provider: ClipboardProvider) {
+    *CLIPBOARD.write().await = provider;
+}
+
+pub fn is_valid(name: char) -> bool {
+    name.is_ascii_alphanumeric() || "\"-_.:%+*".contains(name)
+}
+
+impl Registers {
+    /// where a write of ``op`` to ``name`` goes, ``None`` for the black hole
+    fn store(
+        &mut self,
+        name: Option<char>,
+        op: Operation,
+        reg: Register,
+    ) -> Result<Option<char>, String> {
+        let target = match name {
+            Some('_') => return Ok(None),
+            None | Some('"') => match op {
+                Operation::Yank => '0',
+                // small deletes within a line don't push the numbered registers
+                Operation::Delete
+                    if reg.kind == RegisterKind::Charwise && !reg.text.contains('\n') =>
+                {
+                    '-'
+                }
+                Operation::Delete => {
+                    for i in (1..9).rev() {
+                        let from = char::from_digit(i, 10).unwrap();
+                        if let Some(older) = self.stored.remove(&from) {
+                            self.stored
+                                .insert(char::from_digit(i + 1, 10).unwrap(), older);
+                        }
+                    }
+                    '1'
+                }
+            },
+            Some(c @ 'A'..='Z') => {
+                let lower = c.to_ascii_lowercase();
+                match self.stored.get_mut(&lower) {
+                    Some(existing) => existing.append(reg),
+                    None => {
+                        self.stored.insert(lower, reg);
+                    }
+                }
+                self.unnamed = Some(lower);
+                return Ok(Some(lower));
+            }
+            Some(c) if c.is_ascii_alphanumeric() || "-+*".contains(c) => c,
+            Some(c @ ('.' | ':' | '%')) => return Err(format!("register {c} is read only")),
+            Some(c) => return Err(format!("invalid register: {c}")),
+        };
+        self.stored.insert(target, reg);
+        self.unnamed = Some(target);
+        Ok(Some(target))
+    }
+
+    fn get(&self, name: Option<char>) -> Option<Register> {
+        let name = match name {
+            None | Some('"') => self.unnamed?,
+            Some(c) => c.to_ascii_lowercase(),
+        };
+        match name {
+            '.' => Some(Register::new(&self.last_inserted, RegisterKind::Charwise)),
+            ':' => Some(Register::new(&self.last_cmdline, RegisterKind::Charwise)),
+            '_' => None,
+            name => self.stored.get(&name).cloned(),
+        }
+    }
+}
+
+/// standard base64 with padding, as OSC 52 expects it
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut ret = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                ret.push(CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                ret.push('=');
+            }
+        }
+    }
+    ret
+}
+
+/// the escape sequence, that puts ``text`` into the clipboard of the terminal
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+async fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let provider = CLIPBOARD.read().await.clone();
+    match provider {
+        ClipboardProvider::None => Ok(()),
+        // without a terminal, there is nobody to receive the escape sequence
+        ClipboardProvider::Osc52 if terminal::headless() => Ok(()),
+        ClipboardProvider::Osc52 => {
+            let mut out = stdout();
+            out.write_all(osc52(text).as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(|err| err.to_string())
+        }
+        ClipboardProvider::Command { copy, .. } => {
+            let (program, args) = copy.split_first().ok_or("no clipboard command")?;
+            let mut child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|err| format!("clipboard: {err}"))?;
+            let mut stdin = child.stdin.take().ok_or("clipboard: no stdin")?;
+            stdin
+                .write_all(text.as_bytes())
+                .await
+                .map_err(|err| format!("clipboard: {err}"))?;
+            drop(stdin);
+            child
+                .wait()
+                .await
+                .map_err(|err| format!("clipboard: {err}"))?;
+            Ok(())
+        }
+    }
+}
+
+/// the clipboard content, ``None`` if the provider can't read it
+async fn paste_from_clipboard() -> Option<Register> {
+    let provider = CLIPBOARD.read().await.clone();
+    let ClipboardProvider::Command {
+        paste: Some(paste), ..
+    } = provider
+    else {
+        return None;
+    };
+    let (program, args) = paste.split_first()?;
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    let kind = if text.ends_with('\n') {
+        RegisterKind::Linewise
+    } else {
+        RegisterKind::Charwise
+    };
+    Some(Register::new(text, kind))
+}
+
+/// writes ``reg`` to the register ``name`` (``None`` if the user didn't pick one)
+pub async fn store(name: Option<char>, op: Operation, reg: Register) -> Result<(), String> {
+    let text = reg.text.clone();
+    let target = REGISTERS.lock().await.store(name, op, reg)?;
+    if let Some('+' | '*') = target {
+        copy_to_clipboard(&text).await?;
+    }
+    Ok(())
+}
+
+/// the content of register ``name``, ``None`` if it's empty
+pub async fn get(name: Option<char>) -> Option<Register> {
+    match name {
+        Some('+' | '*') => {
+            if let Some(reg) = paste_from_clipboard().await {
+                return Some(reg);
+            }
+        }
+        Some('%') => {
+            let focused = manager::focused().await.ok()?;
+            let path = focused
+                .deref()
+                .await
+                .document()
+                .read()
+                .unwrap()
+                .path()?
+                .to_string();
+            return Some(Register::new(path, RegisterKind::Charwise));
+        }
+        _ => {}
+    }
+    REGISTERS.lock().await.get(name)
+}
+
+/// the registers kept in the state file: named, numbered and ``-``
+pub async fn persistent() -> Vec<(char, Register)> {
+    let registers = REGISTERS.lock().await;
+    let mut ret: Vec<_> = registers
+        .stored
+        .iter()
+        .filter(|(name, _)| name.is_ascii_alphanumeric() || **name == '-')
+        .map(|(name, reg)| (*name, reg.clone()))
+        .collect();
+    ret.sort_by_key(|(name, _)| *name);
+    ret
+}
+
+/// sets a register without shifting the numbered ones or changing ``"``, e.g. when the state file
+/// is read
+pub async fn restore(name: char, reg: Register) {
+    REGISTERS.lock().await.stored.insert(name, reg);
+}
+
+/// remembers the text typed in the last insert mode session (``.``)
+pub async fn set_last_inserted(text: String) {
+    REGISTERS.lock().await.last_inserted = text;
+}
+
+/// remembers the last executed command line (``:``)
+pub async fn set_last_cmdline(cmdline: String) {
+    REGISTERS.lock().await.last_cmdline = cmdline;
+}
+
+fn line_len(doc: &Document, y: u32) -> u32 {
+    doc.line(y as usize)
+        .map_or(0, |line| line.chars().count() as u32)
+}
+
+/// position of the first char of line ``y``, that isn't whitespace
+pub fn first_non_blank(doc: &Document, y: u32) -> CursorPosition {
+    let x = doc.line(y as usize).map_or(0, |line| {
+        line.chars().take_while(|c| c.is_whitespace()).count()
+    });
+    CursorPosition {
+        x: std::cmp::min(x as u32, line_len(doc, y).saturating_sub(1)),
+        y,
+    }
+}
+
+/// puts ``reg`` ``count`` times after (``p``) or before (``P``) the cursor and returns the new
+/// cursor position
+pub fn put(
+    doc: &mut Document,
+    cursor: CursorPosition,
+    reg: &Register,
+    after: bool,
+    count: u32,
+) -> CursorPosition {
+    let count = count.max(1) as usize;
+    let len = line_len(doc, cursor.y);
+    // after the cursor means after the char under it, as long as there is one
+    let col = if after && len > 0 {
+        std::cmp::min(cursor.x + 1, len)
+    } else {
+        std::cmp::min(cursor.x, len)
+    };
+    match reg.kind {
+        RegisterKind::Charwise => {
+            let text = reg.text.repeat(count);
+            let start = CursorPosition {
+                x: col,
+                y: cursor.y,
+            };
+            let end = doc.insert_str(start, &text);
+            if text.contains('\n') {
+                start
+            } else {
+                CursorPosition {
+                    x: end.x.saturating_sub(1),
+                    y: end.y,
+                }
+            }
+        }
+        RegisterKind::Linewise => {
+            let text = reg.text.repeat(count);
+            let line_count = doc.line_count() as u32;
+            let y = if after { cursor.y + 1 } else { cursor.y };
+            if doc.line_count() == 0 || y < line_count {
+                doc.insert_str(CursorPosition { x: 0, y }, &text);
+            } else {
+                // below the last line, the new lines start with the line break
+                let last = line_count - 1;
+                let text = format!("\n{}", text.strip_suffix('\n').unwrap_or(&text));
+                doc.insert_str(
+                    CursorPosition {
+                        x: line_len(doc, last),
+                        y: last,
+                    },
+                    &text,
+                );
+            }
+            first_non_blank(doc, y)
+        }
+        RegisterKind::Blockwise => {
+            let rows: Vec<&str> = reg.text.split('\n').collect();
+            let width = rows
+                .iter()
+                .map(|row| row.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (i, row) in rows.iter().enumerate() {
+                let y = cursor.y + i as u32;
+                if y as usize >= doc.line_count() {
+                    let last = doc.line_count().saturating_sub(1) as u32;
+                    doc.insert_str(
+                        CursorPosition {
+                            x: line_len(doc, last),
+                            y: last,
+                        },
+                        "\n",
+                    );
+                }
+                let len = line_len(doc, y);
+                let mut text = " ".repeat(col.saturating_sub(len) as usize);
+                // the rows are padded to the same width, unless nothing follows them
+                let padded = format!("{row:width$}");
+                let block = padded.repeat(count);
+                text.push_str(if len > col {
+                    &block
+                } else {
+                    block.trim_end_matches(' ')
+                });
+                doc.insert_str(CursorPosition { x: col.min(len), y }, &text);
+            }
+            CursorPosition {
+                x: col,
+                y: cursor.y,
+            }
+        }
+    }
+}
+
+/// the ``count`` lines starting at ``y``
+pub fn yank_lines(doc: &Document, y: u32, count: u32) -> Register {
+    let lines: Vec<&str> = doc
+        .lines()
+        .iter()
+        .skip(y as usize)
+        .take(count.max(1) as usize)
+        .map(|line| line.as_str())
+        .collect();
+    Register::new(lines.join("\n"), RegisterKind::Linewise)
+}
+
+/// removes the ``count`` lines starting at ``y`` and returns them
+pub fn delete_lines(doc: &mut Document, y: u32, count: u32) -> Register {
+    let reg = yank_lines(doc, y, count);
+    let line_count = doc.line_count() as u32;
+    let end = y + count.max(1);
+    if end < line_count {
+        doc.remove(CursorPosition { x: 0, y }, CursorPosition { x: 0, y: end });
+    } else if y > 0 {
+        // the last lines take the line break in front of them along
+        doc.remove(
+            CursorPosition {
+                x: line_len(doc, y - 1),
+                y: y - 1,
+            },
+            CursorPosition {
+                x: line_len(doc, line_count - 1),
+                y: line_count - 1,
+            },
+        );
+    } else {
+        doc.remove(
+            CursorPosition { x: 0, y: 0 },
+            CursorPosition {
+                x: line_len(doc, line_count.saturating_sub(1)),
+                y: line_count.saturating_sub(1),
+            },
+        );
+    }
+    reg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(lines: &[&str]) -> Document {
+        Document::new(lines.iter().map(|line| line.to_string()).collect())
+    }
+
+    const fn pos(x: u32, y: u32) -> CursorPosition {
+        CursorPosition { x, y }
+    }
+
+    #[test]
+    fn numbered_registers_shift() {
+        let mut regs = Registers::default();
+        let lines = |text: &str| Register::new(text, RegisterKind::Linewise);
+        regs.store(None, Operation::Delete, lines("one")).unwrap();
+        regs.store(None, Operation::Delete, lines("two")).unwrap();
+        regs.store(None, Operation::Yank, lines("yanked")).unwrap();
+        regs.store(
+            None,
+            Operation::Delete,
+            Register::new("x", RegisterKind::Charwise),
+        )
+        .unwrap();
+        assert_eq!(regs.get(Some('1')).unwrap().text, "two\n");
+        assert_eq!(regs.get(Some('2')).unwrap().text, "one\n");
+        assert_eq!(regs.get(Some('0')).unwrap().text, "yanked\n");
+        assert_eq!(regs.get(Some('-')).unwrap().text, "x");
+        assert_eq!(regs.get(None).unwrap().text, "x");
+    }
+
+    #[test]
+    fn named_registers_append() {
+        let mut regs = Registers::default();
+        let chars = |text: &str| Register::new(text, RegisterKind::Charwise);
+        regs.store(Some('a'), Operation::Yank, chars("foo"))
+            .unwrap();
+        regs.store(Some('A'), Operation::Yank, chars("bar"))
+            .unwrap();
+        assert_eq!(regs.get(Some('a')), Some(chars("foobar")));
+        regs.store(
+            Some('A'),
+            Operation::Yank,
+            Register::new("baz", RegisterKind::Linewise),
+        )
+        .unwrap();
+        assert_eq!(
+            regs.get(Some('a')),
+            Some(Register::new("foobar\nbaz\n", RegisterKind::Linewise))
+        );
+        regs.store(Some('_'), Operation::Delete, chars("gone"))
+            .unwrap();
+        assert_eq!(
+            regs.get(None),
+            Some(Register::new("foobar\nbaz\n", RegisterKind::Linewise))
+        );
+        assert!(regs.store(Some('.'), Operation::Yank, chars("no")).is_err());
+    }
+
+    #[test]
+    fn clipboard_commands() {
+        let argv = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(
+            ClipboardProvider::from_commands("xclip,-i;xclip,-o"),
+            Some(ClipboardProvider::Command {
+                copy: argv(&["xclip", "-i"]),
+                paste: Some(argv(&["xclip", "-o"])),
+            })
+        );
+        assert_eq!(
+            ClipboardProvider::from_commands("pbcopy"),
+            Some(ClipboardProvider::Command {
+                copy: argv(&["pbcopy"]),
+                paste: None,
+            })
+        );
+        assert_eq!(ClipboardProvider::from_commands(";xclip"), None);
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+
+    #[test]
+    fn put_charwise() {
+        let mut doc = doc(&["abc"]);
+        let reg = Register::new("XY", RegisterKind::Charwise);
+        assert_eq!(put(&mut doc, pos(0, 0), &reg, true, 2), pos(4, 0));
+        assert_eq!(doc.lines(), &vec!["aXYXYbc"]);
+        put(&mut doc, pos(0, 0), &reg, false, 1);
+        assert_eq!(doc.lines(), &vec!["XYaXYXYbc"]);
+    }
+
+    #[test]
+    fn put_linewise() {
+        let mut doc = doc(&["one", "two"]);
+        let reg = Register::new("  new", RegisterKind::Linewise);
+        assert_eq!(put(&mut doc, pos(1, 0), &reg, true, 1), pos(2, 1));
+        assert_eq!(doc.lines(), &vec!["one", "  new", "two"]);
+        put(&mut doc, pos(0, 2), &reg, true, 1);
+        assert_eq!(doc.lines(), &vec!["one", "  new", "two", "  new"]);
+        put(&mut doc, pos(0, 0), &reg, false, 1);
+        assert_eq!(doc.lines(), &vec!["  new", "one", "  new", "two", "  new"]);
+    }
+
+    #[test]
+    fn put_blockwise() {
+        let mut doc = doc(&["abcd", "ab", "abcd"]);
+        let reg = Register::new("XY\nZ", RegisterKind::Blockwise);
+        assert_eq!(put(&mut doc, pos(1, 0), &reg, false, 1), pos(1, 0));
+        assert_eq!(doc.lines(), &vec!["aXYbcd", "aZ b", "abcd"]);
+        let mut doc = self::doc(&["a"]);
+        put(&mut doc, pos(0, 0), &reg, true, 1);
+        assert_eq!(doc.lines(), &vec!["aXY", " Z"]);
+    }
+
+    #[test]
+    fn delete_and_yank_lines() {
+        let mut doc = doc(&["one", "two", "three"]);
+        assert_eq!(yank_lines(&doc, 1, 5).text, "two\nthree\n");
+        let reg = delete_lines(&mut doc, 1, 1);
+        assert_eq!(reg, Register::new("two", RegisterKind::Linewise));
+        assert_eq!(doc.lines(), &vec!["one", "three"]);
+        delete_lines(&mut doc, 1, 1);
+        assert_eq!(doc.lines(), &vec!["one"]);
+        delete_lines(&mut doc, 0, 1);
+        assert_eq!(doc.lines(), &vec![""]);
+    }
+}
@@ -0,0 +1,246 @@
+//! # Sessions
+//! ``:mksession [file]`` saves the window arrangement: the layers with their layouts (including
+//! split ratios, the master area, the current tab, ...), the files shown in the windows, their
+//! cursor and scroll positions and which window is focused. Starting the editor with
+//! ``-S [file]`` restores it. The file has one line per layer and window, the windows belong to
+//! the layer above them and are listed in the order the layout shows them:
+//! ```text
+//! layer master left 0.5 1
+//! window 12 4 0 src/main.rs
+//! window 0 0 0 Cargo.toml
+//! zoom 1
+//! layer floating
+//! focus 0 1
+//! ```
+use super::document::Document;
+use super::CursorPosition;
+use crate::core::io;
+use crate::core::render::manager::{self, LayerState, WindowState};
+
+/// used by ``:mksession`` and ``-S`` without a file name, like vim's ``Session.vim``
+pub const DEFAULT_PATH: &str = "Session.neo";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    /// ``None`` for a window without a file
+    path: Option<String>,
+    cursor: CursorPosition,
+    scroll: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Session {
+    layers: Vec<LayerState<Window>>,
+    /// layer and index of the focused window
+    focus: Option<(usize, usize)>,
+}
+
+fn format(session: &Session) -> String {
+    let mut ret = String::new();
+    for layer in session.layers.iter() {
+        ret.push_str(format!("layer {} {}", layer.layout, layer.arrangement).trim_end());
+        ret.push('\n');
+        for window in layer.windows.iter() {
+            let CursorPosition { x, y } = window.cursor;
+            let line = format!("window {y} {x} {}", window.scroll);
+            match &window.path {
+                Some(path) => ret.push_str(&format!("{line} {path}\n")),
+                None => ret.push_str(&format!("{line}\n")),
+            }
+        }
+        if let Some(zoomed) = layer.zoomed {
+            ret.push_str(&format!("zoom {zoomed}\n"));
+        }
+    }
+    if let Some((layer, idx)) = session.focus {
+        ret.push_str(&format!("focus {layer} {idx}\n"));
+    }
+    ret
+}
+
+fn parse_line(line: &str, session: &mut Session) -> Result<(), &'static str> {
+    let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let num = |text: Option<&str>| {
+        text.and_then(|text| text.parse::<u32>().ok())
+            .ok_or("invalid number")
+    };
+    if kind == "layer" {
+        let (layout, arrangement) = rest.split_once(' ').unwrap_or((rest, ""));
+        session.layers.push(LayerState {
+            layout: layout.to_string(),
+            arrangement: arrangement.to_string(),
+            zoomed: None,
+            windows: Vec::new(),
+        });
+        return Ok(());
+    }
+    if kind == "focus" {
+        let mut parts = rest.split(' ');
+        let layer = num(parts.next())? as usize;
+        session.focus = Some((layer, num(parts.next())? as usize));
+        return Ok(());
+    }
+    let layer = session
+        .layers
+        .last_mut()
+        .ok_or("window outside of a layer")?;
+    match kind {
+        "window" => {
+            // the path comes last, so it may contain spaces
+            let mut parts = rest.splitn(4, ' ');
+            let y = num(parts.next())?;
+            let x = num(parts.next())?;
+            let scroll = num(parts.next())?;
+            layer.windows.push(Window {
+                path: parts.next().map(|path| path.to_string()),
+                cursor: CursorPosition { x, y },
+                scroll,
+            });
+        }
+        "zoom" => layer.zoomed = Some(num(Some(rest))? as usize),
+        _ => return Err("unknown entry"),
+    }
+    Ok(())
+}
+
+fn parse(text: &str) -> Result<Session, String> {
+    let mut session = Session::default();
+    for (idx, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        parse_line(line, &mut session).map_err(|err| format!("line {}: {err}", idx + 1))?;
+    }
+    Ok(session)
+}
+
+/// writes the current window arrangement to ``path``. An existing file is only replaced with
+/// ``overwrite`` (``:mksession!``)
+pub async fn save(path: &str, overwrite: bool) -> Result<(), String> {
+    if !overwrite && tokio::fs::try_exists(path).await.unwrap_or(false) {
+        return Err(format!("{path} exists (add ! to override)"));
+    }
+    let (layers, focus) = manager::layer_states().await;
+    let layers = layers
+        .into_iter()
+        .map(|layer| LayerState {
+            layout: layer.layout,
+            arrangement: layer.arrangement,
+            zoomed: layer.zoomed,
+            windows: layer
+                .windows
+                .into_iter()
+                .map(|window| Window {
+                    path: window
+                        .doc
+                        .read()
+                        .unwrap()
+                        .path()
+                        .map(|path| path.to_string()),
+                    cursor: window.cursor,
+                    scroll: window.scroll,
+                })
+                .collect(),
+        })
+        .collect();
+    tokio::fs::write(path, format(&Session { layers, focus }))
+        .await
+        .map_err(|err| format!("can't write {path}: {err}"))
+}
+
+/// the window of a session, with its document opened
+async fn open(window: Window) -> Result<WindowState, String> {
+    let doc = match &window.path {
+        // like in vim, a file, that is gone, gets an empty window
+        Some(path) => {
+            io::open_or_create(path)
+                .await
+                .map_err(|err| format!("can't open {path}: {err}"))?
+                .0
+        }
+        None => Document::new_ref(Vec::new(), None),
+    };
+    // the file might have changed since the session was saved
+    let cursor = doc.read().unwrap().clamp(window.cursor);
+    Ok(WindowState {
+        doc,
+        cursor,
+        scroll: window.scroll,
+    })
+}
+
+/// opens the windows of the session in ``path``. Has to happen before any window is opened
+pub async fn restore(path: &str) -> Result<(), String> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|err| format!("can't read {path}: {err}"))?;
+    let session = parse(&text).map_err(|err| format!("{path}: {err}"))?;
+    let mut layers = Vec::with_capacity(session.layers.len());
+    for layer in session.layers {
+        let mut windows = Vec::with_capacity(layer.windows.len());
+        for window in layer.windows {
+            windows.push(open(window).await?);
+        }
+        layers.push(LayerState {
+            layout: layer.layout,
+            arrangement: layer.arrangement,
+            zoomed: layer.zoomed,
+            windows,
+        });
+    }
+    manager::restore_layers(layers, session.focus).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(path: Option<&str>, y: u32, x: u32) -> Window {
+        Window {
+            path: path.map(|path| path.to_string()),
+            cursor: CursorPosition { x, y },
+            scroll: y / 2,
+        }
+    }
+
+    #[test]
+    fn format_and_parse() {
+        let session = Session {
+            layers: vec![
+                LayerState {
+                    layout: "split".to_string(),
+                    arrangement: "v0.5 _ _".to_string(),
+                    zoomed: Some(1),
+                    windows: vec![window(Some("my notes.md"), 12, 4), window(None, 0, 0)],
+                },
+                LayerState {
+                    layout: "floating".to_string(),
+                    arrangement: String::new(),
+                    zoomed: None,
+                    windows: Vec::new(),
+                },
+            ],
+            focus: Some((0, 1)),
+        };
+        let text = format(&session);
+        assert_eq!(
+            text,
+            "layer split v0.5 _ _\nwindow 12 4 6 my notes.md\nwindow 0 0 0\nzoom 1\n\
+             layer floating\nfocus 0 1\n"
+        );
+        assert_eq!(parse(&text), Ok(session));
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        assert_eq!(
+            parse("layer grid\nwindow 1 x 0 a.rs"),
+            Err("line 2: invalid number".to_string())
+        );
+        assert_eq!(
+            parse("window 0 0 0 a.rs"),
+            Err("line 1: window outside of a layer".to_string())
+        );
+        assert!(parse("layer grid\ntab 1").is_err());
+    }
+}
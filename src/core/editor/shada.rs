@@ -0,0 +1,614 @@
+//! # State file
+//! Like vim's viminfo (or neovim's shada), the command and search history, the registers, the
+//! marks (including ``'"``, the last cursor position in a file) and the jump list are written to
+//! a file on exit and read again on startup.
+//!
+//! Every entry is saved with the time it was last changed. On exit, the file is read again and
+//! merged with the state of this editor, where the newer of two entries wins, so several editors
+//! running at once don't throw away each other's state. The file is a plain text file with one
+//! entry per line, its fields are separated by tabs (shown as spaces here):
+//! ```text
+//! 1718000000 history cmd vsplit
+//! 1718000000 register a line first line\nsecond line\n
+//! 1718000000 mark A 12 4 /home/user/notes.md
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use tokio::sync::{Mutex, RwLock};
+
+use super::document::{Document, DocumentRef};
+use super::history::{self, HistoryKind};
+use super::marks;
+use super::registers::{self, Register, RegisterKind};
+use super::CursorPosition;
+use crate::core::io;
+use crate::core::render::manager;
+
+/// the newest entries of the history and jump list are kept, the others are dropped
+const MAX_LIST_ENTRIES: usize = 100;
+/// a lock file older than this was left behind by an editor, that crashed while writing
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+    History {
+        kind: HistoryKind,
+        text: String,
+    },
+    Register {
+        name: char,
+        reg: Register,
+    },
+    GlobalMark {
+        name: char,
+        path: String,
+        pos: CursorPosition,
+    },
+    LocalMark {
+        name: char,
+        path: String,
+        pos: CursorPosition,
+    },
+    Jump {
+        path: String,
+        pos: CursorPosition,
+    },
+}
+
+impl Entry {
+    /// of entries with the same key, only the newest one is kept
+    fn key(&self) -> String {
+        match self {
+            Entry::History { kind, text } => format!("history\t{}\t{text}", kind.name()),
+            Entry::Register { name, .. } => format!("register\t{name}"),
+            Entry::GlobalMark { name, .. } => format!("mark\t{name}"),
+            Entry::LocalMark { name, path, .. } => format!("local\t{name}\t{path}"),
+            Entry::Jump { path, pos } => format!("jump\t{}\t{path}", pos.y),
+        }
+    }
+
+    /// the list the entry belongs to, those are limited to ``MAX_LIST_ENTRIES``
+    fn list(&self) -> Option<&'static str> {
+        match self {
+            Entry::History { kind, .. } => Some(kind.name()),
+            Entry::Jump { .. } => Some("jump"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Stamped {
+    /// seconds since the epoch
+    time: u64,
+    entry: Entry,
+}
+
+static PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(default_path()));
+/// the entries read on startup, to tell which ones were changed since
+static LOADED: Lazy<Mutex<Vec<Stamped>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// ``$XDG_STATE_HOME/neoxide/state``, falling back to ``~/.local/state/neoxide/state``
+fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(dir.join("neoxide").join("state"))
+}
+
+/// changes where the state is kept, ``None`` turns it off
+pub async fn set_path(path: Option<PathBuf>) {
+    *PATH.write().await = path;
+}
+
+fn escape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => ret.push_str("\\\\"),
+            '\t' => ret.push_str("\\t"),
+            '\n' => ret.push_str("\\n"),
+            // a line ending in a CR would lose it, when the file is split into lines
+            '\r' => ret.push_str("\\r"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+fn unescape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => ret.push('\t'),
+            Some('n') => ret.push('\n'),
+            Some('r') => ret.push('\r'),
+            Some(c) => ret.push(c),
+            None => ret.push('\\'),
+        }
+    }
+    ret
+}
+
+fn kind_name(kind: RegisterKind) -> &'static str {
+    match kind {
+        RegisterKind::Charwise => "char",
+        RegisterKind::Linewise => "line",
+        RegisterKind::Blockwise => "block",
+    }
+}
+
+fn format_entry(stamped: &Stamped) -> String {
+    let fields = match &stamped.entry {
+        Entry::History { kind, text } => format!("history\t{}\t{}", kind.name(), escape(text)),
+        Entry::Register { name, reg } => format!(
+            "register\t{name}\t{}\t{}",
+            kind_name(reg.kind),
+            escape(&reg.text)
+        ),
+        Entry::GlobalMark { name, path, pos } => {
+            format!("mark\t{name}\t{}\t{}\t{}", pos.y, pos.x, escape(path))
+        }
+        Entry::LocalMark { name, path, pos } => {
+            format!("local\t{name}\t{}\t{}\t{}", pos.y, pos.x, escape(path))
+        }
+        Entry::Jump { path, pos } => format!("jump\t{}\t{}\t{}", pos.y, pos.x, escape(path)),
+    };
+    format!("{}\t{fields}", stamped.time)
+}
+
+fn format_file(entries: &[Stamped]) -> String {
+    let mut ret = String::from("# neoxide state file, rewritten whenever an editor exits\n");
+    for stamped in entries {
+        ret.push_str(&format_entry(stamped));
+        ret.push('\n');
+    }
+    ret
+}
+
+/// ``None`` for lines, that aren't understood (e.g. written by a newer version)
+fn parse_entry(line: &str) -> Option<Stamped> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let time = fields.first()?.parse().ok()?;
+    let name = |idx: usize| {
+        let mut chars = fields.get(idx)?.chars();
+        let c = chars.next()?;
+        chars.next().is_none().then_some(c)
+    };
+    let pos = |idx: usize| -> Option<CursorPosition> {
+        Some(CursorPosition {
+            y: fields.get(idx)?.parse().ok()?,
+            x: fields.get(idx + 1)?.parse().ok()?,
+        })
+    };
+    let text = |idx: usize| fields.get(idx).map(|field| unescape(field));
+    let entry = match (*fields.get(1)?, fields.len()) {
+        ("history", 4) => Entry::History {
+            kind: HistoryKind::from_name(fields[2])?,
+            text: text(3)?,
+        },
+        ("register", 5) => {
+            let kind = match fields[3] {
+                "char" => RegisterKind::Charwise,
+                "line" => RegisterKind::Linewise,
+                "block" => RegisterKind::Blockwise,
+                _ => return None,
+            };
+            Entry::Register {
+                name: name(2)?,
+                reg: Register::new(text(4)?, kind),
+            }
+        }
+        ("mark", 6) => Entry::GlobalMark {
+            name: name(2)?,
+            pos: pos(3)?,
+            path: text(5)?,
+        },
+        ("local", 6) => Entry::LocalMark {
+            name: name(2)?,
+            pos: pos(3)?,
+            path: text(5)?,
+        },
+        ("jump", 5) => Entry::Jump {
+            pos: pos(2)?,
+            path: text(4)?,
+        },
+        _ => return None,
+    };
+    Some(Stamped { time, entry })
+}
+
+fn parse(text: &str) -> Vec<Stamped> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(parse_entry)
+        .collect()
+}
+
+/// combines the entries of the file with ours. Of entries with the same key the newer one is
+/// kept (ours, if both are equally old) and the lists are cut down to their newest entries
+fn merge(theirs: Vec<Stamped>, ours: Vec<Stamped>) -> Vec<Stamped> {
+    let mut all: Vec<Stamped> = theirs.into_iter().chain(ours).collect();
+    // stable, so ours stay behind theirs with the same time
+    all.sort_by_key(|stamped| stamped.time);
+    let mut seen = HashSet::new();
+    let mut list_lens: HashMap<&'static str, usize> = HashMap::new();
+    let mut ret: Vec<Stamped> = all
+        .into_iter()
+        .rev()
+        .filter(|stamped| {
+            if !seen.insert(stamped.entry.key()) {
+                return false;
+            }
+            match stamped.entry.list() {
+                Some(list) => {
+                    let len = list_lens.entry(list).or_default();
+                    *len += 1;
+                    *len <= MAX_LIST_ENTRIES
+                }
+                None => true,
+            }
+        })
+        .collect();
+    ret.reverse();
+    ret
+}
+
+/// gives every entry the time it was read with, or ``now`` if it's new or was changed. In the
+/// lists, everything behind a new or moved entry counts as new as well, since their order changed
+fn stamp(loaded: &[Stamped], current: Vec<Entry>, now: u64) -> Vec<Stamped> {
+    let mut changed_lists = HashSet::new();
+    let mut list_times: HashMap<&'static str, u64> = HashMap::new();
+    current
+        .into_iter()
+        .map(|entry| {
+            let old = loaded
+                .iter()
+                .find(|stamped| stamped.entry == entry)
+                .map(|stamped| stamped.time);
+            let time = match (entry.list(), old) {
+                (Some(list), Some(time))
+                    if !changed_lists.contains(list)
+                        && time >= list_times.get(list).copied().unwrap_or(0) =>
+                {
+                    list_times.insert(list, time);
+                    time
+                }
+                (Some(list), _) => {
+                    changed_lists.insert(list);
+                    now
+                }
+                (None, Some(time)) => time,
+                (None, None) => now,
+            };
+            Stamped { time, entry }
+        })
+        .collect()
+}
+
+/// reads the state file and restores its content. Marks of files are set once they are opened
+pub async fn load() -> Result<(), String> {
+    let Some(path) = PATH.read().await.clone() else {
+        return Ok(());
+    };
+    let text = match tokio::fs::read_to_string(&path).await {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(format!("can't read {}: {err}", path.display())),
+    };
+    let entries = parse(&text);
+    // the file is sorted by time, so the lists are restored in their order
+    for stamped in entries.iter() {
+        match &stamped.entry {
+            Entry::History { kind, text } => history::add(*kind, text.clone()).await,
+            Entry::Register { name, reg } => registers::restore(*name, reg.clone()).await,
+            Entry::GlobalMark { name, path, pos } => {
+                marks::restore_global(*name, path.clone(), *pos).await
+            }
+            Entry::LocalMark { .. } => {}
+            Entry::Jump { path, pos } => marks::restore_jump(path.clone(), *pos).await,
+        }
+    }
+    *LOADED.lock().await = entries;
+    Ok(())
+}
+
+/// the local marks saved for the file with the absolute ``path``
+fn local_marks<'a>(
+    loaded: &'a [Stamped],
+    path: &'a str,
+) -> impl Iterator<Item = (char, CursorPosition)> + 'a {
+    loaded
+        .iter()
+        .filter_map(move |stamped| match &stamped.entry {
+            Entry::LocalMark {
+                name,
+                path: mark_path,
+                pos,
+            } if mark_path == path => Some((*name, *pos)),
+            _ => None,
+        })
+}
+
+/// sets the marks saved for the file of ``doc`` and returns where the cursor was, when it was
+/// last closed
+pub async fn restore_file(doc: &DocumentRef) -> Option<CursorPosition> {
+    let path = io::absolute_path(doc.read().unwrap().path()?);
+    {
+        let loaded = LOADED.lock().await;
+        let mut doc = doc.write().unwrap();
+        for (name, pos) in local_marks(&loaded, &path) {
+            doc.set_mark(name, pos);
+        }
+    }
+    // the marks of a document closed in this editor are newer than the ones in the file
+    marks::adopt_file_marks(doc).await;
+    let doc = doc.read().unwrap();
+    // the file might have been changed by someone else since
+    Some(doc.clamp(doc.mark('"')?))
+}
+
+/// everything, that is saved in the state file. Paths are saved as absolute ones, since the state
+/// file is shared by editors started in any directory
+async fn collect() -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for kind in [HistoryKind::Command, HistoryKind::Search] {
+        for text in history::entries(kind).await {
+            entries.push(Entry::History { kind, text });
+        }
+    }
+    for (name, reg) in registers::persistent().await {
+        entries.push(Entry::Register { name, reg });
+    }
+    for (name, path, pos) in marks::global_marks().await {
+        let path = io::absolute_path(&path);
+        entries.push(Entry::GlobalMark { name, path, pos });
+    }
+    for (doc, pos) in manager::window_cursors().await {
+        doc.write().unwrap().set_mark('"', pos);
+    }
+    for doc in Document::all() {
+        let doc = doc.read().unwrap();
+        let Some(path) = doc.path().map(io::absolute_path) else {
+            continue;
+        };
+        let mut marks: Vec<_> = doc
+            .marks()
+            .filter(|(name, _)| name.is_ascii_lowercase() || "\"^.".contains(*name))
+            .collect();
+        marks.sort_by_key(|(name, _)| *name);
+        entries.extend(marks.into_iter().map(|(name, pos)| Entry::LocalMark {
+            name,
+            path: path.clone(),
+            pos,
+        }));
+    }
+    for (path, name, pos) in marks::closed_marks() {
+        entries.push(Entry::LocalMark { name, path, pos });
+    }
+    for (path, pos) in marks::jumps().await {
+        let path = io::absolute_path(&path);
+        entries.push(Entry::Jump { path, pos });
+    }
+    entries
+}
+
+/// held while the state file is rewritten, so editors exiting at the same time don't lose each
+/// other's entries
+struct FileLock(PathBuf);
+
+impl FileLock {
+    async fn acquire(path: &Path) -> Result<Self, String> {
+        let lock = path.with_extension("lock");
+        for _ in 0..100 {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock)
+            {
+                Ok(_) => return Ok(FileLock(lock)),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&lock)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .and_then(|mtime| mtime.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK);
+                    if stale {
+                        let _ = std::fs::remove_file(&lock);
+                    } else {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                    }
+                }
+                Err(err) => return Err(format!("can't lock {}: {err}", lock.display())),
+            }
+        }
+        Err(format!("{} is locked", path.display()))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// merges the state of this editor into the state file
+pub async fn save() -> Result<(), String> {
+    let Some(path) = PATH.read().await.clone() else {
+        return Ok(());
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let ours = stamp(&LOADED.lock().await, collect().await, now);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|err| format!("can't create {}: {err}", dir.display()))?;
+    }
+    let _lock = FileLock::acquire(&path).await?;
+    let theirs = match tokio::fs::read_to_string(&path).await {
+        Ok(text) => parse(&text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(format!("can't read {}: {err}", path.display())),
+    };
+    let merged = merge(theirs, ours);
+    // written next to it and renamed, so nobody reads a half written file
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    tokio::fs::write(&tmp, format_file(&merged))
+        .await
+        .map_err(|err| format!("can't write {}: {err}", tmp.display()))?;
+    tokio::fs::rename(&tmp, &path)
+        .await
+        .map_err(|err| format!("can't write {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: u64, entry: Entry) -> Stamped {
+        Stamped { time, entry }
+    }
+
+    fn cmd(text: &str) -> Entry {
+        Entry::History {
+            kind: HistoryKind::Command,
+            text: text.to_string(),
+        }
+    }
+
+    fn reg(name: char, text: &str) -> Entry {
+        Entry::Register {
+            name,
+            reg: Register::new(text, RegisterKind::Charwise),
+        }
+    }
+
+    #[test]
+    fn format_and_parse() {
+        let entries = vec![
+            at(1, cmd("s/a\\tb/\tc")),
+            at(
+                2,
+                Entry::Register {
+                    name: 'a',
+                    reg: Register::new("two\nlines", RegisterKind::Linewise),
+                },
+            ),
+            at(2, reg('b', "dos\r\nline\r")),
+            at(
+                3,
+                Entry::GlobalMark {
+                    name: 'A',
+                    path: "/tmp/a b.txt".to_string(),
+                    pos: CursorPosition { x: 4, y: 12 },
+                },
+            ),
+            at(
+                3,
+                Entry::LocalMark {
+                    name: '"',
+                    path: "notes.md".to_string(),
+                    pos: CursorPosition { x: 0, y: 3 },
+                },
+            ),
+            at(
+                4,
+                Entry::Jump {
+                    path: "notes.md".to_string(),
+                    pos: CursorPosition { x: 1, y: 2 },
+                },
+            ),
+        ];
+        let text = format_file(&entries);
+        assert_eq!(text.lines().count(), entries.len() + 1);
+        assert_eq!(parse(&text), entries);
+        // unknown or broken lines are skipped
+        assert_eq!(parse("5\tfuture\tthing\nx\thistory\tcmd\ta\n"), vec![]);
+    }
+
+    #[test]
+    fn local_marks_from_another_directory() {
+        let root = std::env::temp_dir().join(format!("neoxide-shada-{}", std::process::id()));
+        let (a, b) = (root.join("a"), root.join("b"));
+        for dir in [&a, &b] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("notes.md"), "text\n").unwrap();
+        }
+        // saved by an editor started in ``a``
+        let loaded = vec![at(
+            1,
+            Entry::LocalMark {
+                name: '"',
+                path: io::absolute_path_in("notes.md", &a),
+                pos: CursorPosition { x: 0, y: 3 },
+            },
+        )];
+        // another file with the same name doesn't get them
+        let other = io::absolute_path_in("notes.md", &b);
+        assert_eq!(local_marks(&loaded, &other).count(), 0);
+        let same = io::absolute_path_in("../a/notes.md", &b);
+        assert_eq!(
+            local_marks(&loaded, &same).collect::<Vec<_>>(),
+            vec![('"', CursorPosition { x: 0, y: 3 })]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn newer_entries_win() {
+        let theirs = vec![at(1, reg('a', "old")), at(5, reg('b', "theirs"))];
+        let ours = vec![at(3, reg('a', "ours")), at(3, reg('b', "ours"))];
+        assert_eq!(
+            merge(theirs, ours),
+            vec![at(3, reg('a', "ours")), at(5, reg('b', "theirs"))]
+        );
+        // with the same time, ours is kept
+        assert_eq!(
+            merge(
+                vec![at(2, reg('a', "theirs"))],
+                vec![at(2, reg('a', "ours"))]
+            ),
+            vec![at(2, reg('a', "ours"))]
+        );
+    }
+
+    #[test]
+    fn lists_are_merged_and_limited() {
+        let theirs = (0..MAX_LIST_ENTRIES as u64)
+            .map(|i| at(i, cmd(&format!("theirs {i}"))))
+            .collect();
+        let ours = vec![at(50, cmd("ours")), at(200, cmd("theirs 0"))];
+        let merged = merge(theirs, ours);
+        assert_eq!(merged.len(), MAX_LIST_ENTRIES);
+        assert_eq!(merged.last(), Some(&at(200, cmd("theirs 0"))));
+        assert!(merged.contains(&at(50, cmd("ours"))));
+        // the oldest ones were dropped
+        assert!(!merged.contains(&at(1, cmd("theirs 1"))));
+    }
+
+    #[test]
+    fn only_changed_entries_get_a_new_time() {
+        let loaded = vec![at(1, reg('a', "a")), at(1, cmd("one")), at(2, cmd("two"))];
+        let current = vec![reg('a', "a"), reg('b', "b"), cmd("two"), cmd("one")];
+        assert_eq!(
+            stamp(&loaded, current, 9),
+            vec![
+                at(1, reg('a', "a")),
+                at(9, reg('b', "b")),
+                at(2, cmd("two")),
+                at(9, cmd("one"))
+            ]
+        );
+    }
+}
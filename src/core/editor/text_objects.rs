@@ -0,0 +1,920 @@
+//! # Text objects
+//! The counterpart of ``Motion`` for operators: instead of moving the cursor, a text object
+//! returns the range of text around it (``iw`` is the word under the cursor, ``a(`` the
+//! parentheses around it, ...). ``ObjectScope::Inner`` leaves out the surrounding white space or
+//! delimiters, ``ObjectScope::Around`` includes them.
+use crate::core::{editor::CursorPosition, render::manager::ContentRef};
+
+use super::keymap::{self, ActionArgs};
+use super::mode::{self, Mode};
+use super::motions::{char_class, Flat};
+use super::registers::{self, Operation, Register, RegisterKind};
+use super::visual::{self, Operator, Selection};
+use crate::core::render::manager;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectScope {
+    Inner,
+    Around,
+}
+
+/// the text from ``start`` (inclusive) to ``end`` (exclusive, ``x: 0`` on the next line to include
+/// a line break). Linewise ranges reach from the first to the last of their lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextRange {
+    pub start: CursorPosition,
+    pub end: CursorPosition,
+    /// whole lines, e.g. a paragraph
+    pub linewise: bool,
+}
+
+pub trait TextObject {
+    /// the range of the object at the cursor, ``None`` if there is none (e.g. ``i(`` outside of
+    /// parentheses)
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange>;
+
+    /// ``range`` grown by one more count (``2aw``): by the next object behind it, or for objects,
+    /// that nest, to the one around it. ``None`` if there is none
+    fn extend(
+        &self,
+        buf: impl ContentRef,
+        range: &TextRange,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let behind = match range.linewise {
+            true => CursorPosition {
+                x: 0,
+                y: range.end.y + 1,
+            },
+            false => range.end,
+        };
+        let next = self.get_range(buf, &behind, scope)?;
+        ((next.end.y, next.end.x) > (range.end.y, range.end.x)).then_some(TextRange {
+            end: next.end,
+            ..*range
+        })
+    }
+}
+
+pub struct WordObject; // iw e.g.
+pub struct BigWordObject; // iW e.g.
+pub struct SentenceObject; // is e.g.
+pub struct ParagraphObject; // ip e.g.
+pub struct QuoteObject(pub char); // i" e.g.
+pub struct BracketObject(pub char, pub char); // i( e.g.
+pub struct TagObject; // it e.g.
+
+/// the range of the chars ``start..end`` of ``flat``
+fn char_range(flat: &Flat, start: usize, end: usize) -> TextRange {
+    TextRange {
+        start: flat.pos(start),
+        end: flat.pos(end),
+        linewise: false,
+    }
+}
+
+/// extends ``start..end`` by the white space behind it, or in front of it if there is none
+/// behind, like vim does for the ``a`` objects. Doesn't cross lines
+fn with_white_space(chars: &[char], start: usize, end: usize) -> (usize, usize) {
+    let is_blank = |c: &char| *c == ' ' || *c == '\t';
+    let trailing = chars[end..].iter().take_while(|c| is_blank(c)).count();
+    if trailing > 0 {
+        return (start, end + trailing);
+    }
+    let leading = chars[..start]
+        .iter()
+        .rev()
+        .take_while(|c| is_blank(c))
+        .count();
+    (start - leading, end)
+}
+
+fn word_range(
+    lines: &[String],
+    cursor_position: &CursorPosition,
+    scope: ObjectScope,
+    big: bool,
+) -> Option<TextRange> {
+    let line: Vec<char> = lines.get(cursor_position.y as usize)?.chars().collect();
+    if line.is_empty() {
+        return None;
+    }
+    let x = std::cmp::min(cursor_position.x as usize, line.len() - 1);
+    let class = char_class(line[x], big);
+    let same = |c: &char| char_class(*c, big) == class;
+    let start = x - line[..x].iter().rev().take_while(|c| same(c)).count();
+    let end = x + line[x..].iter().take_while(|c| same(c)).count();
+    let (start, end) = match scope {
+        ObjectScope::Inner => (start, end),
+        // on white space, the word after it is included
+        ObjectScope::Around if class == 0 => {
+            let next = line[end..].first().map_or(0, |c| char_class(*c, big));
+            let word = line[end..]
+                .iter()
+                .take_while(|c| char_class(**c, big) == next)
+                .count();
+            (start, end + word)
+        }
+        ObjectScope::Around => with_white_space(&line, start, end),
+    };
+    let y = cursor_position.y;
+    Some(TextRange {
+        start: CursorPosition { x: start as u32, y },
+        end: CursorPosition { x: end as u32, y },
+        linewise: false,
+    })
+}
+
+impl TextObject for WordObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        word_range(&buf.content(), cursor_position, scope, false)
+    }
+}
+
+impl TextObject for BigWordObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        word_range(&buf.content(), cursor_position, scope, true)
+    }
+}
+
+impl TextObject for SentenceObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let flat = Flat::new(&buf.content());
+        let chars = &flat.chars;
+        let idx = flat.index(cursor_position);
+        // a sentence ends after '.', '!' or '?' followed by white space, or at an empty line
+        let is_end = |i: usize| {
+            matches!(chars[i], '.' | '!' | '?')
+                && chars.get(i + 1).is_none_or(|c| c.is_whitespace())
+        };
+        let is_break = |i: usize| chars[i] == '\n' && chars.get(i + 1).is_none_or(|c| *c == '\n');
+        let mut start = idx;
+        while start > 0 && !(is_end(start - 1) || is_break(start - 1)) {
+            start -= 1;
+        }
+        while start < idx && chars[start].is_whitespace() {
+            start += 1;
+        }
+        let mut end = idx;
+        while end < chars.len() && !is_break(end) {
+            end += 1;
+            if is_end(end - 1) {
+                break;
+            }
+        }
+        if start >= end {
+            return None;
+        }
+        let end = match scope {
+            ObjectScope::Inner => end,
+            ObjectScope::Around => {
+                end + chars[end..]
+                    .iter()
+                    .take_while(|c| **c == ' ' || **c == '\t')
+                    .count()
+            }
+        };
+        Some(char_range(&flat, start, end))
+    }
+}
+
+impl TextObject for ParagraphObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let lines = buf.content();
+        let y = cursor_position.y as usize;
+        let blank = |y: usize| lines[y].trim().is_empty();
+        if y >= lines.len() {
+            return None;
+        }
+        // a paragraph is a run of lines, that are all blank or all not blank
+        let run_end = |from: usize| {
+            let kind = blank(from);
+            (from..lines.len())
+                .find(|&y| blank(y) != kind)
+                .unwrap_or(lines.len())
+        };
+        let kind = blank(y);
+        let start = (0..y)
+            .rev()
+            .find(|&y| blank(y) != kind)
+            .map_or(0, |y| y + 1);
+        let mut end = run_end(y);
+        let mut start = start;
+        if scope == ObjectScope::Around {
+            if end < lines.len() {
+                end = run_end(end);
+            } else if start > 0 {
+                // nothing follows, the blank lines in front of the paragraph are taken instead
+                let prev_kind = blank(start - 1);
+                start = (0..start)
+                    .rev()
+                    .find(|&y| blank(y) != prev_kind)
+                    .map_or(0, |y| y + 1);
+            }
+        }
+        Some(TextRange {
+            start: CursorPosition {
+                x: 0,
+                y: start as u32,
+            },
+            end: CursorPosition {
+                x: lines[end - 1].chars().count() as u32,
+                y: end as u32 - 1,
+            },
+            linewise: true,
+        })
+    }
+}
+
+impl TextObject for QuoteObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let line: Vec<char> = buf
+            .content()
+            .get(cursor_position.y as usize)?
+            .chars()
+            .collect();
+        let x = cursor_position.x as usize;
+        // like vim, quotes are paired up from the start of the line, escaped ones don't count
+        let quotes: Vec<usize> = (0..line.len())
+            .filter(|&i| line[i] == self.0 && (i == 0 || line[i - 1] != '\\'))
+            .collect();
+        let (open, close) = quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|&(open, close)| x <= close && (x >= open || !quotes.iter().any(|&q| q < x)))?;
+        let (start, end) = match scope {
+            ObjectScope::Inner => (open + 1, close),
+            ObjectScope::Around => with_white_space(&line, open, close + 1),
+        };
+        let y = cursor_position.y;
+        Some(TextRange {
+            start: CursorPosition { x: start as u32, y },
+            end: CursorPosition { x: end as u32, y },
+            linewise: false,
+        })
+    }
+
+    /// quotes don't nest, a count adds the quotes to the inner text, like in vim
+    fn extend(
+        &self,
+        buf: impl ContentRef,
+        range: &TextRange,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let line: Vec<char> = buf.content().get(range.start.y as usize)?.chars().collect();
+        let (start, end) = (range.start.x as usize, range.end.x as usize);
+        let quoted = start > 0 && line[start - 1] == self.0 && line.get(end) == Some(&self.0);
+        (scope == ObjectScope::Inner && quoted).then_some(TextRange {
+            start: CursorPosition {
+                x: range.start.x - 1,
+                ..range.start
+            },
+            end: CursorPosition {
+                x: range.end.x + 1,
+                ..range.end
+            },
+            linewise: false,
+        })
+    }
+}
+
+impl BracketObject {
+    /// the flat indices of the brackets around ``idx``. With ``on_close``, a closing bracket at
+    /// ``idx`` is one of them (the cursor is on it), otherwise it belongs to a pair in front
+    fn pair(&self, chars: &[char], idx: usize, on_close: bool) -> Option<(usize, usize)> {
+        let (open, close) = (self.0, self.1);
+        if chars.is_empty() {
+            return None;
+        }
+        // the unmatched opening bracket in front of the cursor (or under it)
+        let mut depth = 0;
+        let mut start = None;
+        for i in (0..=std::cmp::min(idx, chars.len() - 1)).rev() {
+            if chars[i] == close && !(on_close && i == idx) {
+                depth += 1;
+            } else if chars[i] == open {
+                if depth == 0 {
+                    start = Some(i);
+                    break;
+                }
+                depth -= 1;
+            }
+        }
+        let start = start?;
+        let mut depth = 0;
+        let end = (start + 1..chars.len()).find(|&i| {
+            if chars[i] == open {
+                depth += 1;
+            } else if chars[i] == close {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            }
+            false
+        })?;
+        Some((start, end))
+    }
+}
+
+fn bracket_range(flat: &Flat, (start, end): (usize, usize), scope: ObjectScope) -> TextRange {
+    match scope {
+        ObjectScope::Inner => char_range(flat, start + 1, end),
+        ObjectScope::Around => char_range(flat, start, end + 1),
+    }
+}
+
+impl TextObject for BracketObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let flat = Flat::new(&buf.content());
+        let pair = self.pair(&flat.chars, flat.index(cursor_position), true)?;
+        Some(bracket_range(&flat, pair, scope))
+    }
+
+    fn extend(
+        &self,
+        buf: impl ContentRef,
+        range: &TextRange,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let flat = Flat::new(&buf.content());
+        let start = flat.index(&range.start);
+        let open = match scope {
+            ObjectScope::Inner => start.checked_sub(1)?,
+            ObjectScope::Around => start,
+        };
+        let pair = self.pair(&flat.chars, open.checked_sub(1)?, false)?;
+        Some(bracket_range(&flat, pair, scope))
+    }
+}
+
+/// an XML tag, ``<name ...>``, ``</name>`` or ``<name/>``
+struct Tag {
+    start: usize,
+    end: usize,
+    name: String,
+    closing: bool,
+    self_closing: bool,
+}
+
+fn parse_tag(chars: &[char], start: usize) -> Option<Tag> {
+    let end = start + chars[start..].iter().position(|c| *c == '>')? + 1;
+    let inner = &chars[start + 1..end - 1];
+    let closing = inner.first() == Some(&'/');
+    let name: String = inner
+        .iter()
+        .skip(closing as usize)
+        .take_while(|c| !c.is_whitespace() && **c != '/')
+        .collect();
+    if name.is_empty() || !name.chars().next()?.is_alphabetic() {
+        return None;
+    }
+    Some(Tag {
+        start,
+        end,
+        name,
+        closing,
+        self_closing: inner.last() == Some(&'/'),
+    })
+}
+
+/// the pairs of opening and closing tags in ``chars``, from the inside out
+fn tag_pairs(chars: &[char]) -> Vec<(Tag, Tag)> {
+    let mut pairs = Vec::new();
+    let mut open: Vec<Tag> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let tag = match (chars[i] == '<').then(|| parse_tag(chars, i)).flatten() {
+            Some(tag) => tag,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+        i = tag.end;
+        if tag.self_closing {
+            continue;
+        }
+        if !tag.closing {
+            open.push(tag);
+            continue;
+        }
+        // unclosed tags in between (like <br>) are skipped
+        let Some(at) = open.iter().rposition(|o| o.name == tag.name) else {
+            continue;
+        };
+        let opening = open.swap_remove(at);
+        open.truncate(at);
+        // pairs are completed from the inside out
+        pairs.push((opening, tag));
+    }
+    pairs
+}
+
+fn tag_range(flat: &Flat, (opening, closing): &(Tag, Tag), scope: ObjectScope) -> TextRange {
+    match scope {
+        ObjectScope::Inner => char_range(flat, opening.end, closing.start),
+        ObjectScope::Around => char_range(flat, opening.start, closing.end),
+    }
+}
+
+impl TextObject for TagObject {
+    fn get_range(
+        &self,
+        buf: impl ContentRef,
+        cursor_position: &CursorPosition,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let flat = Flat::new(&buf.content());
+        let idx = flat.index(cursor_position);
+        // the first pair around the cursor is the innermost
+        tag_pairs(&flat.chars)
+            .iter()
+            .find(|(opening, closing)| opening.start <= idx && idx < closing.end)
+            .map(|pair| tag_range(&flat, pair, scope))
+    }
+
+    fn extend(
+        &self,
+        buf: impl ContentRef,
+        range: &TextRange,
+        scope: ObjectScope,
+    ) -> Option<TextRange> {
+        let flat = Flat::new(&buf.content());
+        let (start, end) = (flat.index(&range.start), flat.index(&range.end));
+        tag_pairs(&flat.chars).iter().find_map(|pair| {
+            let around = tag_range(&flat, pair, scope);
+            let (a, b) = (flat.index(&around.start), flat.index(&around.end));
+            (a <= start && end <= b && (a, b) != (start, end)).then_some(around)
+        })
+    }
+}
+
+/// the range of ``object`` at the cursor, extended once for every count above 1 (``2aw``). It
+/// stops growing, once there is nothing more to extend it with
+fn counted(
+    object: impl TextObject,
+    buf: impl ContentRef,
+    cursor_position: &CursorPosition,
+    scope: ObjectScope,
+    count: u32,
+) -> Option<TextRange> {
+    let mut range = object.get_range(&buf, cursor_position, scope)?;
+    for _ in 1..count {
+        match object.extend(&buf, &range, scope) {
+            Some(extended) => range = extended,
+            None => break,
+        }
+    }
+    Some(range)
+}
+
+/// the range of the text object typed after ``i``/``a`` (e.g. ``w`` or ``(``) with ``count``,
+/// ``None`` for keys, that aren't text objects
+pub fn range_for(
+    key: char,
+    buf: impl ContentRef,
+    cursor_position: &CursorPosition,
+    scope: ObjectScope,
+    count: u32,
+) -> Option<TextRange> {
+    let pos = cursor_position;
+    match key {
+        'w' => counted(WordObject, buf, pos, scope, count),
+        'W' => counted(BigWordObject, buf, pos, scope, count),
+        's' => counted(SentenceObject, buf, pos, scope, count),
+        'p' => counted(ParagraphObject, buf, pos, scope, count),
+        '"' | '\'' | '`' => counted(QuoteObject(key), buf, pos, scope, count),
+        '(' | ')' | 'b' => counted(BracketObject('(', ')'), buf, pos, scope, count),
+        '[' | ']' => counted(BracketObject('[', ']'), buf, pos, scope, count),
+        '{' | '}' | 'B' => counted(BracketObject('{', '}'), buf, pos, scope, count),
+        '<' | '>' => counted(BracketObject('<', '>'), buf, pos, scope, count),
+        't' => counted(TagObject, buf, pos, scope, count),
+        _ => None,
+    }
+}
+
+/// the keys, that select a text object after ``i``/``a``
+pub const OBJECT_KEYS: &str = "wWsp\"'`()b[]{}B<>t";
+
+/// the range of the text object ``key`` with ``count`` at the cursor of the focused window
+pub async fn focused_range(
+    key: char,
+    scope: ObjectScope,
+    count: u32,
+) -> Result<Option<TextRange>, String> {
+    let focused = manager::focused().await?;
+    let buf = focused.deref().await;
+    let cursor = buf.cursor_position();
+    Ok(range_for(key, &buf, &cursor, scope, count))
+}
+
+/// applies ``op`` to ``range``, e.g. the word of ``diw`` or the text ``dw`` moves over
+pub async fn apply(args: ActionArgs, op: Operator, range: TextRange) -> Result<(), String> {
+    let (start, end) = (range.start, range.end);
+    let kind = if range.linewise {
+        RegisterKind::Linewise
+    } else {
+        RegisterKind::Charwise
+    };
+    let mut reg = None;
+    if op == Operator::Change {
+        // the deletion is part of the undo step of the insert
+        keymap::start_insert_at(start, None).await?;
+    }
+    manager::edit_focused(|doc, cursor| {
+        if op == Operator::Yank {
+            reg = Some(Register::new(visual::text_between(doc, start, end), kind));
+            doc.mark_range(start, end);
+            // a linewise yank keeps the column, like ``yy``
+            if range.linewise {
+                let len = doc
+                    .line(start.y as usize)
+                    .map_or(0, |line| line.chars().count());
+                return CursorPosition {
+                    x: cursor.x.min(len.saturating_sub(1) as u32),
+                    y: start.y,
+                };
+            }
+            return start;
+        }
+        if op == Operator::Delete {
+            doc.begin_undo_step(cursor);
+        }
+        let cursor = if range.linewise && op == Operator::Delete {
+            reg = Some(registers::delete_lines(doc, start.y, end.y - start.y + 1));
+            let y = start.y.min(doc.line_count().saturating_sub(1) as u32);
+            registers::first_non_blank(doc, y)
+        } else {
+            reg = Some(Register::new(doc.remove(start, end), kind));
+            // in normal mode, the cursor stays on a char (e.g. after ``d$``)
+            let len = doc
+                .line(start.y as usize)
+                .map_or(0, |line| line.chars().count());
+            match op {
+                Operator::Delete => CursorPosition {
+                    x: start.x.min(len.saturating_sub(1) as u32),
+                    y: start.y,
+                },
+                _ => start,
+            }
+        };
+        if op == Operator::Delete {
+            doc.end_undo_step();
+        }
+        cursor
+    })
+    .await?;
+    let op = match op {
+        Operator::Yank => Operation::Yank,
+        Operator::Delete | Operator::Change => Operation::Delete,
+    };
+    match reg {
+        Some(reg) => registers::store(args.register, op, reg).await,
+        None => Ok(()),
+    }
+}
+
+/// selects a text object in visual mode, e.g. ``viw``
+pub async fn select(key: char, scope: ObjectScope, count: u32) -> Result<(), String> {
+    let Some(range) = focused_range(key, scope, count).await? else {
+        return Ok(());
+    };
+    let (kind, last) = if range.linewise {
+        (RegisterKind::Linewise, range.end)
+    } else if range.start == range.end {
+        // nothing to select, e.g. ``i(`` in ``()``
+        return Ok(());
+    } else if range.end.x == 0 {
+        // the selection ends on the line break of the line before
+        let y = range.end.y - 1;
+        let len = manager::focused()
+            .await?
+            .deref()
+            .await
+            .document()
+            .read()
+            .unwrap()
+            .line(y as usize)
+            .map_or(0, |line| line.chars().count() as u32);
+        (RegisterKind::Charwise, CursorPosition { x: len, y })
+    } else {
+        let end = CursorPosition {
+            x: range.end.x - 1,
+            y: range.end.y,
+        };
+        (RegisterKind::Charwise, end)
+    };
+    // a linewise object makes the whole selection linewise, like ``vip`` does in vim
+    let kind = match (kind, mode::current().await) {
+        (RegisterKind::Linewise, _) | (_, Mode::VisualLine) => RegisterKind::Linewise,
+        (_, Mode::VisualBlock) => RegisterKind::Blockwise,
+        _ => RegisterKind::Charwise,
+    };
+    if mode::current().await != visual::mode_for(kind) {
+        mode::set(visual::mode_for(kind)).await;
+    }
+    manager::edit_focused(|_, _| last).await?;
+    manager::set_visual(Some(Selection {
+        anchor: range.start,
+        kind,
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+
+    use super::*;
+
+    struct TestBuffer {
+        content: Vec<String>,
+    }
+    impl ContentRef for TestBuffer {
+        fn content(&self) -> impl Deref<Target = Vec<String>> + '_ {
+            &self.content
+        }
+    }
+
+    fn get_content(lines: &[&str]) -> TestBuffer {
+        TestBuffer {
+            content: lines.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    const fn pos(x: u32, y: u32) -> CursorPosition {
+        CursorPosition { x, y }
+    }
+
+    fn range(start: CursorPosition, end: CursorPosition) -> Option<TextRange> {
+        Some(TextRange {
+            start,
+            end,
+            linewise: false,
+        })
+    }
+
+    mod word {
+        use super::*;
+
+        #[test]
+        fn inner_and_around() {
+            let content = get_content(&["foo bar.baz  qux"]);
+            let cursor_position = pos(5, 0);
+            assert_eq!(
+                WordObject.get_range(&content, &cursor_position, ObjectScope::Inner),
+                range(pos(4, 0), pos(7, 0))
+            );
+            assert_eq!(
+                BigWordObject.get_range(&content, &cursor_position, ObjectScope::Inner),
+                range(pos(4, 0), pos(11, 0))
+            );
+            assert_eq!(
+                BigWordObject.get_range(&content, &cursor_position, ObjectScope::Around),
+                range(pos(4, 0), pos(13, 0))
+            );
+        }
+
+        #[test]
+        fn around_takes_leading_space_at_line_end() {
+            let content = get_content(&["foo bar"]);
+            assert_eq!(
+                WordObject.get_range(&content, &pos(6, 0), ObjectScope::Around),
+                range(pos(3, 0), pos(7, 0))
+            );
+        }
+
+        #[test]
+        fn around_on_white_space() {
+            let content = get_content(&["foo  bar"]);
+            assert_eq!(
+                WordObject.get_range(&content, &pos(3, 0), ObjectScope::Around),
+                range(pos(3, 0), pos(8, 0))
+            );
+        }
+
+        #[test]
+        fn empty_line() {
+            let content = get_content(&[""]);
+            assert_eq!(
+                WordObject.get_range(&content, &pos(0, 0), ObjectScope::Inner),
+                None
+            );
+        }
+    }
+
+    mod sentence {
+        use super::*;
+
+        #[test]
+        fn across_lines() {
+            let content = get_content(&["One. Two is", "longer! Three."]);
+            assert_eq!(
+                SentenceObject.get_range(&content, &pos(6, 0), ObjectScope::Inner),
+                range(pos(5, 0), pos(7, 1))
+            );
+            assert_eq!(
+                SentenceObject.get_range(&content, &pos(1, 0), ObjectScope::Around),
+                range(pos(0, 0), pos(5, 0))
+            );
+        }
+    }
+
+    mod paragraph {
+        use super::*;
+
+        #[test]
+        fn inner_and_around() {
+            let content = get_content(&["a", "b", "", "", "c"]);
+            let lines = |start: u32, end: u32, len: u32| {
+                Some(TextRange {
+                    start: pos(0, start),
+                    end: pos(len, end),
+                    linewise: true,
+                })
+            };
+            assert_eq!(
+                ParagraphObject.get_range(&content, &pos(0, 1), ObjectScope::Inner),
+                lines(0, 1, 1)
+            );
+            assert_eq!(
+                ParagraphObject.get_range(&content, &pos(0, 0), ObjectScope::Around),
+                lines(0, 3, 0)
+            );
+            // the last paragraph takes the blank lines in front of it
+            assert_eq!(
+                ParagraphObject.get_range(&content, &pos(0, 4), ObjectScope::Around),
+                lines(2, 4, 1)
+            );
+        }
+    }
+
+    mod quote {
+        use super::*;
+
+        #[test]
+        fn pairs_from_line_start() {
+            let content = get_content(&[r#"a "b" c "d \" e" f"#]);
+            let quote = QuoteObject('"');
+            assert_eq!(
+                quote.get_range(&content, &pos(3, 0), ObjectScope::Inner),
+                range(pos(3, 0), pos(4, 0))
+            );
+            // between two pairs isn't inside of a string
+            assert_eq!(
+                quote.get_range(&content, &pos(6, 0), ObjectScope::Inner),
+                None
+            );
+            assert_eq!(
+                quote.get_range(&content, &pos(9, 0), ObjectScope::Around),
+                range(pos(8, 0), pos(17, 0))
+            );
+            // in front of the first quote, the first string is used
+            assert_eq!(
+                quote.get_range(&content, &pos(0, 0), ObjectScope::Inner),
+                range(pos(3, 0), pos(4, 0))
+            );
+        }
+    }
+
+    mod bracket {
+        use super::*;
+
+        #[test]
+        fn nested_and_multiline() {
+            let content = get_content(&["f(a, (b),", "  c)"]);
+            let parens = BracketObject('(', ')');
+            assert_eq!(
+                parens.get_range(&content, &pos(3, 0), ObjectScope::Inner),
+                range(pos(2, 0), pos(3, 1))
+            );
+            assert_eq!(
+                parens.get_range(&content, &pos(6, 0), ObjectScope::Around),
+                range(pos(5, 0), pos(8, 0))
+            );
+            // on the closing bracket
+            assert_eq!(
+                parens.get_range(&content, &pos(7, 0), ObjectScope::Inner),
+                range(pos(6, 0), pos(7, 0))
+            );
+            assert_eq!(
+                parens.get_range(&content, &pos(0, 0), ObjectScope::Inner),
+                None
+            );
+        }
+    }
+
+    mod tag {
+        use super::*;
+
+        #[test]
+        fn innermost_pair() {
+            let content = get_content(&["<a><b x=\"1\">hi<br/></b>", "</a>"]);
+            assert_eq!(
+                TagObject.get_range(&content, &pos(12, 0), ObjectScope::Inner),
+                range(pos(12, 0), pos(19, 0))
+            );
+            assert_eq!(
+                TagObject.get_range(&content, &pos(12, 0), ObjectScope::Around),
+                range(pos(3, 0), pos(23, 0))
+            );
+            assert_eq!(
+                TagObject.get_range(&content, &pos(1, 1), ObjectScope::Inner),
+                range(pos(3, 0), pos(0, 1))
+            );
+        }
+    }
+
+    mod count {
+        use super::*;
+
+        #[test]
+        fn next_objects() {
+            let content = get_content(&["foo bar baz"]);
+            let words = |scope, count| range_for('w', &content, &pos(1, 0), scope, count);
+            assert_eq!(words(ObjectScope::Around, 2), range(pos(0, 0), pos(8, 0)));
+            // the white space between the words counts as well
+            assert_eq!(words(ObjectScope::Inner, 2), range(pos(0, 0), pos(4, 0)));
+            // there is nothing more after the end of the line
+            assert_eq!(words(ObjectScope::Around, 5), range(pos(0, 0), pos(11, 0)));
+            let content = get_content(&["a", "", "b", "", "c"]);
+            assert_eq!(
+                range_for('p', &content, &pos(0, 0), ObjectScope::Inner, 3),
+                Some(TextRange {
+                    start: pos(0, 0),
+                    end: pos(1, 2),
+                    linewise: true,
+                })
+            );
+        }
+
+        #[test]
+        fn objects_around() {
+            let content = get_content(&["f(a, (b),", "  c)"]);
+            assert_eq!(
+                range_for('(', &content, &pos(6, 0), ObjectScope::Inner, 2),
+                range(pos(2, 0), pos(3, 1))
+            );
+            assert_eq!(
+                range_for('b', &content, &pos(6, 0), ObjectScope::Around, 3),
+                range(pos(1, 0), pos(4, 1))
+            );
+            // the pair in front doesn't count
+            let content = get_content(&["((a)(b))"]);
+            assert_eq!(
+                range_for('(', &content, &pos(5, 0), ObjectScope::Inner, 2),
+                range(pos(1, 0), pos(7, 0))
+            );
+            let content = get_content(&["<a><b x=\"1\">hi<br/></b>", "</a>"]);
+            assert_eq!(
+                range_for('t', &content, &pos(12, 0), ObjectScope::Inner, 2),
+                range(pos(3, 0), pos(0, 1))
+            );
+            assert_eq!(
+                range_for('t', &content, &pos(12, 0), ObjectScope::Around, 2),
+                range(pos(0, 0), pos(4, 1))
+            );
+            let content = get_content(&["say \"hi\" now"]);
+            assert_eq!(
+                range_for('"', &content, &pos(5, 0), ObjectScope::Inner, 2),
+                range(pos(4, 0), pos(8, 0))
+            );
+        }
+    }
+}
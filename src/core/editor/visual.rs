@@ -0,0 +1,436 @@
+//! # Visual mode
+//! Selections made with ``v`` (charwise), ``V`` (linewise) and ``<C-v>`` (blockwise). The
+//! selection reaches from its anchor to the cursor, both inclusive. Operators (``y``, ``d``,
+//! ``c``) act on the selected text and leave visual mode, ``I`` and ``A`` insert the same text on
+//! every line of a block.
+use std::ops::Range;
+
+use super::document::Document;
+use super::keymap::{self, ActionArgs};
+use super::mode::{self, Mode};
+use super::registers::{self, Operation, Register, RegisterKind};
+use super::CursorPosition;
+use crate::core::render::manager;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    /// the end of the selection, that doesn't move with the cursor
+    pub anchor: CursorPosition,
+    pub kind: RegisterKind,
+}
+
+/// the visual mode, that selects ``kind``
+pub fn mode_for(kind: RegisterKind) -> Mode {
+    match kind {
+        RegisterKind::Charwise => Mode::Visual,
+        RegisterKind::Linewise => Mode::VisualLine,
+        RegisterKind::Blockwise => Mode::VisualBlock,
+    }
+}
+
+/// text typed at the top of a block, that is repeated on the lines below once insert mode is
+/// left
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockInsert {
+    /// the lines below the first one
+    pub rows: Range<u32>,
+    pub col: u32,
+    /// ``A`` pads short lines with spaces, ``I`` skips them
+    pub append: bool,
+}
+
+/// inserts ``text`` into all ``block.rows``
+pub fn replicate(doc: &mut Document, block: &BlockInsert, text: &str) {
+    if text.is_empty() || text.contains('\n') {
+        return;
+    }
+    for y in block.rows.clone() {
+        let len = line_len(doc, y);
+        if len < block.col {
+            if !block.append {
+                continue;
+            }
+            let padding = " ".repeat((block.col - len) as usize);
+            doc.insert_str(CursorPosition { x: len, y }, &padding);
+        }
+        doc.insert_str(CursorPosition { x: block.col, y }, text);
+    }
+}
+
+fn line_len(doc: &Document, y: u32) -> u32 {
+    doc.line(y as usize)
+        .map_or(0, |line| line.chars().count() as u32)
+}
+
+/// ``a`` and ``b`` in document order
+pub(crate) fn ordered(a: CursorPosition, b: CursorPosition) -> (CursorPosition, CursorPosition) {
+    if (a.y, a.x) <= (b.y, b.x) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// the position right after the charwise selection from ``start`` to ``end``. A selection, that
+/// reaches past the end of a line, includes the line break
+fn charwise_end(doc: &Document, end: CursorPosition) -> CursorPosition {
+    let len = line_len(doc, end.y);
+    if end.x + 1 > len && (end.y as usize) + 1 < doc.line_count() {
+        CursorPosition { x: 0, y: end.y + 1 }
+    } else {
+        CursorPosition {
+            x: std::cmp::min(end.x + 1, len),
+            y: end.y,
+        }
+    }
+}
+
+/// the text from ``start`` (inclusive) to ``end`` (exclusive)
+pub(crate) fn text_between(doc: &Document, start: CursorPosition, end: CursorPosition) -> String {
+    let mut ret = String::new();
+    for y in start.y..=end.y {
+        let line = doc.line(y as usize).map_or("", |line| line.as_str());
+        let from = if y == start.y { start.x as usize } else { 0 };
+        let to = if y == end.y {
+            end.x as usize
+        } else {
+            usize::MAX
+        };
+        ret.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+        if y != end.y {
+            ret.push('\n');
+        }
+    }
+    ret
+}
+
+/// the columns and lines of a block with the corners ``a`` and ``b``
+fn block(a: CursorPosition, b: CursorPosition) -> (Range<u32>, Range<u32>) {
+    (
+        a.x.min(b.x)..a.x.max(b.x) + 1,
+        a.y.min(b.y)..a.y.max(b.y) + 1,
+    )
+}
+
+/// where the cursor goes after an operator: the top left of the selection
+fn top_left(a: CursorPosition, b: CursorPosition, kind: RegisterKind) -> CursorPosition {
+    match kind {
+        RegisterKind::Blockwise => CursorPosition {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+        },
+        _ => ordered(a, b).0,
+    }
+}
+
+/// the text selected between ``a`` and ``b``
+pub fn selected(
+    doc: &Document,
+    a: CursorPosition,
+    b: CursorPosition,
+    kind: RegisterKind,
+) -> Register {
+    match kind {
+        RegisterKind::Charwise => {
+            let (start, end) = ordered(a, b);
+            Register::new(text_between(doc, start, charwise_end(doc, end)), kind)
+        }
+        RegisterKind::Linewise => {
+            let (start, end) = ordered(a, b);
+            registers::yank_lines(doc, start.y, end.y - start.y + 1)
+        }
+        RegisterKind::Blockwise => {
+            let (cols, rows) = block(a, b);
+            let rows: Vec<String> = rows
+                .map(|y| {
+                    doc.line(y as usize).map_or(String::new(), |line| {
+                        line.chars()
+                            .skip(cols.start as usize)
+                            .take(cols.len())
+                            .collect()
+                    })
+                })
+                .collect();
+            Register::new(rows.join("\n"), kind)
+        }
+    }
+}
+
+/// removes the text selected between ``a`` and ``b``, returns it and the new cursor position
+pub fn delete(
+    doc: &mut Document,
+    a: CursorPosition,
+    b: CursorPosition,
+    kind: RegisterKind,
+) -> (Register, CursorPosition) {
+    let reg = selected(doc, a, b, kind);
+    let cursor = top_left(a, b, kind);
+    match kind {
+        RegisterKind::Charwise => {
+            let (start, end) = ordered(a, b);
+            let end = charwise_end(doc, end);
+            doc.remove(start, end);
+        }
+        RegisterKind::Linewise => {
+            let (start, end) = ordered(a, b);
+            registers::delete_lines(doc, start.y, end.y - start.y + 1);
+            let y = start.y.min(doc.line_count().saturating_sub(1) as u32);
+            return (reg, registers::first_non_blank(doc, y));
+        }
+        RegisterKind::Blockwise => {
+            let (cols, rows) = block(a, b);
+            for y in rows {
+                let len = line_len(doc, y);
+                if cols.start < len {
+                    doc.remove(
+                        CursorPosition { x: cols.start, y },
+                        CursorPosition {
+                            x: cols.end.min(len),
+                            y,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    let len = line_len(doc, cursor.y);
+    let cursor = CursorPosition {
+        x: cursor.x.min(len.saturating_sub(1)),
+        y: cursor.y,
+    };
+    (reg, cursor)
+}
+
+/// empties the selected lines, but keeps one (empty) line to type into, like ``c`` in ``V``
+fn clear_lines(doc: &mut Document, a: CursorPosition, b: CursorPosition) -> Register {
+    let reg = selected(doc, a, b, RegisterKind::Linewise);
+    let (start, end) = ordered(a, b);
+    doc.remove(
+        CursorPosition { x: 0, y: start.y },
+        CursorPosition {
+            x: line_len(doc, end.y),
+            y: end.y,
+        },
+    );
+    reg
+}
+
+/// the selection of the focused window together with the cursor
+async fn current() -> Result<Option<(Selection, CursorPosition)>, String> {
+    let focused = manager::focused().await?;
+    let buf = focused.deref().await;
+    Ok(buf.visual().map(|sel| (sel, buf.cursor_position())))
+}
+
+/// enters the visual mode for ``kind``. In the same visual mode, it's left again, in another
+/// one the selection just changes its kind
+pub async fn toggle(kind: RegisterKind) -> Result<(), String> {
+    let mode = mode::current().await;
+    if mode == mode_for(kind) {
+        return leave().await;
+    }
+    let anchor = match current().await? {
+        Some((sel, _)) if mode.is_visual() => sel.anchor,
+        _ => manager::focused().await?.deref().await.cursor_position(),
+    };
+    mode::set(mode_for(kind)).await;
+    manager::set_visual(Some(Selection { anchor, kind })).await
+}
+
+pub async fn leave() -> Result<(), String> {
+    mode::set(Mode::Normal).await;
+    manager::set_visual(None).await
+}
+
+/// jumps to the other end of the selection (``o``)
+pub async fn swap_ends() -> Result<(), String> {
+    let Some((sel, cursor)) = current().await? else {
+        return Ok(());
+    };
+    manager::edit_focused(|_, _| sel.anchor).await?;
+    manager::set_visual(Some(Selection {
+        anchor: cursor,
+        ..sel
+    }))
+    .await
+}
+
+/// what an operator does with the selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Yank,
+    Delete,
+    /// deletes and starts insert mode in place of the selection
+    Change,
+}
+
+/// applies ``op`` to the selection and leaves visual mode
+pub async fn operate(args: ActionArgs, op: Operator) -> Result<(), String> {
+    let Some((sel, cursor)) = current().await? else {
+        return Ok(());
+    };
+    let (anchor, kind) = (sel.anchor, sel.kind);
+    leave().await?;
+    let mut reg = None;
+    if op == Operator::Change {
+        let start = top_left(anchor, cursor, kind);
+        let start = match kind {
+            RegisterKind::Linewise => CursorPosition { x: 0, y: start.y },
+            _ => start,
+        };
+        let block = (kind == RegisterKind::Blockwise).then(|| BlockInsert {
+            rows: start.y + 1..anchor.y.max(cursor.y) + 1,
+            col: start.x,
+            append: false,
+        });
+        // the deletion is part of the undo step of the insert
+        keymap::start_insert_at(start, block).await?;
+        manager::edit_focused(|doc, _| {
+            reg = Some(match kind {
+                RegisterKind::Linewise => clear_lines(doc, anchor, cursor),
+                _ => delete(doc, anchor, cursor, kind).0,
+            });
+            start
+        })
+        .await?;
+    } else {
+        manager::edit_focused(|doc, _| {
+            if op == Operator::Yank {
+                reg = Some(selected(doc, anchor, cursor, kind));
+                let start = top_left(anchor, cursor, kind);
+                let last = if (anchor.y, anchor.x) < (cursor.y, cursor.x) {
+                    cursor
+                } else {
+                    anchor
+                };
+                doc.mark_range(
+                    start,
+                    CursorPosition {
+                        x: last.x + 1,
+                        ..last
+                    },
+                );
+                return start;
+            }
+            doc.begin_undo_step(cursor);
+            let (deleted, cursor) = delete(doc, anchor, cursor, kind);
+            doc.end_undo_step();
+            reg = Some(deleted);
+            cursor
+        })
+        .await?;
+    }
+    let op = match op {
+        Operator::Yank => Operation::Yank,
+        Operator::Delete | Operator::Change => Operation::Delete,
+    };
+    match reg {
+        Some(reg) => registers::store(args.register, op, reg).await,
+        None => Ok(()),
+    }
+}
+
+/// starts inserting in front of (``I``) or behind (``A``) the block, the text is repeated on all
+/// of its lines
+pub async fn block_insert(append: bool) -> Result<(), String> {
+    let Some((sel, cursor)) = current().await? else {
+        return Ok(());
+    };
+    let (cols, rows) = block(sel.anchor, cursor);
+    leave().await?;
+    let col = if append { cols.end } else { cols.start };
+    let top = CursorPosition {
+        x: col,
+        y: rows.start,
+    };
+    if append {
+        // the first line needs to reach the column as well
+        manager::edit_focused(|doc, _| {
+            let len = line_len(doc, top.y);
+            if len < col {
+                doc.insert_str(
+                    CursorPosition { x: len, y: top.y },
+                    &" ".repeat((col - len) as usize),
+                );
+            }
+            top
+        })
+        .await?;
+    }
+    let block = BlockInsert {
+        rows: rows.start + 1..rows.end,
+        col,
+        append,
+    };
+    keymap::start_insert_at(top, Some(block)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(lines: &[&str]) -> Document {
+        Document::new(lines.iter().map(|line| line.to_string()).collect())
+    }
+
+    const fn pos(x: u32, y: u32) -> CursorPosition {
+        CursorPosition { x, y }
+    }
+
+    #[test]
+    fn selected_text() {
+        let doc = doc(&["hello", "world", "foo"]);
+        let text = |a, b, kind| selected(&doc, a, b, kind).text;
+        assert_eq!(
+            text(pos(3, 1), pos(1, 0), RegisterKind::Charwise),
+            "ello\nworl"
+        );
+        assert_eq!(text(pos(1, 0), pos(5, 0), RegisterKind::Charwise), "ello\n");
+        assert_eq!(
+            text(pos(2, 2), pos(0, 1), RegisterKind::Linewise),
+            "world\nfoo\n"
+        );
+        assert_eq!(
+            text(pos(3, 0), pos(1, 2), RegisterKind::Blockwise),
+            "ell\norl\noo"
+        );
+    }
+
+    #[test]
+    fn delete_selection() {
+        let mut d = doc(&["hello", "world", "foo"]);
+        let (reg, cursor) = delete(&mut d, pos(3, 0), pos(1, 1), RegisterKind::Charwise);
+        assert_eq!(reg.text, "lo\nwo");
+        assert_eq!(cursor, pos(3, 0));
+        assert_eq!(d.lines(), &vec!["helrld", "foo"]);
+
+        let mut d = doc(&["hello", "ab", "world"]);
+        let (reg, cursor) = delete(&mut d, pos(1, 2), pos(3, 0), RegisterKind::Blockwise);
+        assert_eq!(reg.text, "ell\nb\norl");
+        assert_eq!(cursor, pos(1, 0));
+        assert_eq!(d.lines(), &vec!["ho", "a", "wd"]);
+
+        let mut d = doc(&["one", "  two", "three"]);
+        let (_, cursor) = delete(&mut d, pos(2, 0), pos(0, 0), RegisterKind::Linewise);
+        assert_eq!(cursor, pos(2, 0));
+        assert_eq!(d.lines(), &vec!["  two", "three"]);
+    }
+
+    #[test]
+    fn replicate_block_insert() {
+        let mut d = doc(&["abc", "a", "abc"]);
+        let block = BlockInsert {
+            rows: 1..3,
+            col: 2,
+            append: false,
+        };
+        replicate(&mut d, &block, "X");
+        assert_eq!(d.lines(), &vec!["abc", "a", "abXc"]);
+        let block = BlockInsert {
+            append: true,
+            ..block
+        };
+        replicate(&mut d, &block, "Y");
+        assert_eq!(d.lines(), &vec!["abc", "a Y", "abYXc"]);
+    }
+}
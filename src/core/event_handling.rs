@@ -31,6 +31,17 @@ fn get_enum_position<T>(enum_type: T) -> u8 {
     }
 }
 
+/// maps an event to its slot in the ``EventHandler``. The default reads the discriminant of a
+/// fieldless enum, enums with data (where the first byte might be part of the data) have to
+/// implement it themselves
+pub trait EventIndex: Clone {
+    fn event_index(&self) -> u8 {
+        get_enum_position(self.clone())
+    }
+}
+
+impl EventIndex for DemoEvent {}
+
 struct DemoEventData {
     cursor_position: i32,
     cool_string: String,
@@ -67,7 +78,7 @@ impl<E, D> EventCallback<E, D> {
 
 pub struct EventHandler<E, D>
 where
-    E: EnumCount + Clone + Send,
+    E: EnumCount + EventIndex + Send,
     D: Send,
 {
     subscriptions: Mutex<Vec<HashMap<u32, EventCallback<E, D>>>>,
@@ -75,7 +86,7 @@ where
 
 impl<E, D> EventHandler<E, D>
 where
-    E: EnumCount + Clone + Send + 'static,
+    E: EnumCount + EventIndex + Send + 'static,
     D: Send + 'static,
 {
     pub fn new() -> EventHandler<E, D> {
@@ -94,7 +105,7 @@ where
     /// returns: randomly generated id which can be used to remove the callback in the future
     pub async fn subscribe(&self, event_callback: EventCallback<E, D>) -> u32 {
         let mut lock = self.subscriptions.lock().await;
-        let enum_idx = get_enum_position(event_callback.event.clone());
+        let enum_idx = event_callback.event.event_index();
         logger::log(LogLevel::Debug, format!("Enum pos is {enum_idx}").as_str()).await;
         let callback_map = lock
             .get_mut(enum_idx as usize)
@@ -107,7 +118,7 @@ where
 
     pub async fn unsubscribe(&self, event: E, id: u32) -> Result<(), &str> {
         let mut lock = self.subscriptions.lock().await;
-        let enum_idx = get_enum_position(event);
+        let enum_idx = event.event_index();
         let callback_map = lock
             .get_mut(enum_idx as usize)
             .expect(&format!("unsafe code not so good (sub;{enum_idx})"));
@@ -132,7 +143,7 @@ where
         let lock = self.subscriptions.lock().await;
         // let data = Arc::new(Mutex::new(data));
         let callback_map = lock
-            .get(get_enum_position(event) as usize)
+            .get(event.event_index() as usize)
             .expect("unsafe code not so good (dispatch)");
         // I think the rustaceans consider this to be more idiomatic
         logger::log(LogLevel::Debug, "Starting to execute callbacks").await;
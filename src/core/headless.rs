@@ -0,0 +1,128 @@
+//! # Headless mode
+//! ``neoxide --headless`` edits files without a terminal, for scripts and tests:
+//! ```text
+//! neoxide --headless -c "normal ggdd" -c "wq" notes.md
+//! ```
+//! The files are opened like usual, then the ``-c`` commands run in order on the same editing
+//! engine, ``:normal`` feeds keys and ``:source`` runs a whole file of commands. Nothing gets
+//! drawn, the screen has a fixed size and the state file is neither read nor written, so a
+//! script behaves the same on every machine. The editor exits after the last command or when
+//! one of them quits; a failing command ends it with an error.
+use super::editor::commands;
+#[cfg(test)]
+use super::editor::document::{Document, DocumentRef};
+use super::input;
+use super::terminal;
+
+/// switches to headless mode. Has to happen before the first window is opened
+pub fn enter() {
+    terminal::set_headless(true);
+}
+
+/// runs ``commands`` one after another, until one fails or quits the editor
+pub async fn run(commands: &[String]) -> Result<(), String> {
+    for command in commands.iter() {
+        commands::execute(command)
+            .await
+            .map_err(|msg| format!("{command}: {msg}"))?;
+        if input::quit_requested() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// whether the window of ``test_window`` is open. Its lock keeps the tests, that edit through the
+/// focused window, from running at the same time
+#[cfg(test)]
+static TEST_WINDOW: tokio::sync::Mutex<bool> = tokio::sync::Mutex::const_new(false);
+
+/// shows a new document with ``lines`` in the focused window, in normal mode with the cursor at
+/// the start. The tests share this window (opened by the first one), which is only theirs as long
+/// as they hold the returned guard
+#[cfg(test)]
+pub(crate) async fn test_window(
+    lines: &[&str],
+) -> (DocumentRef, tokio::sync::MutexGuard<'static, bool>) {
+    use super::editor::mode::{self, Mode};
+    use super::editor::CursorPosition;
+    use super::render::manager::{self, ClientBuffer};
+
+    let mut opened = TEST_WINDOW.lock().await;
+    enter();
+    mode::set(Mode::Normal).await;
+    let lines = lines.iter().map(|line| line.to_string()).collect();
+    let doc = Document::new_ref(lines, None);
+    if *opened {
+        manager::show_document(doc.clone(), CursorPosition { x: 0, y: 0 })
+            .await
+            .unwrap();
+    } else {
+        let buf = ClientBuffer::build_with_document(0, true, doc.clone())
+            .await
+            .unwrap();
+        buf.focus().await.unwrap();
+        manager::adopt(buf).await;
+        *opened = true;
+    }
+    (doc, opened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::editor::CursorPosition;
+    use crate::core::io;
+    use crate::core::render::manager::{self, ClientBuffer};
+    use std::sync::Arc;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn edit_and_write() {
+        let (doc, _window) = test_window(&["one", "two", "three"]).await;
+        let path = std::env::temp_dir().join(format!("neoxide-headless-{}", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let script = [
+            "normal jddp".to_string(),
+            "normal gg$a!<Esc>".to_string(),
+            format!("write {path}"),
+        ];
+        run(&script).await.unwrap();
+        assert_eq!(
+            doc.read().unwrap().lines(),
+            &lines(&["one!", "three", "two"])
+        );
+        assert!(!doc.read().unwrap().is_modified());
+        assert_eq!(
+            io::read_file(&path).await.unwrap(),
+            "one!\nthree\ntwo\n".to_string()
+        );
+        let _ = std::fs::remove_file(&path);
+        assert!(run(&["frobnicate".to_string()]).await.is_err());
+        // a second window on the document keeps its cursor on the same line
+        let other = ClientBuffer::build_with_document(0, true, doc.clone())
+            .await
+            .unwrap();
+        other
+            .set_cursor_position(CursorPosition { x: 1, y: 2 })
+            .await;
+        manager::adopt(other).await;
+        run(&["normal ggdd".to_string()]).await.unwrap();
+        // the focused window comes last
+        let other_cursor = || async {
+            manager::window_cursors()
+                .await
+                .into_iter()
+                .find(|(shown, _)| Arc::ptr_eq(shown, &doc))
+                .unwrap()
+                .1
+        };
+        assert_eq!(other_cursor().await, CursorPosition { x: 1, y: 1 });
+        // its line is deleted, the cursor must not end up past the end
+        run(&["normal jdd".to_string()]).await.unwrap();
+        assert_eq!(other_cursor().await.y, 0);
+    }
+}
@@ -1,6 +1,7 @@
 use crossterm::event::{poll, KeyEvent, KeyModifiers};
 use std::future::Future;
 use std::io::{stdout, Result as IoResult, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
@@ -9,7 +10,7 @@ use tokio::sync::Mutex;
 use once_cell::sync::Lazy;
 use strum::EnumCount;
 
-use super::event_handling::{EventCallback, EventHandler};
+use super::event_handling::{EventCallback, EventHandler, EventIndex};
 use super::logger::{self, LogLevel};
 use super::render;
 use crossterm::{
@@ -30,8 +31,34 @@ impl Clone for InputEvent {
     }
 }
 
+// the first byte of ``Event`` isn't always the discriminant, e.g. for ``Paste`` it's part of the
+// string
+impl EventIndex for InputEvent {
+    fn event_index(&self) -> u8 {
+        match self.0 {
+            Event::FocusGained => 0,
+            Event::FocusLost => 1,
+            Event::Key(_) => 2,
+            Event::Mouse(_) => 3,
+            Event::Paste(_) => 4,
+            Event::Resize(_, _) => 5,
+        }
+    }
+}
+
 pub struct EvtData(pub Event);
 static INPUT_EVH: Lazy<EventHandler<InputEvent, EvtData>> = Lazy::new(EventHandler::new);
+/// set by ``:quit``, the input loop ends after the current event
+static QUIT: AtomicBool = AtomicBool::new(false);
+
+/// makes the editor exit, once the current event is handled
+pub fn quit() {
+    QUIT.store(true, Ordering::Relaxed);
+}
+
+pub fn quit_requested() -> bool {
+    QUIT.load(Ordering::Relaxed)
+}
 
 pub async fn subscribe(evcb: EventCallback<InputEvent, EvtData>) -> u32 {
     INPUT_EVH.subscribe(evcb).await
@@ -60,6 +87,7 @@ fn get_next_event() -> InputFuture {
     InputFuture
 }
 
+#[derive(Debug, Clone, Copy, Default)]
 pub struct InputConfig {
     pub bracketed_paste: bool,
     pub focus_change: bool,
@@ -75,16 +103,8 @@ fn set_opt(opt: bool, enable_com: impl Command, disable_com: impl Command) -> Io
     Ok(())
 }
 
-use crossterm::event::KeyCode;
-
-/// The main loop, that will transmit all InputEvents over the Event Handling system.
-/// This function needs to be only called once on initialization (maybe I should write some code to
-/// prevent calling it multiple times) and should live in it's own tokio task. This function
-/// follows the fail-fast principle, and returns on the first IO error it sees, which means that
-/// once crossterm breaks, you can't send keypresses etc. anymore
-/// All Events are directly transferred to the dedicated Event Handler, provided through a
-/// newtype pattern, which implements the Clone- and EnumCount traits for the events
-pub async fn input_loop(config: InputConfig) -> IoResult<()> {
+/// enables the optional input events of ``config`` and disables the others
+pub(crate) fn apply_config(config: &InputConfig) -> IoResult<()> {
     set_opt(
         config.bracketed_paste,
         EnableBracketedPaste,
@@ -96,8 +116,21 @@ pub async fn input_loop(config: InputConfig) -> IoResult<()> {
         EnableMouseCapture,
         DisableMouseCapture,
     )?;
-    stdout().flush()?;
+    stdout().flush()
+}
+
+use crossterm::event::KeyCode;
 
+/// The main loop, that will transmit all InputEvents over the Event Handling system.
+/// This function needs to be only called once on initialization (maybe I should write some code to
+/// prevent calling it multiple times) and should live in it's own tokio task. This function
+/// follows the fail-fast principle, and returns on the first IO error it sees, which means that
+/// once crossterm breaks, you can't send keypresses etc. anymore
+/// All Events are directly transferred to the dedicated Event Handler, provided through a
+/// newtype pattern, which implements the Clone- and EnumCount traits for the events.
+/// The optional events (mouse, paste, focus) are enabled by
+/// [`TerminalSession`](super::terminal::TerminalSession)
+pub async fn input_loop() -> IoResult<()> {
     loop {
         // NOTE: look into streams for this
         let evt = read()?;
@@ -109,16 +142,28 @@ pub async fn input_loop(config: InputConfig) -> IoResult<()> {
                 modifiers: KeyModifiers::CONTROL,
                 ..
             }) => break,
-            Event::Key(KeyEvent {
-                code: KeyCode::Char(c),
-                ..
-            }) if c >= '0' && c <= '9' => {}
             Event::Resize(_, _) => render::manager::dispatch_resize().await,
             evt => {
                 let evt = InputEvent(evt);
                 INPUT_EVH.dispatch(evt, evt_data).await;
             }
         }
+        if quit_requested() {
+            break;
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_with_data_get_their_own_slot() {
+        let paste = InputEvent(Event::Paste("pasted text".to_string()));
+        assert_eq!(paste.event_index(), 4);
+        assert_eq!(InputEvent(Event::FocusGained).event_index(), 0);
+        assert!(paste.event_index() < InputEvent::COUNT as u8);
+    }
+}
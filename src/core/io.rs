@@ -6,10 +6,17 @@ use tokio::{
 };
 
 use super::{
+    editor::document::{Document, DocumentRef},
+    editor::fileformat::{self, Encoding, FileFormat},
+    editor::shada,
+    editor::CursorPosition,
     logger::{self, LogLevel},
-    render::ClientBuffer,
+    render::{manager, ClientBuffer},
 };
 use std::io::Result;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 pub async fn read(reader: impl AsyncReadExt, start_size: usize) -> std::io::Result<String> {
     let mut ret = String::with_capacity(start_size);
@@ -28,10 +35,15 @@ pub async fn read_n_bytes(
     Ok(ret)
 }
 
+/// reads ``file_name`` in whatever encoding it is in, see ``fileformat::decode``
 pub async fn read_file(file_name: &str) -> std::io::Result<String> {
-    let file = File::open(file_name).await?;
-    let size = file.metadata().await.unwrap().len() as usize;
-    read(file, size).await
+    Ok(read_file_encoded(file_name).await?.0)
+}
+
+/// like ``read_file``, but also returns the encoding of the file
+pub async fn read_file_encoded(file_name: &str) -> std::io::Result<(String, Encoding)> {
+    let bytes = tokio::fs::read(file_name).await?;
+    Ok(fileformat::decode(&bytes))
 }
 
 pub async fn read_n_bytes_from_file(
@@ -44,20 +56,133 @@ pub async fn read_n_bytes_from_file(
     read_n_bytes(file, bytes_to_read).await
 }
 
+/// ``path`` as an absolute path, with symlinks and ``..`` resolved if the file exists. Relative
+/// paths are relative to ``cwd``
+pub fn absolute_path_in(path: &str, cwd: &Path) -> String {
+    let joined = cwd.join(path);
+    std::fs::canonicalize(&joined)
+        .unwrap_or(joined)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// ``path`` as an absolute path, so it still names the same file when the editor is started in
+/// another directory (e.g. in the state file)
+pub fn absolute_path(path: &str) -> String {
+    match std::env::current_dir() {
+        Ok(cwd) => absolute_path_in(path, &cwd),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// whether both paths name the same file, no matter how they were typed
+pub fn same_file(path: &str, other: &str) -> bool {
+    path == other || absolute_path(path) == absolute_path(other)
+}
+
+/// the document of ``file_name``, read from disk unless it is already open. A freshly read
+/// document comes with the cursor position from the last time the file was open
+pub async fn open_document(
+    file_name: &str,
+) -> std::io::Result<(DocumentRef, Option<CursorPosition>)> {
+    if let Some(doc) = Document::find_by_path(file_name) {
+        return Ok((doc, None));
+    }
+    let doc = Document::new_ref(Vec::new(), Some(file_name.to_string()));
+    reload(&doc).await?;
+    // marks and the cursor position from the last time the file was open
+    let cursor = shada::restore_file(&doc).await;
+    Ok((doc, cursor))
+}
+
+/// like ``open_document``, but a file, that doesn't exist (yet), gets an empty document, which
+/// creates it when written
+pub async fn open_or_create(
+    file_name: &str,
+) -> std::io::Result<(DocumentRef, Option<CursorPosition>)> {
+    match open_document(file_name).await {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok((
+            Document::new_ref(Vec::new(), Some(file_name.to_string())),
+            None,
+        )),
+        res => res,
+    }
+}
+
+/// opens ``file_name`` in a new window. If the file is already open, the new window shows the
+/// same document instead of reading the file again
 pub async fn open_file(file_name: &str) -> std::io::Result<ClientBuffer> {
-    let mut c = ClientBuffer::build_on_tiled(2).await;
-    while let Err(_) = c {
+    let (doc, cursor) = open_document(file_name).await?;
+    let mut c = ClientBuffer::build_with_document(2, true, Arc::clone(&doc)).await;
+    while c.is_err() {
         // this is life now
-        c = ClientBuffer::build_on_tiled(2).await;
+        c = ClientBuffer::build_with_document(2, true, Arc::clone(&doc)).await;
     }
-    let mut c = c.unwrap();
-    if let Err(msg) = c.set_content(read_file(file_name).await?).await {
+    let c = c.unwrap();
+    if let Some(pos) = cursor {
+        c.set_cursor_position(pos).await;
+    }
+    if let Err(msg) = c.rerender().await {
         logger::log(LogLevel::Error, &msg).await;
         return Err(std::io::ErrorKind::Other.into());
     }
     Ok(c)
 }
 
+/// modification time of ``file_name``, ``None`` if the file system doesn't track it
+pub async fn mtime(file_name: &str) -> std::io::Result<Option<SystemTime>> {
+    Ok(tokio::fs::metadata(file_name).await?.modified().ok())
+}
+
+/// reads the file of ``doc`` again, replacing the content (and the undo history)
+pub async fn reload(doc: &DocumentRef) -> std::io::Result<()> {
+    let path = doc
+        .read()
+        .unwrap()
+        .path()
+        .map(|path| path.to_string())
+        .ok_or(std::io::ErrorKind::NotFound)?;
+    // the mtime is taken first, a change while reading is detected the next time
+    let mtime = mtime(&path).await?;
+    let (content, encoding) = read_file_encoded(&path).await?;
+    // the cursors of the windows showing it have to stay on the new content
+    manager::edit_document(doc, |doc| {
+        doc.set_content(&content);
+        let format = FileFormat {
+            encoding,
+            ..doc.format()
+        };
+        doc.set_format(format);
+        doc.set_modified(false);
+        doc.set_disk_mtime(mtime);
+    })
+    .await;
+    Ok(())
+}
+
+/// writes the content of ``doc`` to ``file_name``, in the format of the document. Writing the
+/// file of the document itself marks it as unmodified
+pub async fn write_document(doc: &DocumentRef, file_name: &str) -> std::io::Result<()> {
+    let bytes = {
+        let doc = doc.read().unwrap();
+        let format = doc.format();
+        fileformat::encode(
+            &fileformat::join_lines(doc.lines(), &format),
+            format.encoding,
+        )
+        .map_err(|msg| std::io::Error::new(std::io::ErrorKind::InvalidData, msg))?
+    };
+    tokio::fs::write(file_name, bytes).await?;
+    let mtime = mtime(file_name).await?;
+    let mut doc = doc.write().unwrap();
+    if doc.path().is_some_and(|path| same_file(path, file_name)) {
+        doc.set_modified(false);
+        // our own write shouldn't look like a change by someone else
+        doc.set_disk_mtime(mtime);
+    }
+    Ok(())
+}
+
 pub struct OpenFileBuffer {
     cl: ClientBuffer,
     file_handle: File,
@@ -81,3 +206,28 @@ impl OpenFileBuffer {
 //     writer.write_all_buf(&mut buf);
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_the_format() {
+        let path = std::env::temp_dir().join(format!("neoxide-format-{}", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        tokio::fs::write(&path, b"caf\xE9\r\nend").await.unwrap();
+        let doc = Document::new_ref(Vec::new(), Some(path.clone()));
+        reload(&doc).await.unwrap();
+        assert_eq!(
+            doc.read().unwrap().lines(),
+            &vec!["café".to_string(), "end".to_string()]
+        );
+        write_document(&doc, &path).await.unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), b"caf\xE9\r\nend");
+        // converted on save
+        doc.write().unwrap().set_format(FileFormat::default());
+        write_document(&doc, &path).await.unwrap();
+        assert_eq!(read_file(&path).await.unwrap(), "café\nend\n");
+        let _ = std::fs::remove_file(&path);
+    }
+}
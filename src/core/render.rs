@@ -7,7 +7,11 @@ use crossterm::{cursor::MoveTo, queue, style::Print};
 // })
 
 mod border;
+pub mod cursor;
+pub mod gutter;
 pub mod manager;
+pub mod statusline;
+pub mod theme;
 
 pub use manager::ClientBuffer;
 
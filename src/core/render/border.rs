@@ -1,7 +1,10 @@
 use crate::core::logger::{self, LogLevel};
 
-use super::manager::{Buffer, BufferBorder, RenderBuffer};
+use super::gutter::Gutter;
+use super::manager::{ANSICode, Buffer, BufferBorder, RenderBuffer};
+use super::theme::{self, Style};
 use std::cmp::min;
+use std::collections::HashMap;
 use std::fmt::Display;
 // TODO: change to unicode
 pub const HBORDER: &str = "─";
@@ -22,7 +25,7 @@ async fn write_str(render_buf: &mut RenderBuffer, params: &mut WriteLineParams<'
             params.line, // may need to also consider padding
         )
         .await;
-    params.offx += params.line.len();
+    params.offx += params.line.chars().count();
 }
 
 #[inline]
@@ -82,7 +85,8 @@ async fn write_padding<'a>(
     let blank = PADDING.repeat(pad as usize);
     let mut temp_padding = WriteLineParams {
         line: &blank,
-        ..*params
+        selection: None,
+        ..params.clone()
     };
     write_str(render_buf, &mut temp_padding).await;
     params.offx = temp_padding.offx;
@@ -95,6 +99,38 @@ async fn write_line_with_padding(render_buf: &mut RenderBuffer, params: &mut Wri
 
     write_padding(render_buf, params, params.border.lpad).await;
 
+    if !params.gutter.is_empty() {
+        for (text, style) in params.gutter {
+            let y = params.offy;
+            render_buf
+                .add_ctrl_code(ANSICode::Reset, params.offx, y, params.term_width)
+                .await;
+            for code in style.codes() {
+                render_buf
+                    .add_ctrl_code(code, params.offx, y, params.term_width)
+                    .await;
+            }
+            render_buf
+                .write_str(params.offx, y, params.term_width, text)
+                .await;
+            params.offx += text.chars().count();
+        }
+        render_buf
+            .add_ctrl_code(ANSICode::Reset, params.offx, params.offy, params.term_width)
+            .await;
+    }
+
+    if let Some((range, style)) = params.selection.as_ref() {
+        let (start, end) = (params.offx + range.start, params.offx + range.end);
+        for code in style.codes() {
+            render_buf
+                .add_ctrl_code(code, start, params.offy, params.term_width)
+                .await;
+        }
+        render_buf
+            .add_ctrl_code(ANSICode::Reset, end, params.offy, params.term_width)
+            .await;
+    }
     write_str(render_buf, params).await;
 
     write_padding(render_buf, params, params.border.rpad).await;
@@ -121,6 +157,26 @@ fn create_line(params: &CreateLineParams) -> String {
     ret
 }
 
+/// looks up the styles of the gutter pieces, ``cache`` avoids asking the theme for every line
+async fn gutter_styles(
+    pieces: Vec<(String, String)>,
+    cache: &mut HashMap<String, Style>,
+) -> Vec<(String, Style)> {
+    let mut ret = Vec::with_capacity(pieces.len());
+    for (text, group) in pieces {
+        let style = match cache.get(&group) {
+            Some(style) => *style,
+            None => {
+                let style = theme::get(&group).await;
+                cache.insert(group, style);
+                style
+            }
+        };
+        ret.push((text, style));
+    }
+    ret
+}
+
 impl Buffer {
     pub async fn render(&self, term_width: u16, render_buf: &mut RenderBuffer) {
         let content = self.to_string();
@@ -158,12 +214,18 @@ impl Buffer {
                     term_width,
                     width_without_border,
                     line: &hborder,
+                    gutter: &[],
+                    selection: None,
                     border,
                     borders_shown,
                 };
 
                 let code_offy = offy + border.tpad as usize + if borders_shown[1] { 1 } else { 0 };
-                let code_offx = offx + border.lpad as usize + if borders_shown[0] { 1 } else { 0 };
+                let gutter_width = self.gutter_width();
+                let code_offx = offx
+                    + border.lpad as usize
+                    + gutter_width as usize
+                    + if borders_shown[0] { 1 } else { 0 };
                 for (code, pos) in self.ctrl_codes() {
                     logger::log(
                         LogLevel::Debug,
@@ -191,34 +253,54 @@ impl Buffer {
                     write_line_without_padding(render_buf, &mut params).await;
                 }
 
-                let mut i = 0;
-                let mut skip_newline = false;
-                let content_width =
-                    (width_without_border - params.border.lpad - params.border.rpad - hborders)
-                        as usize;
-                while i < content.len()
-                    && params.offy
-                        <= (orig_offy as u16 + height
-                            - params.border.dpad
-                            - if borders_shown[2] { 1 } else { 0 })
-                            as usize
-                {
-                    let end_idx = if let Some(newline_idx) = content[i..].find('\n') {
-                        skip_newline = true;
-                        i + newline_idx
-                    } else {
-                        content.len()
-                    };
-                    let end_idx = min(i + content_width, end_idx);
-                    if end_idx == i + content_width {
-                        skip_newline = false;
+                let content_width = self.text_width() as usize;
+                let text_height = self.text_height() as usize;
+                let (signs, line_count) = {
+                    let doc = self.document().read().unwrap();
+                    (doc.signs().to_vec(), doc.line_count())
+                };
+                let gutter =
+                    Gutter::new(self.gutter(), &signs, line_count, self.cursor_position().y);
+                let mut gutter_cache = HashMap::new();
+                let visual = theme::get("Visual").await;
+                let mut rows = 0;
+                // lines, that are too long, get wrapped onto the next screen line
+                'lines: for (line_no, line) in (self.scroll()..).zip(content.lines()) {
+                    let chars: Vec<char> = line.chars().collect();
+                    let mut chunks: Vec<String> = chars
+                        .chunks(content_width)
+                        .map(|chunk| chunk.iter().collect())
+                        .collect();
+                    if chunks.is_empty() {
+                        chunks.push(String::new());
                     }
-                    params.line = &content[i..end_idx];
-                    write_line_with_padding(render_buf, &mut params).await;
-                    i += end_idx - i;
-                    if skip_newline {
-                        skip_newline = false;
-                        i += 1;
+                    let selected = self.selection_on_line(line_no);
+                    for (i, chunk) in chunks.iter().enumerate() {
+                        if rows >= text_height {
+                            break 'lines;
+                        }
+                        // only the first screen line of a document line gets a line number etc.
+                        let doc_line = (i == 0).then_some(line_no);
+                        let pieces = gutter_styles(gutter.line(doc_line), &mut gutter_cache).await;
+                        // the part of the selection, that is on this screen line
+                        let chunk_start = i * content_width;
+                        let selection = selected
+                            .as_ref()
+                            .map(|sel| {
+                                sel.start.max(chunk_start) - chunk_start
+                                    ..sel.end.min(chunk_start + content_width + 1) - chunk_start
+                            })
+                            .filter(|sel| sel.start < sel.end)
+                            .map(|sel| (sel, visual));
+                        let mut line_params = WriteLineParams {
+                            line: chunk,
+                            gutter: &pieces,
+                            selection,
+                            ..params.clone()
+                        };
+                        write_line_with_padding(render_buf, &mut line_params).await;
+                        (params.offx, params.offy) = (line_params.offx, line_params.offy);
+                        rows += 1;
                     }
                 }
 
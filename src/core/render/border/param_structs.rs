@@ -1,4 +1,6 @@
 use super::*;
+use crate::core::render::theme::Style;
+use std::ops::Range;
 
 #[derive(Clone, Debug)]
 pub struct WriteLineParams<'a> {
@@ -8,6 +10,10 @@ pub struct WriteLineParams<'a> {
     pub term_width: u16,
     pub width_without_border: u16,
     pub line: &'a str,
+    /// written between the left padding and the line
+    pub gutter: &'a [(String, Style)],
+    /// chars of the line, that are highlighted as selected
+    pub selection: Option<(Range<usize>, Style)>,
     pub border: &'a BufferBorder,
     pub borders_shown: [bool; 4],
 }
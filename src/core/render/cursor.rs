@@ -0,0 +1,79 @@
+//! # Cursor
+//! The shape of the terminal cursor depends on the mode the editor is in, like vim's
+//! ``guicursor``. The shape the user had before is restored, when the editor exits.
+use std::collections::HashMap;
+use std::io::{self, stdout};
+
+use crossterm::{cursor::SetCursorStyle, execute};
+use once_cell::sync::Lazy;
+use tokio::sync::RwLock;
+
+use crate::core::editor::mode::Mode;
+use crate::core::terminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    Block,
+    Bar,
+    Underline,
+}
+
+impl CursorShape {
+    fn style(&self) -> SetCursorStyle {
+        match self {
+            CursorShape::Block => SetCursorStyle::SteadyBlock,
+            CursorShape::Bar => SetCursorStyle::SteadyBar,
+            CursorShape::Underline => SetCursorStyle::SteadyUnderScore,
+        }
+    }
+}
+
+static SHAPES: Lazy<RwLock<HashMap<Mode, CursorShape>>> = Lazy::new(|| {
+    RwLock::new(HashMap::from([
+        (Mode::Normal, CursorShape::Block),
+        (Mode::Insert, CursorShape::Bar),
+        (Mode::CommandLine, CursorShape::Bar),
+        (Mode::OperatorPending, CursorShape::Underline),
+    ]))
+});
+
+pub async fn shape(mode: Mode) -> CursorShape {
+    SHAPES
+        .read()
+        .await
+        .get(&mode)
+        .copied()
+        .unwrap_or(CursorShape::Block)
+}
+
+/// the shape is used the next time the editor switches to ``mode``
+pub async fn set_shape(mode: Mode, shape: CursorShape) {
+    SHAPES.write().await.insert(mode, shape);
+}
+
+/// switches the terminal cursor to the shape of ``mode``
+pub async fn apply(mode: Mode) -> io::Result<()> {
+    if terminal::headless() {
+        return Ok(());
+    }
+    let style = shape(mode).await.style();
+    execute!(stdout(), style)
+}
+
+/// gives the cursor back the shape the user configured for their terminal
+pub fn restore() -> io::Result<()> {
+    execute!(stdout(), SetCursorStyle::DefaultUserShape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shapes() {
+        assert_eq!(shape(Mode::CommandLine).await, CursorShape::Bar);
+        set_shape(Mode::CommandLine, CursorShape::Underline).await;
+        assert_eq!(shape(Mode::CommandLine).await, CursorShape::Underline);
+        set_shape(Mode::CommandLine, CursorShape::Bar).await;
+    }
+}
@@ -0,0 +1,231 @@
+//! # Gutter
+//! The columns left of the text of a window: fold indicators, signs and line numbers (in this
+//! order, like in vim). Every window has its own ``GutterConfig``, the signs belong to the document.
+use std::ops::Range;
+
+use crate::core::editor::document::Sign;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    /// distance to the cursor line
+    Relative,
+    /// relative numbers, but the absolute one on the cursor line
+    Hybrid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignColumn {
+    No,
+    Yes,
+    /// only shown if the document has signs
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GutterConfig {
+    pub numbers: LineNumbers,
+    pub signs: SignColumn,
+    /// ranges of lines, that form a fold. Shown in the fold column if there is at least one
+    pub folds: Vec<Range<u32>>,
+}
+
+const SIGN_WIDTH: usize = 2;
+const MIN_NUMBER_WIDTH: usize = 3;
+
+/// the gutter of one window for one render pass
+pub struct Gutter<'a> {
+    config: &'a GutterConfig,
+    signs: &'a [Sign],
+    cursor_line: u32,
+    /// without the space after the numbers
+    number_width: usize,
+}
+
+impl<'a> Gutter<'a> {
+    pub fn new(
+        config: &'a GutterConfig,
+        signs: &'a [Sign],
+        line_count: usize,
+        cursor_line: u32,
+    ) -> Self {
+        Gutter {
+            config,
+            signs,
+            cursor_line,
+            number_width: std::cmp::max(MIN_NUMBER_WIDTH, line_count.to_string().len()),
+        }
+    }
+
+    fn show_folds(&self) -> bool {
+        !self.config.folds.is_empty()
+    }
+
+    fn show_signs(&self) -> bool {
+        match self.config.signs {
+            SignColumn::No => false,
+            SignColumn::Yes => true,
+            SignColumn::Auto => !self.signs.is_empty(),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        let mut width = 0;
+        if self.show_folds() {
+            width += 1;
+        }
+        if self.show_signs() {
+            width += SIGN_WIDTH;
+        }
+        if self.config.numbers != LineNumbers::Off {
+            width += self.number_width + 1;
+        }
+        width as u16
+    }
+
+    fn fold_indicator(&self, line: u32) -> char {
+        let folds = &self.config.folds;
+        if folds.iter().any(|fold| fold.start == line) {
+            '-'
+        } else if folds.iter().any(|fold| fold.contains(&line)) {
+            '│'
+        } else {
+            ' '
+        }
+    }
+
+    fn number(&self, line: u32) -> (String, &'static str) {
+        let width = self.number_width;
+        let distance = line.abs_diff(self.cursor_line);
+        let text = match self.config.numbers {
+            LineNumbers::Off => return (String::new(), "LineNr"),
+            LineNumbers::Absolute => format!("{:>width$} ", line + 1),
+            LineNumbers::Relative => format!("{distance:>width$} "),
+            LineNumbers::Hybrid if distance == 0 => format!("{:<width$} ", line + 1),
+            LineNumbers::Hybrid => format!("{distance:>width$} "),
+        };
+        if distance == 0 {
+            (text, "CursorLineNr")
+        } else {
+            (text, "LineNr")
+        }
+    }
+
+    /// the gutter next to a screen line, as pieces of text with their highlight group. ``line`` is
+    /// the document line starting on this screen line, ``None`` for the continuation of a wrapped
+    /// line
+    pub fn line(&self, line: Option<u32>) -> Vec<(String, String)> {
+        let mut ret = Vec::with_capacity(3);
+        if self.show_folds() {
+            let indicator = match line {
+                Some(line) => self.fold_indicator(line),
+                None => ' ',
+            };
+            ret.push((indicator.to_string(), "FoldColumn".to_string()));
+        }
+        if self.show_signs() {
+            let sign = line.and_then(|line| {
+                self.signs
+                    .iter()
+                    .filter(|sign| sign.line == line)
+                    .max_by_key(|sign| sign.priority)
+            });
+            ret.push(match sign {
+                Some(sign) => {
+                    let text: String = sign.text.chars().take(SIGN_WIDTH).collect();
+                    (format!("{text:<SIGN_WIDTH$}"), sign.group.clone())
+                }
+                None => (" ".repeat(SIGN_WIDTH), "SignColumn".to_string()),
+            });
+        }
+        if self.config.numbers != LineNumbers::Off {
+            ret.push(match line {
+                Some(line) => {
+                    let (text, group) = self.number(line);
+                    (text, group.to_string())
+                }
+                None => (" ".repeat(self.number_width + 1), "LineNr".to_string()),
+            });
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(gutter: &Gutter, line: Option<u32>) -> String {
+        gutter
+            .line(line)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    fn config(numbers: LineNumbers) -> GutterConfig {
+        GutterConfig {
+            numbers,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        let absolute = config(LineNumbers::Absolute);
+        let gutter = Gutter::new(&absolute, &[], 20, 4);
+        assert_eq!(gutter.width(), 4);
+        assert_eq!(text(&gutter, Some(1)), "  2 ");
+        assert_eq!(text(&gutter, None), "    ");
+
+        let relative = config(LineNumbers::Relative);
+        let gutter = Gutter::new(&relative, &[], 20, 4);
+        assert_eq!(text(&gutter, Some(1)), "  3 ");
+        assert_eq!(text(&gutter, Some(4)), "  0 ");
+
+        let hybrid = config(LineNumbers::Hybrid);
+        let gutter = Gutter::new(&hybrid, &[], 20, 4);
+        assert_eq!(text(&gutter, Some(4)), "5   ");
+        assert_eq!(gutter.line(Some(4))[0].1, "CursorLineNr");
+    }
+
+    #[test]
+    fn grows_with_line_count() {
+        let absolute = config(LineNumbers::Absolute);
+        assert_eq!(Gutter::new(&absolute, &[], 12345, 0).width(), 6);
+    }
+
+    #[test]
+    fn signs_by_priority() {
+        let sign = |id, priority, text: &str| Sign {
+            id,
+            line: 2,
+            text: text.to_string(),
+            group: "Error".to_string(),
+            priority,
+        };
+        let signs = vec![sign(0, 1, "W"), sign(1, 10, "EEE")];
+        let config = GutterConfig::default();
+        let gutter = Gutter::new(&config, &signs, 20, 0);
+        assert_eq!(gutter.width(), 2);
+        assert_eq!(text(&gutter, Some(2)), "EE");
+        assert_eq!(text(&gutter, Some(3)), "  ");
+        // auto sign column without signs
+        assert_eq!(Gutter::new(&config, &[], 20, 0).width(), 0);
+    }
+
+    #[test]
+    fn fold_column() {
+        let config = GutterConfig {
+            signs: SignColumn::No,
+            folds: vec![Range { start: 1, end: 4 }],
+            ..Default::default()
+        };
+        let gutter = Gutter::new(&config, &[], 20, 0);
+        let column: String = (0..5).map(|line| text(&gutter, Some(line))).collect();
+        assert_eq!(column, " -││ ");
+    }
+}
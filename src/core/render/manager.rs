@@ -1,14 +1,24 @@
-use crate::core::editor::{Buffer as MotionBuffer, CursorPosition};
-use crate::core::event_handling::{EventCallback, EventHandler};
+use crate::core::editor::document::{Document, DocumentRef};
+use crate::core::editor::fileformat::FileFormat;
+use crate::core::editor::keymap;
+use crate::core::editor::mode;
+use crate::core::editor::prompt;
+use crate::core::editor::registers::RegisterKind;
+use crate::core::editor::visual::Selection;
+use crate::core::editor::CursorPosition;
+use crate::core::event_handling::{EventCallback, EventHandler, EventIndex};
 use crate::core::logger::{self, LogLevel};
+use crate::core::terminal::{headless, screen_size};
 use std::ops::{Deref, DerefMut};
 
 use super::border::{PrintBorder, CORNER, HBORDER, VBORDER};
+use super::gutter::{Gutter, GutterConfig};
+use super::statusline::{self, StatusContext, StatusLine};
 use async_trait::async_trait;
 use crossterm::cursor::MoveTo;
 use crossterm::style::Print;
 use crossterm::terminal::{Clear, ClearType};
-use crossterm::{queue, terminal, ExecutableCommand, QueueableCommand};
+use crossterm::{queue, ExecutableCommand, QueueableCommand};
 use downcast_rs::{impl_downcast, DowncastSync};
 use futures::executor::block_on;
 use once_cell::sync::Lazy;
@@ -52,12 +62,26 @@ pub async fn dispatch_resize() {
 
 pub struct DirectBufferReference<'a>(MutexGuard<'a, Box<dyn Layout>>, BufferRef);
 pub trait ContentRef {
-    fn content(&self) -> &Vec<String>;
+    fn content(&self) -> impl Deref<Target = Vec<String>> + '_;
+}
+impl<T: ContentRef> ContentRef for &T {
+    fn content(&self) -> impl Deref<Target = Vec<String>> + '_ {
+        (**self).content()
+    }
 }
 
 impl<'a> ContentRef for DirectBufferReference<'a> {
-    fn content(&self) -> &Vec<String> {
-        &self.content
+    fn content(&self) -> impl Deref<Target = Vec<String>> + '_ {
+        ContentGuard(self.doc.read().unwrap())
+    }
+}
+
+/// read access to the lines of a document, handed out by ``ContentRef``
+pub struct ContentGuard<'a>(std::sync::RwLockReadGuard<'a, Document>);
+impl<'a> Deref for ContentGuard<'a> {
+    type Target = Vec<String>;
+    fn deref(&self) -> &Self::Target {
+        self.0.lines()
     }
 }
 impl<'a> Deref for DirectBufferReference<'a> {
@@ -129,7 +153,569 @@ pub async fn update_cursor_pos(new_pos: CursorPosition) {
     }
 }
 
+// windows created by the editor itself (e.g. through ``:split``) have no client holding their
+// ClientBuffer, so they are kept alive here until they get closed
+static WINDOWS: Lazy<Mutex<Vec<ClientBuffer>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// hands the ownership of a window over to the editor, which allows the user to close it
+pub async fn adopt(buf: ClientBuffer) {
+    WINDOWS.lock().await.push(buf);
+}
+
+async fn focused_ref() -> Result<BufferRef, &'static str> {
+    bufman_read().await.focused.clone().ok_or("no focus")
+}
+
+async fn focus_ref(buf_ref: BufferRef) -> Result<(), String> {
+    bufman_write().await.change_focus(buf_ref.clone()).await?;
+    let handle = bufman_read().await;
+    let buf = handle.get_buf(buf_ref.layer, buf_ref.id).await?;
+    let (x, y) = buf.cursor_screen_pos();
+    set_cursor(x, y).map_err(|err| err.to_string())
+}
+
+async fn split_ref(buf_ref: &BufferRef, dir: SplitDirection) -> Result<BufferRef, String> {
+    let handle = bufman_read().await;
+    let mut layer = handle.layers[buf_ref.layer as usize].lock().await;
+    let old = layer.get_buf(buf_ref.id)?;
+    let mut buf = Buffer::default();
+    buf.doc = Arc::clone(&old.doc);
+    buf.cursor_pos = old.cursor_pos;
+    buf.scroll = old.scroll;
+    // like in vim, the new window inherits the options of the old one
+    buf.gutter = old.gutter.clone();
+    let id = layer.add_buf_next_to(buf_ref.id, dir, buf).await?;
+    Ok(BufferRef {
+        layer: buf_ref.layer,
+        id,
+    })
+}
+
+/// redraws the whole screen
+pub async fn rerender() -> Result<(), String> {
+    bufman_read()
+        .await
+        .rerender()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn rerender_and_refocus() -> Result<(), String> {
+    rerender().await?;
+    focus_ref(focused_ref().await?).await
+}
+
+/// the windows showing ``doc`` (except ``except``) with the cursor and the first visible line
+/// of each, as positions in the document
+async fn windows_showing(
+    doc: &DocumentRef,
+    except: Option<&BufferRef>,
+) -> (Vec<BufferRef>, Vec<CursorPosition>) {
+    let handle = bufman_read().await;
+    let (mut refs, mut positions) = (Vec::new(), Vec::new());
+    for (idx, layer) in handle.layers.iter().enumerate() {
+        let layer = layer.lock().await;
+        for id in layer.buf_ids() {
+            let buf_ref = BufferRef {
+                layer: idx as u8,
+                id,
+            };
+            let Ok(buf) = layer.get_buf(id) else {
+                continue;
+            };
+            if Arc::ptr_eq(&buf.doc, doc) && except != Some(&buf_ref) {
+                refs.push(buf_ref);
+                positions.push(buf.cursor_pos);
+                positions.push(CursorPosition {
+                    x: 0,
+                    y: buf.scroll,
+                });
+            }
+        }
+    }
+    (refs, positions)
+}
+
+/// moves the windows to the positions from ``windows_showing``, after they went through
+/// ``Document::track``
+async fn move_windows(refs: Vec<BufferRef>, positions: Vec<CursorPosition>) {
+    for (buf_ref, positions) in refs.iter().zip(positions.chunks_exact(2)) {
+        // a window, that got closed in the meantime, doesn't need to move anymore
+        let _ = update_window(buf_ref, |buf| {
+            buf.cursor_pos = positions[0];
+            buf.scroll = positions[1].y;
+            buf.scroll_to_cursor();
+        })
+        .await;
+    }
+}
+
+/// changes ``doc`` without going through a window, e.g. when reloading it. The cursors of all
+/// windows showing it are moved along with the changes. Doesn't redraw
+pub async fn edit_document<R>(doc: &DocumentRef, f: impl FnOnce(&mut Document) -> R) -> R {
+    let (refs, mut positions) = windows_showing(doc, None).await;
+    let ret = doc.write().unwrap().track(&mut positions, f);
+    move_windows(refs, positions).await;
+    ret
+}
+
+/// changes the document of the focused window. ``f`` gets the cursor position and returns the new
+/// one. The cursors of other windows showing the document move along with the changes, like
+/// marks. Redraws everything, since the document might be shown in other windows too
+pub async fn edit_focused<F>(f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Document, CursorPosition) -> CursorPosition,
+{
+    let buf_ref = focused_ref().await?;
+    let doc = update_window(&buf_ref, |buf| Arc::clone(&buf.doc)).await?;
+    let (refs, mut positions) = windows_showing(&doc, Some(&buf_ref)).await;
+    {
+        let focused = focused().await?;
+        let mut buf = focused.deref().await;
+        let cursor = buf.cursor_position();
+        let new_cursor = {
+            let mut doc = buf.doc.write().unwrap();
+            doc.track(&mut positions, |doc| f(doc, cursor))
+        };
+        buf.set_cursor_pos(new_cursor);
+    }
+    move_windows(refs, positions).await;
+    rerender().await
+}
+
+/// changes the gutter of the focused window (``:set number``) and redraws
+pub async fn update_focused_gutter<F>(f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut GutterConfig),
+{
+    {
+        let focused = focused().await?;
+        let mut buf = focused.deref().await;
+        f(&mut buf.gutter);
+    }
+    rerender_and_refocus().await
+}
+
+/// redraws only the status line, e.g. after the cursor moved or the mode changed
+pub async fn redraw_statusline() -> Result<(), String> {
+    let handle = bufman_read().await;
+    let Some(line) = handle.statusline().await else {
+        return Ok(());
+    };
+    let cursor = handle.cursor_screen_pos().await.unwrap_or((0, 0));
+    drop(handle);
+    if headless() {
+        return Ok(());
+    }
+    let term_height = screen_size().map_err(|err| err.to_string())?.1;
+    let mut out = stdout();
+    let chars: Vec<char> = line.text.chars().collect();
+    queue!(out, MoveTo(0, term_height.saturating_sub(1))).map_err(|err| err.to_string())?;
+    for (range, style) in line.styles.iter() {
+        let mut part = ANSICode::Reset.to_string();
+        for code in style.codes() {
+            part.push_str(code.to_string().as_str());
+        }
+        part.extend(&chars[range.clone()]);
+        queue!(out, Print(part)).map_err(|err| err.to_string())?;
+    }
+    queue!(out, Print(ANSICode::Reset), MoveTo(cursor.0, cursor.1))
+        .map_err(|err| err.to_string())?;
+    out.flush().map_err(|err| err.to_string())
+}
+
+/// splits the focused window and focuses the new one
+pub async fn split_focused(dir: SplitDirection) -> Result<(), String> {
+    let new = ClientBuffer {
+        bufman_ref: split_ref(&focused_ref().await?, dir).await?,
+    };
+    new.rerender().await?;
+    new.focus().await?;
+    adopt(new).await;
+    Ok(())
+}
+
+/// closes the focused window, if it is owned by the editor
+pub async fn close_focused() -> Result<(), String> {
+    let BufferRef { layer, id } = focused_ref().await?;
+    let mut windows = WINDOWS.lock().await;
+    let idx = windows
+        .iter()
+        .position(|win| win.layer() == layer && win.id() == id)
+        .ok_or("window is owned by a client and can't be closed")?;
+    let handle = bufman_read().await;
+    let mut windows_left = 0;
+    for layer in handle.layers.iter() {
+        windows_left += layer.lock().await.buf_ids().len();
+    }
+    if windows_left <= 1 {
+        return Err("can't close the last window".to_string());
+    }
+    drop(handle);
+    let win = windows.remove(idx);
+    drop(windows);
+    // removed right away instead of by ClientBuffer::drop, which does it on a task of its own,
+    // so the other windows can be redrawn in the space it leaves
+    bufman_write()
+        .await
+        .rem_buf(layer.into(), id)
+        .await
+        .map_err(|err| err.to_string())?;
+    std::mem::forget(win);
+    rerender_and_refocus().await
+}
+
+/// finds the window, that is the closest one next to ``from`` in ``dir``
+fn find_neighbor(
+    from: Rect,
+    windows: impl Iterator<Item = (BufferId, Rect)>,
+    dir: Direction,
+) -> Option<BufferId> {
+    let overlap = |a1: u16, l1: u16, a2: u16, l2: u16| {
+        std::cmp::min(a1 + l1, a2 + l2) as i32 - std::cmp::max(a1, a2) as i32
+    };
+    windows
+        .filter_map(|(id, rect)| {
+            let (dist, overlap) = match dir {
+                Direction::Left if rect.x + rect.width <= from.x => (
+                    from.x - (rect.x + rect.width),
+                    overlap(rect.y, rect.height, from.y, from.height),
+                ),
+                Direction::Right if rect.x >= from.x + from.width => (
+                    rect.x - (from.x + from.width),
+                    overlap(rect.y, rect.height, from.y, from.height),
+                ),
+                Direction::Up if rect.y + rect.height <= from.y => (
+                    from.y - (rect.y + rect.height),
+                    overlap(rect.x, rect.width, from.x, from.width),
+                ),
+                Direction::Down if rect.y >= from.y + from.height => (
+                    rect.y - (from.y + from.height),
+                    overlap(rect.x, rect.width, from.x, from.width),
+                ),
+                _ => return None,
+            };
+            if overlap > 0 {
+                Some((id, dist, overlap))
+            } else {
+                None
+            }
+        })
+        .min_by(|(_, d1, o1), (_, d2, o2)| d1.cmp(d2).then(o2.cmp(o1)))
+        .map(|(id, _, _)| id)
+}
+
+/// moves the focus ``count`` windows in ``dir`` (``<C-w>h`` etc.)
+pub async fn focus_direction(dir: Direction, count: u32) -> Result<(), String> {
+    let mut buf_ref = focused_ref().await?;
+    for _ in 0..count {
+        let handle = bufman_read().await;
+        let layer = handle.layers[buf_ref.layer as usize].lock().await;
+        let rects = layer
+            .buf_ids()
+            .into_iter()
+            .filter_map(|id| layer.get_buf(id).ok().map(|buf| (id, buf.rect())));
+        let from = layer.get_buf(buf_ref.id)?.rect();
+        match find_neighbor(from, rects, dir) {
+            Some(id) => buf_ref.id = id,
+            None => break,
+        }
+    }
+    focus_ref(buf_ref).await
+}
+
+/// grows the focused window by ``delta`` cells (shrinks if negative)
+pub async fn resize_focused(dir: SplitDirection, delta: i16) -> Result<(), String> {
+    resize_window(&focused_ref().await?, dir, delta).await
+}
+
+/// grows the window by ``delta`` cells (shrinks if negative), e.g. when its border is dragged
+pub async fn resize_window(
+    buf_ref: &BufferRef,
+    dir: SplitDirection,
+    delta: i16,
+) -> Result<(), String> {
+    bufman_read().await.layers[buf_ref.layer as usize]
+        .lock()
+        .await
+        .resize(buf_ref.id, dir, delta)
+        .await?;
+    rerender_and_refocus().await
+}
+
+/// what part of a window is at a position on the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Area {
+    /// the text (or the padding/gutter around it), with the document position closest to it
+    Text(CursorPosition),
+    Border(Direction),
+}
+
+/// the top most visible window at the screen cell (``x``, ``y``)
+pub async fn window_at(x: u16, y: u16) -> Option<(BufferRef, Area)> {
+    let handle = bufman_read().await;
+    for (layer_idx, layer) in handle.layers.iter().enumerate().rev() {
+        let layer = layer.lock().await;
+        for id in layer.visible_ids() {
+            let Ok(buf) = layer.get_buf(id) else {
+                continue;
+            };
+            if let Some(area) = buf.area_at(x, y) {
+                let buf_ref = BufferRef {
+                    layer: layer_idx as u8,
+                    id,
+                };
+                return Some((buf_ref, area));
+            }
+        }
+    }
+    None
+}
+
+pub async fn focus_window(buf_ref: &BufferRef) -> Result<(), String> {
+    focus_ref(buf_ref.clone()).await
+}
+
+/// changes a window, that isn't necessarily the focused one. Doesn't redraw
+pub async fn update_window<F, R>(buf_ref: &BufferRef, f: F) -> Result<R, String>
+where
+    F: FnOnce(&mut Buffer) -> R,
+{
+    let handle = bufman_read().await;
+    let mut layer = handle.layers[buf_ref.layer as usize].lock().await;
+    Ok(f(layer.get_buf_mut(buf_ref.id)?))
+}
+
+/// starts (``Some``) or ends (``None``) the visual selection of the focused window and redraws
+pub async fn set_visual(selection: Option<Selection>) -> Result<(), String> {
+    update_window(&focused_ref().await?, |buf| {
+        if let (None, Some(old)) = (selection, buf.visual) {
+            // remembered as ``'<`` and ``'>``
+            let (anchor, cursor) = (old.anchor, buf.cursor_pos);
+            let (start, end) = if (anchor.y, anchor.x) <= (cursor.y, cursor.x) {
+                (anchor, cursor)
+            } else {
+                (cursor, anchor)
+            };
+            let mut doc = buf.doc.write().unwrap();
+            doc.set_mark('<', start);
+            doc.set_mark('>', end);
+        }
+        buf.visual = selection
+    })
+    .await?;
+    rerender().await
+}
+
+/// shows ``doc`` in the focused window with the cursor at ``pos``, e.g. for a jump into another
+/// file, and redraws
+pub async fn show_document(doc: DocumentRef, pos: CursorPosition) -> Result<(), String> {
+    update_window(&focused_ref().await?, |buf| {
+        // like in vim, ``'"`` is where the cursor was when leaving the document
+        buf.doc.write().unwrap().set_mark('"', buf.cursor_pos);
+        buf.doc = doc;
+        buf.visual = None;
+        buf.scroll = 0;
+        buf.set_cursor_pos(pos);
+    })
+    .await?;
+    rerender_and_refocus().await
+}
+
+/// the document and cursor of every window, the focused one last
+pub async fn window_cursors() -> Vec<(DocumentRef, CursorPosition)> {
+    let handle = bufman_read().await;
+    let mut ret = Vec::new();
+    let mut focused = None;
+    for (idx, layer) in handle.layers.iter().enumerate() {
+        let layer = layer.lock().await;
+        for id in layer.buf_ids() {
+            let Ok(buf) = layer.get_buf(id) else {
+                continue;
+            };
+            let cursor = (Arc::clone(&buf.doc), buf.cursor_pos);
+            if handle.focused
+                == Some(BufferRef {
+                    layer: idx as u8,
+                    id,
+                })
+            {
+                focused = Some(cursor);
+            } else {
+                ret.push(cursor);
+            }
+        }
+    }
+    ret.extend(focused);
+    ret
+}
+
+/// gives all windows on the layer of the focused window the same size
+pub async fn equalize_focused() -> Result<(), String> {
+    let BufferRef { layer, .. } = focused_ref().await?;
+    bufman_read().await.layers[layer as usize]
+        .lock()
+        .await
+        .equalize()
+        .await;
+    rerender_and_refocus().await
+}
+
+/// exchanges the focused window with the ``count``th next one (``<C-w>x``). The focus stays at
+/// the same place on the screen
+pub async fn swap_focused(count: u32) -> Result<(), String> {
+    let BufferRef { layer, id } = focused_ref().await?;
+    let other = {
+        let handle = bufman_read().await;
+        let mut layout = handle.layers[layer as usize].lock().await;
+        let ids = layout.buf_ids();
+        let idx = ids.iter().position(|i| *i == id).ok_or("not found")?;
+        let other = ids[(idx + count as usize) % ids.len()];
+        layout.swap(id, other).await?;
+        other
+    };
+    bufman_write()
+        .await
+        .change_focus(BufferRef { layer, id: other })
+        .await?;
+    rerender_and_refocus().await
+}
+
+/// moves all buffers from one layout to another, keeping their ids. If ``to`` can't take all of
+/// them, everything is moved back
+async fn move_bufs(from: &mut dyn Layout, to: &mut dyn Layout) -> Result<(), &'static str> {
+    let mut res = Ok(());
+    for id in from.buf_ids() {
+        if to.is_full() {
+            res = Err("too many windows for this layout");
+            break;
+        }
+        let mut buf = from.rem_buf(id).await?;
+        // layouts hide borders between their windows, the new one decides on its own
+        if let Some(border) = buf.border.as_mut() {
+            border.show_all(true);
+        }
+        to.adopt_buf(id, buf).await?;
+    }
+    if res.is_err() {
+        for id in to.buf_ids() {
+            let buf = to.rem_buf(id).await?;
+            from.adopt_buf(id, buf).await?;
+        }
+        return res;
+    }
+    to.equalize().await;
+    Ok(())
+}
+
+/// replaces the layout of the layer ``layer``. ``make_new`` gets the old layout (with a zoomed
+/// layer already unzoomed) and returns the new one
+async fn replace_layout<F, Fut>(layer: u8, make_new: F) -> Result<(), String>
+where
+    F: FnOnce(Box<dyn Layout>) -> Fut,
+    Fut: std::future::Future<Output = Result<Box<dyn Layout>, (Box<dyn Layout>, String)>>,
+{
+    {
+        let handle = bufman_read().await;
+        let mut current = handle.layers[layer as usize].lock().await;
+        let old = std::mem::replace(&mut *current, Box::new(SplitLayout::new()));
+        let old = match old.downcast::<MonocleLayout>() {
+            Ok(monocle) => monocle.into_inner().await,
+            Err(old) => old,
+        };
+        match make_new(old).await {
+            Ok(new) => *current = new,
+            Err((old, err)) => {
+                *current = old;
+                return Err(err);
+            }
+        }
+    }
+    if let Ok(BufferRef { layer: l, id }) = focused_ref().await {
+        if l == layer {
+            bufman_read().await.layers[layer as usize]
+                .lock()
+                .await
+                .focus_changed(id);
+        }
+    }
+    rerender_and_refocus().await
+}
+
+/// switches the layout of the layer of the focused window (``:layout grid``). The windows are
+/// moved to the new layout and keep their documents, cursors and ids
+pub async fn set_layout(name: &str) -> Result<(), String> {
+    let BufferRef { layer, id } = focused_ref().await?;
+    if name == "monocle" {
+        return replace_layout(layer, |old| async move {
+            Ok(Box::new(MonocleLayout::new(old, id)) as Box<dyn Layout>)
+        })
+        .await;
+    }
+    let mut new = builtin_layouts::new_layout(name).ok_or(format!("unknown layout: {name}"))?;
+    replace_layout(layer, |mut old| async move {
+        match move_bufs(&mut *old, &mut *new).await {
+            Ok(()) => Ok(new),
+            Err(err) => Err((old, err.to_string())),
+        }
+    })
+    .await
+}
+
+/// shows only the focused window (on its layer), or brings back the other ones if already zoomed
+pub async fn toggle_zoom() -> Result<(), String> {
+    let BufferRef { layer, id } = focused_ref().await?;
+    let zoomed = bufman_read().await.layers[layer as usize]
+        .lock()
+        .await
+        .is::<MonocleLayout>();
+    replace_layout(layer, |old| async move {
+        if zoomed {
+            Ok(old)
+        } else {
+            Ok(Box::new(MonocleLayout::new(old, id)) as Box<dyn Layout>)
+        }
+    })
+    .await
+}
+
+/// ids of the tabs on the layer of the focused window and the index of the current one
+async fn tabs() -> Result<(u8, Vec<BufferId>, usize), String> {
+    let BufferRef { layer, .. } = focused_ref().await?;
+    let handle = bufman_read().await;
+    let layout = handle.layers[layer as usize].lock().await;
+    let tabs = layout
+        .downcast_ref::<TabLayout>()
+        .ok_or("layer is not a TabLayout")?;
+    Ok((layer, tabs.buf_ids(), tabs.current()))
+}
+
+async fn focus_tab(layer: u8, id: BufferId) -> Result<(), String> {
+    // focusing brings the tab to the front
+    focus_ref(BufferRef { layer, id }).await?;
+    rerender_and_refocus().await
+}
+
+/// goes to the tab ``delta`` tabs further (backwards if negative), wrapping around (``gt``)
+pub async fn cycle_tab(delta: i64) -> Result<(), String> {
+    let (layer, ids, current) = tabs().await?;
+    let idx = (current as i64 + delta).rem_euclid(ids.len() as i64) as usize;
+    focus_tab(layer, ids[idx]).await
+}
+
+/// goes to the tab with the given number, starting at 1 (``3gt``)
+pub async fn goto_tab(number: u32) -> Result<(), String> {
+    let (layer, ids, _) = tabs().await?;
+    let id = *ids
+        .get((number as usize).saturating_sub(1))
+        .ok_or("no such tab")?;
+    focus_tab(layer, id).await
+}
+
 fn set_cursor(x: u16, y: u16) -> std::io::Result<()> {
+    if headless() {
+        return Ok(());
+    }
     stdout().execute(MoveTo(x, y))?;
     Ok(())
 }
@@ -139,12 +725,14 @@ enum Event {
     Resize,
 }
 struct EventData;
+impl EventIndex for Event {}
 unsafe impl Sync for Event {}
 unsafe impl Sync for EventData {}
 async fn set_resize_events() {}
 
-#[derive(Clone)]
-struct BufferRef {
+/// identifies a window on the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferRef {
     layer: u8,
     id: BufferId,
 }
@@ -152,7 +740,6 @@ struct BufferRef {
 type BufferId = u32; // NOTE: just don't create 2^32-1 buffers on one layer
 pub struct ClientBuffer {
     bufman_ref: BufferRef,
-    motion_stuff: MotionBuffer,
 }
 
 const CLIENTBUF_ID_ERR: &str =
@@ -191,7 +778,8 @@ impl ANSICode {
         ret.push_str(CSI);
         match self {
             ANSICode::Reset => ret.push_str("0m"),
-            ANSICode::SetCursor(x, y) => ret.push_str(format!("{};{}H", x + 1, y + 1).as_str()),
+            // CSI takes the row first
+            ANSICode::SetCursor(x, y) => ret.push_str(format!("{};{}H", y + 1, x + 1).as_str()),
             ANSICode::Color(foreground, color) => {
                 ret.push(if *foreground { '3' } else { '4' });
                 ret.push_str("8;2;");
@@ -215,23 +803,7 @@ impl Display for ANSICode {
 
 impl ClientBuffer {
     pub async fn focus(&self) -> Result<(), String> {
-        bufman_write()
-            .await
-            .change_focus(self.bufman_ref.clone())
-            .await?;
-
-        let (x, y) = bufman_read()
-            .await
-            .get_buf(self.layer(), self.id())
-            .await
-            .unwrap()
-            .get_start_of_text();
-        set_cursor(x, y).map_err(|err| err.to_string())?;
-
-        //if let Err(err) = set_cursor(x as u16, y as u16) {
-        //    return Err(format!("set_cursor: {}", err.to_string()));
-        //}
-        Ok(())
+        focus_ref(self.bufman_ref.clone()).await
     }
     #[inline]
     fn id(&self) -> BufferId {
@@ -259,17 +831,37 @@ impl ClientBuffer {
     pub async fn set_content(&mut self, content: String) -> Result<(), String> {
         let handle = bufman_read().await;
         let BufferRef { layer, id } = self.bufman_ref;
-        let mut buf = handle.get_buf_mut(layer, id).await?;
-        buf.content = content.lines().map(|str| str.to_string()).collect(); // TODO: make better
+        let buf = handle.get_buf_mut(layer, id).await?;
+        buf.doc.write().unwrap().set_content(&content);
         drop(buf);
+        drop(handle);
+        self.rerender().await
+    }
+    /// redraws the screen, e.g. after the document of this window was edited directly
+    pub async fn rerender(&self) -> Result<(), String> {
         logger::log(LogLevel::Normal, "start rerendering").await;
-        if let Err(err) = handle.rerender().await {
+        if let Err(err) = bufman_read().await.rerender().await {
             return Err(format!("Error when rerendering: {err}"));
         }
         logger::log(LogLevel::Normal, "finish rerendering (for realz)").await;
         Ok(())
     }
     pub async fn build(id: BufferId, tiled: bool) -> Result<Self, String> {
+        ClientBuffer::build_with_buf(id, tiled, Buffer::default()).await
+    }
+
+    /// creates a new window showing ``doc``, e.g. to open an already opened file again
+    pub async fn build_with_document(
+        id: BufferId,
+        tiled: bool,
+        doc: DocumentRef,
+    ) -> Result<Self, String> {
+        let mut buf = Buffer::default();
+        buf.doc = doc;
+        ClientBuffer::build_with_buf(id, tiled, buf).await
+    }
+
+    async fn build_with_buf(id: BufferId, tiled: bool, buf: Buffer) -> Result<Self, String> {
         let handle = bufman_read().await;
         let vec = if tiled {
             &handle.tiled_layouts
@@ -281,12 +873,10 @@ impl ClientBuffer {
         for layer in vec.iter().map(|layer| *layer) {
             if !handle.layers[layer].lock().await.is_full() {
                 let layer = layer as u8;
-                let id = handle.add_new_buf(layer, id).await?;
+                let id = handle.add_buf(layer, id, buf).await?;
                 logger::log(LogLevel::Debug, "Buffer created!").await;
                 return Ok(ClientBuffer {
                     bufman_ref: BufferRef { layer, id },
-                    motion_stuff: MotionBuffer::new(Vec::new(), CursorPosition { x: 0, y: 0 })
-                        .await,
                 });
             }
         }
@@ -299,6 +889,12 @@ impl ClientBuffer {
     pub async fn build_on_free(id: BufferId) -> Result<Self, String> {
         ClientBuffer::build(id, false).await
     }
+    /// creates a floating window, placed according to ``config``
+    pub async fn build_floating(id: BufferId, config: FloatConfig) -> Result<Self, String> {
+        let buf = ClientBuffer::build_on_free(id).await?;
+        buf.configure(config).await?;
+        Ok(buf)
+    }
 
     pub async fn move_to_layer(&mut self, layer: u8) -> Result<(), String> {
         let mut handle = bufman_write().await;
@@ -312,8 +908,32 @@ impl ClientBuffer {
         Ok(())
     }
 
-    pub fn cursor_position(&self) -> &CursorPosition {
-        &self.motion_stuff.cursor_position
+    pub async fn cursor_position(&self) -> CursorPosition {
+        self.get_pbr().await.deref().await.cursor_position()
+    }
+    pub async fn set_cursor_position(&self, pos: CursorPosition) {
+        self.get_pbr().await.deref().await.set_cursor_pos(pos);
+    }
+
+    /// changes the line numbers, sign column and folds of this window
+    pub async fn set_gutter(&self, config: GutterConfig) -> Result<(), String> {
+        self.get_pbr().await.deref().await.gutter = config;
+        self.rerender().await
+    }
+
+    /// the document shown in this window
+    pub async fn document(&self) -> DocumentRef {
+        Arc::clone(&self.get_pbr().await.deref().await.doc)
+    }
+
+    /// opens a new window next to this one (on the same layer), which shows the same document
+    /// with its own cursor and scroll position
+    pub async fn split(&self, dir: SplitDirection) -> Result<ClientBuffer, String> {
+        let new = ClientBuffer {
+            bufman_ref: split_ref(&self.bufman_ref, dir).await?,
+        };
+        new.rerender().await?;
+        Ok(new)
     }
     async fn get_pbr(&self) -> PublicBufferReference {
         PublicBufferReference(bufman_read().await, self.bufman_ref.clone())
@@ -330,6 +950,68 @@ impl ClientBuffer {
     }
 }
 
+/// orientation of a split. ``Horizontal`` stacks the windows on top of each other (``:split``),
+/// ``Vertical`` puts them side by side (``:vsplit``)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+/// area on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn extent(&self, dir: SplitDirection) -> u16 {
+        match dir {
+            SplitDirection::Horizontal => self.height,
+            SplitDirection::Vertical => self.width,
+        }
+    }
+
+    /// splits the rect in two, the first part getting ``first`` cells along ``dir``
+    pub fn cut(&self, dir: SplitDirection, first: u16) -> (Rect, Rect) {
+        match dir {
+            SplitDirection::Horizontal => (
+                Rect {
+                    height: first,
+                    ..*self
+                },
+                Rect {
+                    y: self.y + first,
+                    height: self.height - first,
+                    ..*self
+                },
+            ),
+            SplitDirection::Vertical => (
+                Rect {
+                    width: first,
+                    ..*self
+                },
+                Rect {
+                    x: self.x + first,
+                    width: self.width - first,
+                    ..*self
+                },
+            ),
+        }
+    }
+}
+
 #[async_trait]
 pub trait BufferDims {
     fn width(&self) -> u16;
@@ -341,6 +1023,46 @@ pub trait BufferDims {
     fn lpad(&self) -> u16;
     fn rpad(&self) -> u16;
     fn get_text_len(&self) -> u16;
+    /// number of screen lines, that show text
+    fn get_text_height(&self) -> u16;
+    /// the first document line shown in the window
+    fn scroll(&self) -> u32;
+}
+
+impl<T: BufferDims> BufferDims for &T {
+    fn width(&self) -> u16 {
+        (**self).width()
+    }
+    fn height(&self) -> u16 {
+        (**self).height()
+    }
+    fn offx(&self) -> u16 {
+        (**self).offx()
+    }
+    fn offy(&self) -> u16 {
+        (**self).offy()
+    }
+    fn tpad(&self) -> u16 {
+        (**self).tpad()
+    }
+    fn dpad(&self) -> u16 {
+        (**self).dpad()
+    }
+    fn lpad(&self) -> u16 {
+        (**self).lpad()
+    }
+    fn rpad(&self) -> u16 {
+        (**self).rpad()
+    }
+    fn get_text_len(&self) -> u16 {
+        (**self).get_text_len()
+    }
+    fn get_text_height(&self) -> u16 {
+        (**self).get_text_height()
+    }
+    fn scroll(&self) -> u32 {
+        (**self).scroll()
+    }
 }
 
 const BLANK_BORDER: BufferBorder = BufferBorder::blank();
@@ -370,11 +1092,13 @@ impl BufferDims for DirectBufferReference<'_> {
         self.height
     }
     fn get_text_len(&self) -> u16 {
-        let mut not_text_space = 0;
-        if let Some(border) = &self.border {
-            not_text_space += border.lpad + border.rpad + border.get_number_of_borders().0;
-        }
-        self.width - not_text_space
+        self.text_width()
+    }
+    fn get_text_height(&self) -> u16 {
+        self.text_height() as u16
+    }
+    fn scroll(&self) -> u32 {
+        Buffer::scroll(self)
     }
 }
 
@@ -525,6 +1249,8 @@ impl BufferBorder {
     }
 }
 
+/// A window: the screen geometry of a view on a ``Document``, plus the view specific state
+/// (cursor, scroll position). Several windows can share one document.
 #[derive(Debug)]
 pub struct Buffer {
     offx: u16,
@@ -534,7 +1260,11 @@ pub struct Buffer {
     border: Option<BufferBorder>,
     ctrl_codes: Vec<(ANSICode, usize)>,
     cursor_pos: CursorPosition,
-    content: Vec<String>,
+    scroll: u32, // first document line shown in the window
+    doc: DocumentRef,
+    gutter: GutterConfig,
+    /// the other end of the selection (the cursor being the first one), while in visual mode
+    visual: Option<Selection>,
 }
 
 impl Buffer {
@@ -547,7 +1277,10 @@ impl Buffer {
             border: Some(BufferBorder::default()),
             ctrl_codes: Vec::new(),
             cursor_pos: CursorPosition { x: 0, y: 0 },
-            content: Vec::new(),
+            scroll: 0,
+            doc: Document::new_ref(Vec::new(), None),
+            gutter: GutterConfig::default(),
+            visual: None,
         }
     }
     pub fn ctrl_codes(&self) -> std::slice::Iter<(ANSICode, usize)> {
@@ -562,24 +1295,208 @@ impl Buffer {
     pub fn offsets(&self) -> (u16, u16) {
         (self.offx, self.offy)
     }
-    pub fn lines(&self) -> impl Iterator<Item = &String> {
-        self.content.iter()
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.offx,
+            y: self.offy,
+            width: self.width,
+            height: self.height,
+        }
+    }
+    pub fn document(&self) -> &DocumentRef {
+        &self.doc
+    }
+    pub fn scroll(&self) -> u32 {
+        self.scroll
+    }
+    pub fn gutter(&self) -> &GutterConfig {
+        &self.gutter
+    }
+    pub fn gutter_mut(&mut self) -> &mut GutterConfig {
+        &mut self.gutter
     }
-    pub fn set_cursor_pos(&mut self, mut new_pos: CursorPosition) {
+    /// width of the line numbers, signs etc. left of the text. Windows without a border are
+    /// rendered as plain text, so they never have a gutter
+    pub fn gutter_width(&self) -> u16 {
+        if self.border.is_none() {
+            return 0;
+        }
+        let doc = self.doc.read().unwrap();
+        Gutter::new(
+            &self.gutter,
+            doc.signs(),
+            doc.line_count(),
+            self.cursor_pos.y,
+        )
+        .width()
+    }
+    pub fn set_cursor_pos(&mut self, new_pos: CursorPosition) {
         self.cursor_pos = new_pos;
+        self.scroll_to_cursor();
+        let (x, y) = self.cursor_screen_pos();
+        let _ = set_cursor(x, y);
+    }
+    /// where the cursor of this window is on the screen
+    pub fn cursor_screen_pos(&self) -> (u16, u16) {
         let (offx, offy) = self.get_start_of_text();
-        new_pos.x += offx as u32;
-        new_pos.y += offy as u32;
-        set_cursor(new_pos.x as u16, new_pos.y as u16);
+        let (col, row) = {
+            let doc = self.doc.read().unwrap();
+            let lines = doc
+                .lines()
+                .iter()
+                .skip(self.scroll as usize)
+                .take(self.cursor_pos.y.saturating_sub(self.scroll) as usize);
+            wrapped_offset(lines, self.cursor_pos.x, self.text_width())
+        };
+        // the cursor stays inside of the window, even if the viewport is outdated
+        let row = row.min(self.text_height().saturating_sub(1));
+        (offx + col as u16, offy + row as u16)
+    }
+    /// number of chars that fit into one screen line of the window
+    pub fn text_width(&self) -> u16 {
+        let not_text_space = match self.border.as_ref() {
+            Some(b) => b.lpad + b.rpad + b.get_number_of_borders().0 + self.gutter_width(),
+            None => 0,
+        };
+        self.width.saturating_sub(not_text_space).max(1)
+    }
+    /// number of screen lines, that fit into the window
+    pub fn text_height(&self) -> u32 {
+        let vpad = match self.border.as_ref() {
+            Some(b) => b.tpad + b.dpad + b.get_number_of_borders().1,
+            None => 0,
+        };
+        self.height.saturating_sub(vpad).max(1) as u32
+    }
+    /// moves the viewport, so the cursor is visible. Lines, that are too long, take up several
+    /// screen lines
+    fn scroll_to_cursor(&mut self) {
+        let y = self.cursor_pos.y;
+        if y < self.scroll {
+            self.scroll = y;
+            return;
+        }
+        let width = self.text_width();
+        let height = self.text_height();
+        let doc = self.doc.read().unwrap();
+        let lines = doc.lines();
+        let cursor_row = |scroll: u32| {
+            let lines = lines
+                .iter()
+                .skip(scroll as usize)
+                .take((y - scroll) as usize);
+            wrapped_offset(lines, self.cursor_pos.x, width).1
+        };
+        // every line takes at least one screen line, so the viewport can start at least that far
+        // down. From there, scroll line by line until the cursor is visible
+        let mut scroll = std::cmp::max(self.scroll, (y + 1).saturating_sub(height));
+        while scroll < y && cursor_row(scroll) >= height {
+            scroll += 1;
+        }
+        self.scroll = scroll;
     }
     pub fn cursor_position(&self) -> CursorPosition {
         self.cursor_pos
     }
+    pub fn visual(&self) -> Option<Selection> {
+        self.visual
+    }
+    /// the chars of document line ``y``, that are part of the visual selection
+    pub fn selection_on_line(&self, y: u32) -> Option<Range<usize>> {
+        let selection = self.visual?;
+        let doc = self.doc.read().unwrap();
+        let len = doc.line(y as usize)?.chars().count();
+        let (a, b) = (selection.anchor, self.cursor_pos);
+        match selection.kind {
+            RegisterKind::Charwise => charwise_selection(a, b, y, len),
+            RegisterKind::Linewise => (a.y.min(b.y)..=a.y.max(b.y))
+                .contains(&y)
+                .then_some(0..len + 1),
+            RegisterKind::Blockwise => blockwise_selection(a, b, y),
+        }
+    }
+    /// moves the viewport ``delta`` lines down (up if negative), like scrolling with the mouse
+    /// wheel. The cursor is moved along, if it would leave the viewport
+    pub fn scroll_by(&mut self, delta: i32) {
+        let width = self.text_width();
+        let height = self.text_height();
+        let doc = self.doc.read().unwrap();
+        let last = doc.line_count().saturating_sub(1) as i64;
+        self.scroll = (self.scroll as i64 + delta as i64).clamp(0, last) as u32;
+        // the last line, that is completely visible
+        let mut rows = 0;
+        let mut bottom = self.scroll;
+        for (y, line) in (self.scroll..).zip(doc.lines().iter().skip(self.scroll as usize)) {
+            rows += wrapped_rows(line, width);
+            if rows > height {
+                break;
+            }
+            bottom = y;
+        }
+        let y = self.cursor_pos.y.clamp(self.scroll, bottom);
+        if y != self.cursor_pos.y {
+            let len = doc.line(y as usize).map_or(0, |line| line.chars().count());
+            self.cursor_pos = CursorPosition {
+                x: self.cursor_pos.x.min(len.saturating_sub(1) as u32),
+                y,
+            };
+        }
+    }
+    /// the document position closest to the screen cell (``x``, ``y``). Cells outside of the text
+    /// count as the closest text cell, e.g. a click below the last line lands on the last line
+    pub fn doc_position_at(&self, x: u16, y: u16) -> CursorPosition {
+        let (text_x, text_y) = self.get_start_of_text();
+        let col = x.saturating_sub(text_x).min(self.text_width() - 1) as u32;
+        let row = y.saturating_sub(text_y).min(self.text_height() as u16 - 1) as u32;
+        let doc = self.doc.read().unwrap();
+        let lines = doc.lines().iter().skip(self.scroll as usize);
+        let (line, x) = match position_at(lines, col, row, self.text_width()) {
+            Some(pos) => pos,
+            None => {
+                let last = doc.line_count().saturating_sub(1);
+                let len = doc.line(last).map_or(0, |line| line.chars().count());
+                (last.saturating_sub(self.scroll as usize) as u32, len as u32)
+            }
+        };
+        let len = doc
+            .line(self.scroll as usize + line as usize)
+            .map_or(0, |line| line.chars().count());
+        CursorPosition {
+            x: x.min(len.saturating_sub(1) as u32),
+            y: self.scroll + line,
+        }
+    }
+    /// what part of the window is at the screen cell (``x``, ``y``), ``None`` if the cell isn't
+    /// inside of it
+    pub fn area_at(&self, x: u16, y: u16) -> Option<Area> {
+        let rect = self.rect();
+        if x < rect.x || y < rect.y || x >= rect.x + rect.width || y >= rect.y + rect.height {
+            return None;
+        }
+        if let Some(border) = self.border.as_ref() {
+            let [left, top, down, right] = border.get_borders_shown();
+            let border = if left && x == rect.x {
+                Some(Direction::Left)
+            } else if right && x == rect.x + rect.width - 1 {
+                Some(Direction::Right)
+            } else if top && y == rect.y {
+                Some(Direction::Up)
+            } else if down && y == rect.y + rect.height - 1 {
+                Some(Direction::Down)
+            } else {
+                None
+            };
+            if let Some(dir) = border {
+                return Some(Area::Border(dir));
+            }
+        }
+        Some(Area::Text(self.doc_position_at(x, y)))
+    }
     pub fn get_start_of_text(&self) -> (u16, u16) {
         let mut x = self.offx;
         let mut y = self.offy;
         if let Some(b) = self.border.as_ref() {
-            x += b.lpad + if b.border_shown >> 3 & 1 > 0 { 1 } else { 0 };
+            x += b.lpad + if b.border_shown >> 3 & 1 > 0 { 1 } else { 0 } + self.gutter_width();
             y += b.tpad + if b.border_shown >> 2 & 1 > 0 { 1 } else { 0 };
         }
         (x, y)
@@ -595,17 +1512,21 @@ impl Buffer {
             Some(b) => b,
             None => BufferBorder::blank(),
         };
-        border.tpad = (self.height - 1 - self.content.len() as u16) / 2;
+        let line_count = self.doc.read().unwrap().line_count() as u16;
+        border.tpad = (self.height - 1 - line_count) / 2;
         border.lpad = (self.width - len as u16) / 2;
         self.border = Some(border);
     }
 
     fn get_auto_width(&self) -> usize {
-        self.content
+        self.doc
+            .read()
+            .unwrap()
+            .lines()
             .iter()
-            .max_by(|x, y| x.len().cmp(&y.len()))
-            .unwrap_or(&String::new())
-            .len()
+            .map(|line| line.len())
+            .max()
+            .unwrap_or(0)
     }
 
     fn auto_size(&mut self) {
@@ -652,13 +1573,84 @@ impl Buffer {
 
 impl Display for Buffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for line in self.content.iter() {
+        let doc = self.doc.read().unwrap();
+        for line in doc.lines().iter().skip(self.scroll as usize) {
             write!(f, "{}\n", line)?;
         }
         Ok(())
     }
 }
 
+/// number of screen lines ``line`` takes up, if a screen line fits ``width`` chars
+pub fn wrapped_rows(line: &str, width: u16) -> u32 {
+    let width = width.max(1) as usize;
+    std::cmp::max(1, line.chars().count().div_ceil(width)) as u32
+}
+
+/// screen position (column, row) of the char ``x`` of the line after ``lines_before``, relative to
+/// the first of ``lines_before``
+fn wrapped_offset<'a>(
+    lines_before: impl Iterator<Item = &'a String>,
+    x: u32,
+    width: u16,
+) -> (u32, u32) {
+    let rows: u32 = lines_before.map(|line| wrapped_rows(line, width)).sum();
+    let width = width.max(1) as u32;
+    (x % width, rows + x / width)
+}
+
+/// the inverse of ``wrapped_offset``: the line (counted from the first of ``lines``) and char, that
+/// is shown at the screen position (``col``, ``row``). ``None`` if the lines end before ``row``
+fn position_at<'a>(
+    lines: impl Iterator<Item = &'a String>,
+    col: u32,
+    row: u32,
+    width: u16,
+) -> Option<(u32, u32)> {
+    let mut first_row = 0;
+    for (i, line) in lines.enumerate() {
+        let rows = wrapped_rows(line, width);
+        if row < first_row + rows {
+            return Some((i as u32, (row - first_row) * width.max(1) as u32 + col));
+        }
+        first_row += rows;
+    }
+    None
+}
+
+/// the chars of line ``y`` (``len`` chars long), that are between ``a`` and ``b`` (both inclusive).
+/// The line break counts as one more char, if the selection continues on the next line
+fn charwise_selection(
+    a: CursorPosition,
+    b: CursorPosition,
+    y: u32,
+    len: usize,
+) -> Option<Range<usize>> {
+    let (start, end) = if (a.y, a.x) <= (b.y, b.x) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    if y < start.y || y > end.y {
+        return None;
+    }
+    let from = if y == start.y { start.x as usize } else { 0 };
+    let to = if y == end.y {
+        end.x as usize + 1
+    } else {
+        len + 1
+    };
+    Some(from..to)
+}
+
+/// the columns of line ``y``, that are inside the rectangle with the corners ``a`` and ``b``
+fn blockwise_selection(a: CursorPosition, b: CursorPosition, y: u32) -> Option<Range<usize>> {
+    if y < a.y.min(b.y) || y > a.y.max(b.y) {
+        return None;
+    }
+    Some(a.x.min(b.x) as usize..a.x.max(b.x) as usize + 1)
+}
+
 const BITS_PER_EL: usize = 32;
 const MAX_VAL_EL: u32 = u32::MAX;
 const GAP_CHAR: char = '@';
@@ -773,12 +1765,18 @@ impl RenderBuffer {
     // flushes a buffer
     async fn flush(&mut self) -> std::io::Result<()> {
         self.fill_rest();
+        if headless() {
+            self.ctrl_codes.clear();
+            return Ok(());
+        }
         queue!(stdout(), Clear(ClearType::All)).unwrap();
         self.init_ctrl_codes.iter().try_for_each(|code| {
             stdout().queue(Print(code.conv()))?;
             Ok::<(), std::io::Error>(())
         })?;
         if self.ctrl_codes.len() > 0 {
+            // codes at the same index get printed in the order they were added
+            self.ctrl_codes.reverse();
             self.ctrl_codes.sort_by(|(_, i), (_, j)| j.cmp(i));
             logger::log(
                 LogLevel::Debug,
@@ -809,6 +1807,8 @@ impl RenderBuffer {
                 Ok::<(), std::io::Error>(())
             })?;
         }
+        // don't let colors leak into the next frame
+        stdout().queue(Print(ANSICode::Reset))?;
         self.last_ctrl_codes.iter().try_for_each(|code| {
             stdout().queue(Print(code.conv()))?;
             Ok::<(), std::io::Error>(())
@@ -821,6 +1821,7 @@ impl RenderBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::headless;
 
     #[test]
     fn test_power2() {
@@ -841,11 +1842,86 @@ mod tests {
         assert!(res == 16 || res == 1);
     }
 
+    #[test]
+    fn screen_to_document() {
+        let lines = [String::from("0123456789abc"), String::from("x")];
+        assert_eq!(position_at(lines.iter(), 2, 1, 10), Some((0, 12)));
+        assert_eq!(position_at(lines.iter(), 5, 2, 10), Some((1, 5)));
+        assert_eq!(position_at(lines.iter(), 0, 3, 10), None);
+    }
+
+    #[test]
+    fn selection() {
+        let pos = |x, y| CursorPosition { x, y };
+        // the order of the ends doesn't matter
+        assert_eq!(charwise_selection(pos(3, 2), pos(1, 0), 0, 5), Some(1..6));
+        assert_eq!(charwise_selection(pos(1, 0), pos(3, 2), 1, 4), Some(0..5));
+        assert_eq!(charwise_selection(pos(1, 0), pos(3, 2), 2, 8), Some(0..4));
+        assert_eq!(charwise_selection(pos(1, 0), pos(3, 2), 3, 8), None);
+        assert_eq!(charwise_selection(pos(4, 1), pos(2, 1), 1, 8), Some(2..5));
+        assert_eq!(blockwise_selection(pos(4, 3), pos(2, 1), 2), Some(2..5));
+        assert_eq!(blockwise_selection(pos(4, 3), pos(2, 1), 0), None);
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(wrapped_rows("", 10), 1);
+        assert_eq!(wrapped_rows("0123456789", 10), 1);
+        assert_eq!(wrapped_rows("0123456789a", 10), 2);
+        // chars, not bytes
+        assert_eq!(wrapped_rows("ääääää", 6), 1);
+        let lines = [String::from("0123456789abc"), String::new()];
+        assert_eq!(wrapped_offset(lines.iter(), 3, 10), (3, 3));
+        assert_eq!(wrapped_offset(lines.iter(), 12, 10), (2, 4));
+        assert_eq!(wrapped_offset([].iter(), 10, 10), (0, 1));
+    }
+
+    #[test]
+    fn neighbor_prefers_closest_and_overlapping() {
+        let rect = |x, y, width, height| Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+        let windows = vec![
+            (0, rect(0, 0, 40, 24)),
+            (1, rect(40, 0, 40, 12)),
+            (2, rect(40, 12, 40, 12)),
+        ];
+        let from = rect(40, 12, 40, 12);
+        assert_eq!(
+            find_neighbor(from, windows.clone().into_iter(), Direction::Left),
+            Some(0)
+        );
+        assert_eq!(
+            find_neighbor(from, windows.clone().into_iter(), Direction::Up),
+            Some(1)
+        );
+        assert_eq!(
+            find_neighbor(from, windows.into_iter(), Direction::Right),
+            None
+        );
+    }
+
     #[test]
     fn test_power2_empty() {
         let val: u32 = 0;
         assert_eq!(RenderBuffer::find_nearest_smaller_pow2(val), 0);
     }
+
+    #[tokio::test]
+    async fn close_removes_the_window_right_away() {
+        let (doc, _window) = headless::test_window(&["text"]).await;
+        let windows = window_cursors().await.len();
+        split_focused(SplitDirection::Horizontal).await.unwrap();
+        assert_eq!(window_cursors().await.len(), windows + 1);
+        close_focused().await.unwrap();
+        let cursors = window_cursors().await;
+        assert_eq!(cursors.len(), windows);
+        // the focused window comes last
+        assert!(Arc::ptr_eq(&cursors.last().unwrap().0, &doc));
+    }
 }
 
 // async fn render(
@@ -870,7 +1946,7 @@ async fn render_internal_faster(
     buffers: impl Iterator<Item = &Buffer> + Send,
     render_buf: &mut RenderBuffer,
 ) {
-    let (term_width, term_height) = terminal::size().unwrap();
+    let (term_width, term_height) = screen_size().unwrap();
     // eprintln!("width = {term_width} height = {term_height}");
     logger::log(LogLevel::Normal, "start rendering buffers...").await;
     // TODO: make this faster
@@ -903,16 +1979,70 @@ async fn render_internal_faster(
 trait Layout: DowncastSync {
     async fn render(&mut self, render_buf: &mut RenderBuffer);
     async fn add_buf(&mut self, name: BufferId, buf: Buffer) -> Result<BufferId, &'static str>;
+    /// adds a buffer, which should be placed next to ``target``. Layouts, that don't have a notion
+    /// of that, just add it the normal way
+    async fn add_buf_next_to(
+        &mut self,
+        _target: BufferId,
+        _dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<BufferId, &'static str> {
+        self.add_buf(0, buf).await
+    }
+    /// adds a buffer under an id handed out by another layout, so the ClientBuffers pointing to
+    /// it stay valid. Used when the layout of a layer gets switched
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str>;
     async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str>; // now this should never be
     fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str>;
     fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str>;
+    /// ids of all buffers, in the order the layout shows them
+    fn buf_ids(&self) -> Vec<BufferId>;
     fn is_full(&self) -> bool;
     fn get_next_focused(&self) -> Option<BufferId>;
+
+    // window management, layouts opt in by overriding these
+    async fn resize(
+        &mut self,
+        _name: BufferId,
+        _dir: SplitDirection,
+        _delta: i16,
+    ) -> Result<(), &'static str> {
+        Err("layout can't be resized")
+    }
+    async fn equalize(&mut self) {}
+    async fn swap(&mut self, _a: BufferId, _b: BufferId) -> Result<(), &'static str> {
+        Err("layout can't swap buffers")
+    }
+    /// called after the terminal changed its size, layouts that depend on it recalculate here
+    async fn term_resized(&mut self) {}
+    /// called when a buffer of this layout gets focused, e.g. to bring its tab to the front
+    fn focus_changed(&mut self, _name: BufferId) {}
+    /// the buffers, that are actually shown on the screen, top most first
+    fn visible_ids(&self) -> Vec<BufferId> {
+        self.buf_ids()
+    }
+    /// the state of the layout besides its windows (split ratios, master area, ...) as one line,
+    /// for ``:mksession``. Windows are referred to by their index in ``buf_ids``
+    fn arrangement(&self) -> String {
+        String::new()
+    }
+    /// restores an ``arrangement``. ``ids`` are the windows of the layout, in the order
+    /// ``buf_ids`` returned back then
+    fn arrange(&mut self, _arrangement: &str, _ids: &[BufferId]) -> Result<(), &'static str> {
+        Ok(())
+    }
 }
 impl_downcast!(sync Layout);
 
 mod builtin_layouts;
-use builtin_layouts::MasterLayout;
+pub use builtin_layouts::{
+    update_focused_master, Anchor, FloatConfig, FloatingLayoutClientAPI, MasterLayout,
+    MasterLayoutClientAPI, MasterPosition, Relative, LAYOUT_NAMES,
+};
+use builtin_layouts::{FloatingLayout, MonocleLayout, SplitLayout, TabLayout};
+
+mod session;
+pub use session::{layer_states, restore_layers, LayerState, WindowState};
 
 struct BufferManager {
     render_buf: Mutex<RenderBuffer>,
@@ -921,12 +2051,16 @@ struct BufferManager {
     layers: Vec<Mutex<Box<dyn Layout>>>,
     focused: Option<BufferRef>,
     term_size: Mutex<(u16, u16)>, // (width, height)
+    /// path of the focused document and the git branch it is on, so the status line doesn't
+    /// look it up on disk for every redraw. Forgotten, when the focus changes
+    git_branch: std::sync::Mutex<Option<(Option<String>, Option<String>)>>,
 }
 
-/// this is completely safe, since the editor should never run without being able to query the
-/// terminal size
+/// the area available to the layouts: the terminal without the status line. This is completely
+/// safe, since the editor should never run without being able to query the terminal size
 pub fn size() -> (u16, u16) {
-    terminal::size().expect("Couldn't fetch terminal size!")
+    let (width, height) = screen_size().expect("Couldn't fetch terminal size!");
+    (width, height.saturating_sub(statusline::height()))
 }
 
 type DynLayout = Box<dyn Layout + Send + Sync>;
@@ -935,17 +2069,21 @@ type DynLayout = Box<dyn Layout + Send + Sync>;
 // handle the lock obtaining stuff, instead of direct method calls
 impl BufferManager {
     fn new() -> BufferManager {
-        let term_size = size();
+        // the whole screen, the status line is drawn into the render buffer as well
+        let term_size = screen_size().expect("Couldn't fetch terminal size!");
         let mut layers = Vec::with_capacity(2);
-        let ml: Box<dyn Layout> = Box::new(MasterLayout::new());
-        layers.push(Mutex::new(ml));
+        let sl: Box<dyn Layout> = Box::new(SplitLayout::new());
+        layers.push(Mutex::new(sl));
+        let fl: Box<dyn Layout> = Box::new(FloatingLayout::new());
+        layers.push(Mutex::new(fl));
         BufferManager {
             render_buf: Mutex::new(RenderBuffer::new(term_size.0, term_size.1)),
             tiled_layouts: RwLock::new(vec![0]), // TODO: not final
-            free_layouts: RwLock::new(Vec::new()), // TODO: not final
+            free_layouts: RwLock::new(vec![1]),  // TODO: not final
             layers,
             focused: None,
             term_size: Mutex::new(term_size),
+            git_branch: std::sync::Mutex::new(None),
         }
     }
 
@@ -953,16 +2091,96 @@ impl BufferManager {
         let mut render_buf = self.render_buf.lock().await;
         render_buf.clear();
         logger::log(LogLevel::Normal, "cleared render_buf bitmap").await;
-        for i in self.layers.len() - 1..=0 {
+        // the top most layer has to be rendered first, since it locks the cells it writes to
+        for i in (0..self.layers.len()).rev() {
             logger::log(LogLevel::Normal, format!("rendering layer {i}...").as_str()).await;
             self.layers[i].lock().await.render(&mut render_buf).await;
         }
         logger::log(LogLevel::Normal, "finish rendering layers").await;
+        if let Some(line) = self.statusline().await {
+            let (term_width, term_height) = screen_size()?;
+            let y = term_height.saturating_sub(1) as usize;
+            for (range, style) in line.styles.iter() {
+                render_buf
+                    .add_ctrl_code(ANSICode::Reset, range.start, y, term_width)
+                    .await;
+                for code in style.codes() {
+                    render_buf
+                        .add_ctrl_code(code, range.start, y, term_width)
+                        .await;
+                }
+            }
+            render_buf.write_str(0, y, term_width, &line.text).await;
+        }
         render_buf.flush().await?;
+        drop(render_buf);
+        self.place_cursor().await?;
         logger::log(LogLevel::Normal, "finish rerendering").await;
         Ok(())
     }
 
+    /// where the terminal cursor belongs: on the command line while typing a command, otherwise
+    /// at the cursor of the focused window
+    async fn cursor_screen_pos(&self) -> Option<(u16, u16)> {
+        let line = match prompt::question().await {
+            // the status line puts a space in front of it
+            Some(question) => Some(format!(" {question}")),
+            None => keymap::cmdline()
+                .await
+                .map(|cmdline| format!(" :{cmdline}")),
+        };
+        if let Some(line) = line {
+            if statusline::enabled() {
+                let (width, height) = screen_size().ok()?;
+                let x = std::cmp::min(line.chars().count() as u16, width.saturating_sub(1));
+                return Some((x, height.saturating_sub(1)));
+            }
+        }
+        self.get_focused()
+            .await
+            .ok()
+            .map(|buf| buf.cursor_screen_pos())
+    }
+
+    async fn place_cursor(&self) -> std::io::Result<()> {
+        if let Some((x, y)) = self.cursor_screen_pos().await {
+            set_cursor(x, y)?;
+        }
+        Ok(())
+    }
+
+    async fn statusline(&self) -> Option<StatusLine> {
+        if !statusline::enabled() {
+            return None;
+        }
+        let (mut path, mut modified, mut readonly, mut cursor, mut line_count) =
+            (None, false, false, CursorPosition { x: 0, y: 0 }, 0);
+        let mut format = FileFormat::default();
+        if let Ok(buf) = self.get_focused().await {
+            let doc = buf.doc.read().unwrap();
+            path = doc.path().map(|path| path.to_string());
+            modified = doc.is_modified();
+            readonly = doc.is_readonly();
+            format = doc.format();
+            line_count = doc.line_count();
+            cursor = buf.cursor_pos;
+        }
+        let ctx = StatusContext {
+            mode: mode::current().await,
+            git_branch: self.git_branch(path.as_deref()),
+            path,
+            modified,
+            readonly,
+            format,
+            cursor,
+            line_count,
+            cmdline: keymap::cmdline().await,
+            message: prompt::question().await,
+            recording: keymap::recording().await,
+        };
+        Some(statusline::build(&ctx, screen_size().ok()?.0 as usize).await)
+    }
+
     async fn add_tiled_layer(&mut self, layout: DynLayout) {
         self.tiled_layouts.write().await.push(self.layers.len());
         self.add_layer(layout);
@@ -977,9 +2195,6 @@ impl BufferManager {
     }
 
     // TODO: make it so, that you can optionally switch focus on buffer add
-    async fn add_new_buf(&self, layer: u8, id: BufferId) -> Result<BufferId, &'static str> {
-        self.add_buf(layer, id, Buffer::default()).await
-    }
     async fn add_buf(
         &self,
         layer: u8,
@@ -1005,31 +2220,51 @@ impl BufferManager {
             return Err("Overflow!");
         }
         let res = self.layers[layer].lock().await.rem_buf(id).await;
-        if res.is_ok() {
-            match self.layers[layer].lock().await.get_next_focused() {
-                Some(id) => {
+        let was_focused =
+            matches!(&self.focused, Some(f) if f.layer as usize == layer && f.id == id);
+        if res.is_ok() && was_focused {
+            // prefer the same layer, then go from the top most layer down
+            let layers = std::iter::once(layer).chain((0..self.layers.len()).rev());
+            self.focused = None;
+            for layer in layers {
+                if let Some(id) = self.layers[layer].lock().await.get_next_focused() {
                     self.focused = Some(BufferRef {
                         layer: layer as u8,
                         id,
-                    })
+                    });
+                    break;
                 }
-                None => {}
             }
         }
         res
     }
 
     async fn change_focus(&mut self, bufman_ref: BufferRef) -> Result<(), &'static str> {
-        self.focused = Some(bufman_ref.clone());
-        let buf = self.get_buf(bufman_ref.layer, bufman_ref.id).await?;
-        let (x, y) = buf.get_start_of_text();
-        self.render_buf
+        self.layers[bufman_ref.layer as usize]
             .lock()
             .await
-            .add_last_ctrl_code(ANSICode::SetCursor(x, y));
+            .focus_changed(bufman_ref.id);
+        self.get_buf(bufman_ref.layer, bufman_ref.id).await?;
+        self.focused = Some(bufman_ref.clone());
+        // e.g. after a checkout, switching windows shows the new branch
+        *self.git_branch.get_mut().unwrap() = None;
         Ok(())
     }
 
+    /// the git branch of the focused document, only looked up again for another file or after a
+    /// focus change
+    fn git_branch(&self, path: Option<&str>) -> Option<String> {
+        let mut cache = self.git_branch.lock().unwrap();
+        match cache.as_ref() {
+            Some((cached, branch)) if cached.as_deref() == path => branch.clone(),
+            _ => {
+                let branch = statusline::git_branch(path);
+                *cache = Some((path.map(|path| path.to_string()), branch.clone()));
+                branch
+            }
+        }
+    }
+
     async fn get_buf(
         &self,
         layer: u8,
@@ -1053,10 +2288,15 @@ impl BufferManager {
     }
 
     async fn resize(&self) -> std::io::Result<()> {
-        let (w, h) = terminal::size().unwrap();
+        let (w, h) = screen_size().unwrap();
         let mut lock = self.term_size.lock().await;
         lock.0 = w;
         lock.1 = h;
+        drop(lock);
+        *self.render_buf.lock().await = RenderBuffer::new(w, h);
+        for layer in self.layers.iter() {
+            layer.lock().await.term_resized().await;
+        }
         self.rerender().await
     }
 }
@@ -1,205 +1,50 @@
 use super::*;
-struct FloatingLayout {
-    buffers: HashMap<BufferId, Buffer>,
+mod floating;
+mod grid;
+mod master;
+mod monocle;
+mod split;
+mod tabs;
+pub use floating::{Anchor, FloatConfig, FloatingLayout, FloatingLayoutClientAPI, Relative};
+pub use grid::GridLayout;
+pub use master::{update_focused_master, MasterLayout, MasterLayoutClientAPI, MasterPosition};
+pub use monocle::MonocleLayout;
+pub use split::SplitLayout;
+pub use tabs::TabLayout;
+
+/// names accepted by ``:layout``
+pub const LAYOUT_NAMES: [&str; 6] = ["split", "master", "tabs", "monocle", "grid", "floating"];
+
+/// creates an empty layout by its name. ``monocle`` is missing here, since it wraps the previous
+/// layout of the layer instead of replacing it
+pub(super) fn new_layout(name: &str) -> Option<Box<dyn Layout>> {
+    Some(match name {
+        "split" => Box::new(SplitLayout::new()),
+        "master" => Box::new(MasterLayout::new()),
+        "tabs" => Box::new(TabLayout::new()),
+        "grid" => Box::new(GridLayout::new()),
+        "floating" => Box::new(FloatingLayout::new()),
+        _ => return None,
+    })
 }
 
-#[derive(Debug)]
-pub struct MasterLayout {
-    master_id: BufferId,
-    top_key: BufferId,
-    master: Option<Buffer>,
-    split_width: u16,
-    buffers: HashMap<BufferId, Buffer>,
-}
-
-trait MasterLayoutClientAPI {
-    async fn make_master(&self) -> Result<(), &str>;
-}
-
-impl MasterLayoutClientAPI for ClientBuffer {
-    async fn make_master(&self) -> Result<(), &str> {
-        match bufman_read().await.layers[self.layer() as usize]
-            .lock()
-            .await
-            .downcast_mut::<MasterLayout>()
-        {
-            Some(masterl) => masterl.change_master(self.id()),
-            None => Err("Layer is not a MasterLayout"),
-        }
-    }
-}
-
-impl MasterLayout {
-    pub fn new() -> Self {
-        MasterLayout {
-            master_id: u32::MAX,
-            top_key: 0,
-            master: None,
-            split_width: terminal::size().expect("Could not get terminal size!").0 / 2,
-            buffers: HashMap::new(),
-        }
-    }
-    async fn reorder(&mut self) {
-        let len = self.buffers.len() as u16;
-        let (term_width, term_height) = terminal::size()
-            .expect("Masterlayout reorder function couldn't get size! This should be impossible");
-
-        let mut master = self.master.take().expect("BUG: master field not set");
-        (master.offx, master.offy) = (0, 0);
-        master.width = if self.buffers.len() > 0 {
-            self.split_width
-        } else {
-            term_width
-        };
-        if let Some(border) = &mut master.border {
-            border.show_all(true);
-        }
-        master.height = term_height;
-        self.master = Some(master);
-        if self.buffers.len() > 0 {
-            let buffer_height = term_height / len;
-            let mut keys: Vec<BufferId> = self.buffers.keys().map(|k| *k).collect();
-            keys.sort();
-            for (i, key) in keys.iter().enumerate() {
-                let buf = self.buffers.get_mut(key).unwrap();
-                if let Some(border) = &mut buf.border {
-                    border.showl(false);
-                    border.showt(false);
-                }
-                buf.height = buffer_height;
-                buf.width = term_width - self.split_width;
-                buf.offx = self.split_width;
-                buf.offy = i as u16 * buf.height;
-            }
-
-            // self.buffers.values_mut().enumerate().for_each(|(i, buf)| {
-            //     let msg = format!("{i}: {buf:?}");
-            //     block_on(logger::log(LogLevel::Debug, msg.as_str()));
-            //     // futs.push(async move { logger::log(LogLevel::Debug, msg.as_str()).await });
-            // });
-
-            // top one needs to have a top border
-            if let Some(border) = &mut self.buffers.get_mut(&keys[0]).unwrap().border {
-                border.toggle_top();
-            }
-
-            // make sure the last buffer takes up all the remaining space
-            self.buffers.get_mut(&keys[keys.len() - 1]).unwrap().height =
-                term_height - buffer_height * (self.buffers.len() - 1) as u16;
-        }
-
-        // BUFMAN_GLOB.write().await.rerender().await
-    }
-
-    pub fn change_master(&mut self, new_master_id: BufferId) -> Result<(), &'static str> {
-        match self.master.take() {
-            Some(master) => match self.buffers.try_insert(self.master_id, master) {
-                Ok(_) => match self.buffers.remove(&new_master_id) {
-                    Some(new_master) => {
-                        self.master = Some(new_master);
-                        self.master_id = new_master_id;
-                        Ok(())
-                    }
-                    None => Err("New master not found!"),
-                },
-                Err(_) => Err("Master already in buffers?"),
-            },
-            None => panic!("BUG: master field not set"),
-        }
-    }
-
-    fn get_id(&mut self) -> BufferId {
-        let ret = self.top_key;
-        self.top_key += 1;
-        ret
-    }
-}
-
-#[async_trait]
-impl Layout for MasterLayout {
-    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
-        if self.master_id == name {
-            return Ok(self.master.as_ref().expect("get_buf: master is None, but master_id matches requested id!"));
-        }
-        match self.buffers.get(&name) {
-            Some(buf) => Ok(buf),
-            None => Err("not found"),
-        }
-    }
-    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
-        if self.master_id == name {
-            return Ok(self.master.as_mut().unwrap());
-        }
-        match self.buffers.get_mut(&name) {
-            Some(buf) => Ok(buf),
-            None => Err("not found"),
-        }
-    }
-    async fn render(&mut self, render_buf: &mut RenderBuffer) {
-        if let None = self.master {
-            return;
-        }
-        let mut buffers = vec![self.master.as_ref().unwrap()];
-        buffers.extend(self.buffers.values());
-        render_internal_faster(buffers.into_iter(), render_buf).await;
-    }
-
-    async fn add_buf(&mut self, _name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
-        let name = self.get_id();
-        if self.master.is_none() {
-            self.master = Some(buf);
-            self.master_id = name;
-        } else if let None = self.buffers.get(&name) {
-            self.buffers.insert(name, buf);
-        } else {
-            return Err("duplicate");
-        }
-        self.reorder().await;
-        return Ok(name);
-    }
-    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
-        let mut reorder = true;
-        let res = if self.master_id == name {
-            let new_master_id = self.buffers.keys().next();
-            match new_master_id {
-                Some(key) => {
-                    let key = *key;
-                    self.master_id = key;
-                    let old_master = self.master.take().expect("rem_buf second impossible state");
-                    self.master =
-                        Some(self.buffers.remove(&key).expect("rem_buf impossible state"));
-                    Ok(old_master)
-                }
-                None => {
-                    reorder = false;
-                    match self.master.take() {
-                        Some(buf) => {
-                            self.master_id = u32::MAX;
-                            Ok(buf)}
-                        ,
-                        None => Err("Masterlayout empty!"),
-                    }
-                }
-            }
-        } else {
-            match self.buffers.remove(&name) {
-                Some(buf) => Ok(buf),
-                None => Err("not found"),
-            }
-        };
-        if res.is_ok() && reorder {
-            self.reorder().await;
-        }
-        res
-    }
-    fn is_full(&self) -> bool {
-        (self.buffers.len() + (if self.master.is_some() { 1 } else { 0 })) >= 11
-    }
-
-    fn get_next_focused(&self) -> Option<BufferId> {
-        match self.master.as_ref() {
-            Some(_) => Some(self.master_id),
-            None => None,
-        }
-    }
+/// the name ``new_layout`` takes to create a layout like ``layout``. A zoomed layer is named after
+/// the layout it wraps
+pub(super) fn layout_name(layout: &dyn Layout) -> Option<&'static str> {
+    if let Some(monocle) = layout.downcast_ref::<MonocleLayout>() {
+        return layout_name(monocle.inner());
+    }
+    Some(if layout.is::<SplitLayout>() {
+        "split"
+    } else if layout.is::<MasterLayout>() {
+        "master"
+    } else if layout.is::<TabLayout>() {
+        "tabs"
+    } else if layout.is::<GridLayout>() {
+        "grid"
+    } else if layout.is::<FloatingLayout>() {
+        "floating"
+    } else {
+        return None;
+    })
 }
@@ -0,0 +1,392 @@
+use super::*;
+
+/// which corner of the floating window is placed at the configured position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    NorthWest,
+    NorthEast,
+    SouthWest,
+    SouthEast,
+}
+
+/// what the position of a floating window is relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relative {
+    Editor,
+    /// the cursor of the focused window, at the time the window gets configured
+    Cursor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatConfig {
+    pub relative: Relative,
+    pub anchor: Anchor,
+    pub row: i32,
+    pub col: i32,
+    pub width: u16,
+    pub height: u16,
+    /// windows with a higher z-index are drawn on top of the others
+    pub z_index: u16,
+}
+
+impl Default for FloatConfig {
+    fn default() -> Self {
+        FloatConfig {
+            relative: Relative::Editor,
+            anchor: Anchor::NorthWest,
+            row: 0,
+            col: 0,
+            width: 20,
+            height: 20,
+            z_index: 50,
+        }
+    }
+}
+
+impl FloatConfig {
+    /// a window of the given size in the middle of the editor
+    pub fn centered(width: u16, height: u16) -> Self {
+        let (term_width, term_height) = size();
+        FloatConfig {
+            row: term_height.saturating_sub(height) as i32 / 2,
+            col: term_width.saturating_sub(width) as i32 / 2,
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// resolves the config to an area on the screen. ``origin`` is the screen position the
+    /// config is relative to. The window is moved (and shrunk if necessary) to stay on screen
+    pub fn rect(&self, screen: (u16, u16), origin: (u16, u16)) -> Rect {
+        let width = std::cmp::min(self.width, screen.0).max(1);
+        let height = std::cmp::min(self.height, screen.1).max(1);
+        let mut x = origin.0 as i32 + self.col;
+        let mut y = origin.1 as i32 + self.row;
+        if let Anchor::NorthEast | Anchor::SouthEast = self.anchor {
+            x -= width as i32;
+        }
+        if let Anchor::SouthWest | Anchor::SouthEast = self.anchor {
+            y -= height as i32;
+        }
+        Rect {
+            x: x.clamp(0, screen.0.saturating_sub(width) as i32) as u16,
+            y: y.clamp(0, screen.1.saturating_sub(height) as i32) as u16,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Float {
+    buf: Buffer,
+    config: FloatConfig,
+    origin: (u16, u16),
+}
+
+/// Layout for popups and other floating windows. Every window has an explicit position and size
+/// and they may overlap each other, as well as the tiled layers below
+#[derive(Debug)]
+pub struct FloatingLayout {
+    top_key: BufferId,
+    floats: HashMap<BufferId, Float>,
+}
+
+impl FloatingLayout {
+    pub fn new() -> Self {
+        FloatingLayout {
+            top_key: 0,
+            floats: HashMap::new(),
+        }
+    }
+
+    fn get_id(&mut self) -> BufferId {
+        let ret = self.top_key;
+        self.top_key += 1;
+        ret
+    }
+
+    fn place(float: &mut Float, screen: (u16, u16)) {
+        let rect = float.config.rect(screen, float.origin);
+        let buf = &mut float.buf;
+        (buf.offx, buf.offy) = (rect.x, rect.y);
+        (buf.width, buf.height) = (rect.width, rect.height);
+    }
+
+    fn reorder(&mut self) {
+        let screen = size();
+        for float in self.floats.values_mut() {
+            FloatingLayout::place(float, screen);
+        }
+    }
+
+    pub fn configure(
+        &mut self,
+        id: BufferId,
+        config: FloatConfig,
+        cursor: (u16, u16),
+    ) -> Result<(), &'static str> {
+        let float = self.floats.get_mut(&id).ok_or("not found")?;
+        float.origin = match config.relative {
+            Relative::Editor => (0, 0),
+            Relative::Cursor => cursor,
+        };
+        float.config = config;
+        FloatingLayout::place(float, size());
+        Ok(())
+    }
+
+    pub fn config(&self, id: BufferId) -> Result<FloatConfig, &'static str> {
+        Ok(self.floats.get(&id).ok_or("not found")?.config)
+    }
+
+    /// puts the window on top of all others
+    pub fn raise(&mut self, id: BufferId) -> Result<(), &'static str> {
+        let others_top = self
+            .floats
+            .iter()
+            .filter(|(other, _)| **other != id)
+            .map(|(_, float)| float.config.z_index)
+            .max();
+        let float = self.floats.get_mut(&id).ok_or("not found")?;
+        if let Some(top) = others_top.filter(|top| *top >= float.config.z_index) {
+            float.config.z_index = top.saturating_add(1);
+        }
+        Ok(())
+    }
+
+    /// ids sorted from the top most window to the bottom most one
+    fn by_z_index(&self) -> Vec<BufferId> {
+        let mut ids: Vec<BufferId> = self.floats.keys().copied().collect();
+        // newer windows win on equal z-index
+        ids.sort_by(|a, b| {
+            let (za, zb) = (self.floats[a].config.z_index, self.floats[b].config.z_index);
+            zb.cmp(&za).then(b.cmp(a))
+        });
+        ids
+    }
+}
+
+#[async_trait]
+impl Layout for FloatingLayout {
+    async fn render(&mut self, render_buf: &mut RenderBuffer) {
+        // the cells of the render buffer are locked by whoever writes them first, so the top most
+        // window has to be rendered first
+        let ids = self.by_z_index();
+        render_internal_faster(ids.iter().map(|id| &self.floats[id].buf), render_buf).await;
+    }
+    async fn add_buf(&mut self, _name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
+        let id = self.get_id();
+        let mut float = Float {
+            buf,
+            config: FloatConfig::centered(40, 10),
+            origin: (0, 0),
+        };
+        FloatingLayout::place(&mut float, size());
+        self.floats.insert(id, float);
+        Ok(id)
+    }
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str> {
+        if self.floats.contains_key(&name) {
+            return Err("duplicate");
+        }
+        self.top_key = self.top_key.max(name + 1);
+        let mut float = Float {
+            buf,
+            config: FloatConfig::centered(40, 10),
+            origin: (0, 0),
+        };
+        FloatingLayout::place(&mut float, size());
+        self.floats.insert(name, float);
+        Ok(())
+    }
+    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
+        match self.floats.remove(&name) {
+            Some(float) => Ok(float.buf),
+            None => Err("not found"),
+        }
+    }
+    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
+        match self.floats.get(&name) {
+            Some(float) => Ok(&float.buf),
+            None => Err("not found"),
+        }
+    }
+    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
+        match self.floats.get_mut(&name) {
+            Some(float) => Ok(&mut float.buf),
+            None => Err("not found"),
+        }
+    }
+    fn buf_ids(&self) -> Vec<BufferId> {
+        self.by_z_index()
+    }
+    fn is_full(&self) -> bool {
+        false
+    }
+    fn get_next_focused(&self) -> Option<BufferId> {
+        self.by_z_index().first().copied()
+    }
+    async fn term_resized(&mut self) {
+        self.reorder();
+    }
+    /// ``row,col,width,height,z-index`` of every window, as it is placed on the screen
+    fn arrangement(&self) -> String {
+        self.buf_ids()
+            .iter()
+            .map(|id| {
+                let Float { buf, config, .. } = &self.floats[id];
+                format!(
+                    "{},{},{},{},{}",
+                    buf.offy, buf.offx, buf.width, buf.height, config.z_index
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    fn arrange(&mut self, arrangement: &str, ids: &[BufferId]) -> Result<(), &'static str> {
+        let configs = arrangement
+            .split_whitespace()
+            .map(parse_placement)
+            .collect::<Option<Vec<_>>>()
+            .ok_or("invalid window placement")?;
+        if configs.len() != ids.len() {
+            return Err("windows don't match");
+        }
+        for (id, config) in ids.iter().zip(configs) {
+            self.configure(*id, config, (0, 0))?;
+        }
+        Ok(())
+    }
+}
+
+/// the config of a window placed at ``row,col,width,height,z-index``
+fn parse_placement(placement: &str) -> Option<FloatConfig> {
+    let nums = placement
+        .split(',')
+        .map(|num| num.parse::<u16>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let [row, col, width, height, z_index] = nums[..] else {
+        return None;
+    };
+    Some(FloatConfig {
+        row: row as i32,
+        col: col as i32,
+        width,
+        height,
+        z_index,
+        ..Default::default()
+    })
+}
+
+#[allow(async_fn_in_trait)]
+pub trait FloatingLayoutClientAPI {
+    async fn configure(&self, config: FloatConfig) -> Result<(), &'static str>;
+    async fn float_config(&self) -> Result<FloatConfig, &'static str>;
+    async fn raise(&self) -> Result<(), &'static str>;
+}
+
+impl FloatingLayoutClientAPI for ClientBuffer {
+    async fn configure(&self, config: FloatConfig) -> Result<(), &'static str> {
+        // the cursor has to be fetched before locking the layer, the focused window might be on it
+        let cursor = match focused().await {
+            Ok(focused) => focused.deref().await.cursor_screen_pos(),
+            Err(_) => (0, 0),
+        };
+        match bufman_read().await.layers[self.layer() as usize]
+            .lock()
+            .await
+            .downcast_mut::<FloatingLayout>()
+        {
+            Some(floating) => floating.configure(self.id(), config, cursor),
+            None => Err("Layer is not a FloatingLayout"),
+        }
+    }
+    async fn float_config(&self) -> Result<FloatConfig, &'static str> {
+        match bufman_read().await.layers[self.layer() as usize]
+            .lock()
+            .await
+            .downcast_ref::<FloatingLayout>()
+        {
+            Some(floating) => floating.config(self.id()),
+            None => Err("Layer is not a FloatingLayout"),
+        }
+    }
+    async fn raise(&self) -> Result<(), &'static str> {
+        match bufman_read().await.layers[self.layer() as usize]
+            .lock()
+            .await
+            .downcast_mut::<FloatingLayout>()
+        {
+            Some(floating) => floating.raise(self.id()),
+            None => Err("Layer is not a FloatingLayout"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (u16, u16) = (80, 24);
+
+    fn config(anchor: Anchor, row: i32, col: i32) -> FloatConfig {
+        FloatConfig {
+            anchor,
+            row,
+            col,
+            width: 10,
+            height: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn anchors() {
+        let nw = config(Anchor::NorthWest, 1, 0).rect(SCREEN, (20, 10));
+        assert_eq!((nw.x, nw.y), (20, 11));
+        let se = config(Anchor::SouthEast, 0, 0).rect(SCREEN, (20, 10));
+        assert_eq!((se.x, se.y), (10, 5));
+    }
+
+    #[test]
+    fn stays_on_screen() {
+        let rect = config(Anchor::NorthWest, 1, 0).rect(SCREEN, (75, 22));
+        assert_eq!(
+            rect,
+            Rect {
+                x: 70,
+                y: 19,
+                width: 10,
+                height: 5
+            }
+        );
+        let too_big = FloatConfig {
+            width: 100,
+            ..config(Anchor::NorthWest, 0, -5)
+        };
+        assert_eq!(too_big.rect(SCREEN, (0, 0)).x, 0);
+        assert_eq!(too_big.rect(SCREEN, (0, 0)).width, 80);
+        // a terminal with only the status line leaves no room at all
+        let rect = config(Anchor::SouthEast, 0, 0).rect((80, 0), (0, 0));
+        assert_eq!((rect.y, rect.height), (0, 1));
+    }
+
+    #[test]
+    fn placements() {
+        let config = parse_placement("3,7,30,10,60").unwrap();
+        assert_eq!(
+            config.rect(SCREEN, (0, 0)),
+            Rect {
+                x: 7,
+                y: 3,
+                width: 30,
+                height: 10
+            }
+        );
+        assert_eq!(config.z_index, 60);
+        assert!(parse_placement("3,7,30,10").is_none());
+        assert!(parse_placement("3,-7,30,10,60").is_none());
+    }
+}
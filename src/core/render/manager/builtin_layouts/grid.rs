@@ -0,0 +1,190 @@
+use super::*;
+
+/// splits ``screen`` into ``count`` cells, filling it row by row. The grid is as square as
+/// possible, the cells of an incomplete last row get wider to fill it
+fn grid_rects(count: usize, screen: Rect) -> Vec<Rect> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let cols = (count as f32).sqrt().ceil() as usize;
+    let rows = count.div_ceil(cols);
+    let mut ret = Vec::with_capacity(count);
+    let mut rest = screen;
+    for row in 0..rows {
+        let (mut line, below) = rest.cut(
+            SplitDirection::Horizontal,
+            rest.height / (rows - row) as u16,
+        );
+        rest = below;
+        let cells = std::cmp::min(cols, count - ret.len());
+        for col in 0..cells {
+            let (cell, right) =
+                line.cut(SplitDirection::Vertical, line.width / (cells - col) as u16);
+            ret.push(cell);
+            line = right;
+        }
+    }
+    ret
+}
+
+/// puts all windows into an evenly sized grid, which gets rebuilt whenever a window is added or
+/// removed
+#[derive(Debug)]
+pub struct GridLayout {
+    top_key: BufferId,
+    order: Vec<BufferId>,
+    buffers: HashMap<BufferId, Buffer>,
+}
+
+impl GridLayout {
+    pub fn new() -> Self {
+        GridLayout {
+            top_key: 0,
+            order: Vec::new(),
+            buffers: HashMap::new(),
+        }
+    }
+
+    fn get_id(&mut self) -> BufferId {
+        let ret = self.top_key;
+        self.top_key += 1;
+        ret
+    }
+
+    fn reorder(&mut self) {
+        let (width, height) = size();
+        let screen = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        for (id, rect) in self.order.iter().zip(grid_rects(self.order.len(), screen)) {
+            let buf = self
+                .buffers
+                .get_mut(id)
+                .expect("BUG: grid order and buffers out of sync");
+            (buf.offx, buf.offy) = (rect.x, rect.y);
+            (buf.width, buf.height) = (rect.width, rect.height);
+        }
+    }
+}
+
+#[async_trait]
+impl Layout for GridLayout {
+    async fn render(&mut self, render_buf: &mut RenderBuffer) {
+        render_internal_faster(self.buffers.values(), render_buf).await;
+    }
+    async fn add_buf(&mut self, _name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
+        let id = self.get_id();
+        self.adopt_buf(id, buf).await?;
+        Ok(id)
+    }
+    async fn add_buf_next_to(
+        &mut self,
+        target: BufferId,
+        _dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<BufferId, &'static str> {
+        let id = self.add_buf(0, buf).await?;
+        // move it right behind the target
+        if let Some(idx) = self.order.iter().position(|id| *id == target) {
+            self.order.pop();
+            self.order.insert(idx + 1, id);
+            self.reorder();
+        }
+        Ok(id)
+    }
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str> {
+        if self.is_full() {
+            return Err("too many windows");
+        }
+        if self.buffers.contains_key(&name) {
+            return Err("duplicate");
+        }
+        self.top_key = self.top_key.max(name + 1);
+        self.order.push(name);
+        self.buffers.insert(name, buf);
+        self.reorder();
+        Ok(())
+    }
+    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
+        let buf = self.buffers.remove(&name).ok_or("not found")?;
+        self.order.retain(|id| *id != name);
+        self.reorder();
+        Ok(buf)
+    }
+    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
+        self.buffers.get(&name).ok_or("not found")
+    }
+    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
+        self.buffers.get_mut(&name).ok_or("not found")
+    }
+    fn buf_ids(&self) -> Vec<BufferId> {
+        self.order.clone()
+    }
+    fn is_full(&self) -> bool {
+        self.buffers.len() >= split::MAX_WINDOWS
+    }
+    fn get_next_focused(&self) -> Option<BufferId> {
+        self.order.first().copied()
+    }
+    async fn swap(&mut self, a: BufferId, b: BufferId) -> Result<(), &'static str> {
+        let ia = self
+            .order
+            .iter()
+            .position(|id| *id == a)
+            .ok_or("not found")?;
+        let ib = self
+            .order
+            .iter()
+            .position(|id| *id == b)
+            .ok_or("not found")?;
+        self.order.swap(ia, ib);
+        self.reorder();
+        Ok(())
+    }
+    async fn term_resized(&mut self) {
+        self.reorder();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 24,
+    };
+
+    #[test]
+    fn square_grid() {
+        let rects = grid_rects(4, SCREEN);
+        let cells: Vec<(u16, u16, u16, u16)> = rects
+            .iter()
+            .map(|r| (r.x, r.y, r.width, r.height))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 0, 40, 12),
+                (40, 0, 40, 12),
+                (0, 12, 40, 12),
+                (40, 12, 40, 12)
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_last_row_fills_width() {
+        let rects = grid_rects(5, SCREEN);
+        assert_eq!(rects.len(), 5);
+        // 3 columns, 2 rows
+        assert_eq!(rects[2].width + rects[1].width + rects[0].width, 80);
+        assert_eq!((rects[3].y, rects[3].width), (12, 40));
+        assert_eq!((rects[4].x, rects[4].width), (40, 40));
+    }
+}
@@ -0,0 +1,474 @@
+use super::*;
+
+/// where the master area is on the screen, the other windows get stacked in the remaining space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterPosition {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl MasterPosition {
+    pub fn name(self) -> &'static str {
+        match self {
+            MasterPosition::Left => "left",
+            MasterPosition::Right => "right",
+            MasterPosition::Top => "top",
+            MasterPosition::Bottom => "bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "left" => MasterPosition::Left,
+            "right" => MasterPosition::Right,
+            "top" => MasterPosition::Top,
+            "bottom" => MasterPosition::Bottom,
+            _ => return None,
+        })
+    }
+
+    /// the direction the screen is split in, to separate the master area from the stack
+    fn split_dir(self) -> SplitDirection {
+        match self {
+            MasterPosition::Left | MasterPosition::Right => SplitDirection::Vertical,
+            MasterPosition::Top | MasterPosition::Bottom => SplitDirection::Horizontal,
+        }
+    }
+}
+
+const MAX_WINDOWS: usize = 11;
+const MIN_RATIO: f32 = 0.1;
+const MAX_RATIO: f32 = 0.9;
+
+/// cuts ``area`` into ``count`` equally sized pieces, that are stacked in ``dir``
+fn stack(area: Rect, count: usize, dir: SplitDirection, out: &mut Vec<Rect>) {
+    let mut rest = area;
+    for i in 0..count {
+        let (piece, next) = rest.cut(dir, rest.extent(dir) / (count - i) as u16);
+        out.push(piece);
+        rest = next;
+    }
+}
+
+/// the areas of ``count`` windows, the first ``masters`` of them share the master area
+fn master_rects(
+    count: usize,
+    masters: usize,
+    ratio: f32,
+    position: MasterPosition,
+    screen: Rect,
+) -> Vec<Rect> {
+    let mut ret = Vec::with_capacity(count);
+    let masters = std::cmp::min(masters, count);
+    let dir = position.split_dir();
+    // the windows inside of an area are stacked across the split
+    let inner_dir = match dir {
+        SplitDirection::Vertical => SplitDirection::Horizontal,
+        SplitDirection::Horizontal => SplitDirection::Vertical,
+    };
+    if masters == 0 || masters == count {
+        stack(screen, count, inner_dir, &mut ret);
+        return ret;
+    }
+    let extent = screen.extent(dir);
+    let master_extent =
+        ((extent as f32 * ratio).round() as u16).clamp(1, extent.saturating_sub(1).max(1));
+    let (master_area, stack_area) = match position {
+        MasterPosition::Left | MasterPosition::Top => screen.cut(dir, master_extent),
+        MasterPosition::Right | MasterPosition::Bottom => {
+            let (stack_area, master_area) = screen.cut(dir, extent - master_extent);
+            (master_area, stack_area)
+        }
+    };
+    stack(master_area, masters, inner_dir, &mut ret);
+    stack(stack_area, count - masters, inner_dir, &mut ret);
+    ret
+}
+
+/// moves ``id`` into the (first) master slot. If it already is there, it swaps places with the
+/// next window, like dwm's zoom
+fn promote(order: &mut Vec<BufferId>, id: BufferId) -> Result<(), &'static str> {
+    let idx = order.iter().position(|i| *i == id).ok_or("not found")?;
+    if idx == 0 {
+        if order.len() > 1 {
+            order.swap(0, 1);
+        }
+    } else {
+        order.remove(idx);
+        order.insert(0, id);
+    }
+    Ok(())
+}
+
+/// A master area holding the most important windows, the rest gets stacked next to it
+#[derive(Debug)]
+pub struct MasterLayout {
+    top_key: BufferId,
+    /// the first ``masters`` windows are in the master area
+    order: Vec<BufferId>,
+    buffers: HashMap<BufferId, Buffer>,
+    /// part of the screen taken by the master area
+    ratio: f32,
+    position: MasterPosition,
+    masters: usize,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait MasterLayoutClientAPI {
+    async fn make_master(&self) -> Result<(), &'static str>;
+}
+
+impl MasterLayoutClientAPI for ClientBuffer {
+    async fn make_master(&self) -> Result<(), &'static str> {
+        match bufman_read().await.layers[self.layer() as usize]
+            .lock()
+            .await
+            .downcast_mut::<MasterLayout>()
+        {
+            Some(masterl) => masterl.change_master(self.id()),
+            None => Err("Layer is not a MasterLayout"),
+        }
+    }
+}
+
+/// runs ``f`` on the MasterLayout of the focused window's layer and redraws. ``f`` gets the id of
+/// the focused window
+pub async fn update_focused_master<F>(f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut MasterLayout, BufferId) -> Result<(), &'static str>,
+{
+    let BufferRef { layer, id } = focused_ref().await?;
+    {
+        let handle = bufman_read().await;
+        let mut layout = handle.layers[layer as usize].lock().await;
+        let master = layout
+            .downcast_mut::<MasterLayout>()
+            .ok_or("layer is not a MasterLayout")?;
+        f(master, id)?;
+    }
+    rerender_and_refocus().await
+}
+
+impl Default for MasterLayout {
+    fn default() -> Self {
+        MasterLayout::new()
+    }
+}
+
+impl MasterLayout {
+    pub fn new() -> Self {
+        MasterLayout {
+            top_key: 0,
+            order: Vec::new(),
+            buffers: HashMap::new(),
+            ratio: 0.5,
+            position: MasterPosition::Left,
+            masters: 1,
+        }
+    }
+
+    fn reorder(&mut self) {
+        let (width, height) = size();
+        let screen = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        let rects = master_rects(
+            self.order.len(),
+            self.masters,
+            self.ratio,
+            self.position,
+            screen,
+        );
+        for (id, rect) in self.order.iter().zip(rects) {
+            let buf = self
+                .buffers
+                .get_mut(id)
+                .expect("BUG: master order and buffers out of sync");
+            (buf.offx, buf.offy) = (rect.x, rect.y);
+            (buf.width, buf.height) = (rect.width, rect.height);
+        }
+    }
+
+    /// puts ``new_master_id`` into the master area
+    pub fn change_master(&mut self, new_master_id: BufferId) -> Result<(), &'static str> {
+        promote(&mut self.order, new_master_id)?;
+        self.reorder();
+        Ok(())
+    }
+
+    /// moves every window ``count`` slots further, the last ones wrap around into the master area
+    pub fn rotate(&mut self, count: i32) {
+        if self.order.is_empty() {
+            return;
+        }
+        let count = count.rem_euclid(self.order.len() as i32) as usize;
+        self.order.rotate_right(count);
+        self.reorder();
+    }
+
+    pub fn set_position(&mut self, position: MasterPosition) {
+        self.position = position;
+        self.reorder();
+    }
+
+    pub fn position(&self) -> MasterPosition {
+        self.position
+    }
+
+    /// how many windows share the master area
+    pub fn set_master_count(&mut self, masters: usize) {
+        self.masters = masters;
+        self.reorder();
+    }
+
+    pub fn master_count(&self) -> usize {
+        self.masters
+    }
+
+    /// grows the master area by ``delta`` (a part of the screen, shrinks if negative)
+    pub fn change_ratio(&mut self, delta: f32) {
+        self.ratio = (self.ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+        self.reorder();
+    }
+
+    fn get_id(&mut self) -> BufferId {
+        let ret = self.top_key;
+        self.top_key += 1;
+        ret
+    }
+}
+
+#[async_trait]
+impl Layout for MasterLayout {
+    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
+        match self.buffers.get(&name) {
+            Some(buf) => Ok(buf),
+            None => Err("not found"),
+        }
+    }
+    fn buf_ids(&self) -> Vec<BufferId> {
+        self.order.clone()
+    }
+    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
+        match self.buffers.get_mut(&name) {
+            Some(buf) => Ok(buf),
+            None => Err("not found"),
+        }
+    }
+    async fn render(&mut self, render_buf: &mut RenderBuffer) {
+        render_internal_faster(self.buffers.values(), render_buf).await;
+    }
+
+    async fn add_buf(&mut self, _name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
+        let name = self.get_id();
+        self.adopt_buf(name, buf).await?;
+        return Ok(name);
+    }
+    async fn add_buf_next_to(
+        &mut self,
+        target: BufferId,
+        _dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<BufferId, &'static str> {
+        let name = self.add_buf(0, buf).await?;
+        if let Some(idx) = self.order.iter().position(|id| *id == target) {
+            self.order.pop();
+            self.order.insert(idx + 1, name);
+            self.reorder();
+        }
+        Ok(name)
+    }
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str> {
+        if self.is_full() {
+            return Err("too many windows");
+        }
+        if self.buffers.contains_key(&name) {
+            return Err("duplicate");
+        }
+        self.top_key = self.top_key.max(name + 1);
+        self.order.push(name);
+        self.buffers.insert(name, buf);
+        self.reorder();
+        Ok(())
+    }
+    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
+        let buf = self.buffers.remove(&name).ok_or("not found")?;
+        self.order.retain(|id| *id != name);
+        self.reorder();
+        Ok(buf)
+    }
+    fn is_full(&self) -> bool {
+        self.buffers.len() >= MAX_WINDOWS
+    }
+
+    fn get_next_focused(&self) -> Option<BufferId> {
+        self.order.first().copied()
+    }
+
+    /// resizing along the master split moves the border between master area and stack
+    async fn resize(
+        &mut self,
+        name: BufferId,
+        dir: SplitDirection,
+        delta: i16,
+    ) -> Result<(), &'static str> {
+        if dir != self.position.split_dir() {
+            return Err("no split to resize");
+        }
+        let idx = self
+            .order
+            .iter()
+            .position(|id| *id == name)
+            .ok_or("not found")?;
+        let extent = size_in(dir);
+        let delta = delta as f32 / extent.max(1) as f32;
+        if idx < self.masters {
+            self.change_ratio(delta);
+        } else {
+            self.change_ratio(-delta);
+        }
+        Ok(())
+    }
+    async fn equalize(&mut self) {
+        self.ratio = 0.5;
+        self.reorder();
+    }
+    async fn swap(&mut self, a: BufferId, b: BufferId) -> Result<(), &'static str> {
+        let ia = self
+            .order
+            .iter()
+            .position(|id| *id == a)
+            .ok_or("not found")?;
+        let ib = self
+            .order
+            .iter()
+            .position(|id| *id == b)
+            .ok_or("not found")?;
+        self.order.swap(ia, ib);
+        self.reorder();
+        Ok(())
+    }
+    async fn term_resized(&mut self) {
+        self.reorder();
+    }
+    /// ``<position> <ratio> <masters>``, the windows are already in the right order
+    fn arrangement(&self) -> String {
+        format!("{} {} {}", self.position.name(), self.ratio, self.masters)
+    }
+    fn arrange(&mut self, arrangement: &str, ids: &[BufferId]) -> Result<(), &'static str> {
+        let (position, ratio, masters) = parse_arrangement(arrangement)?;
+        if ids.len() != self.order.len() || ids.iter().any(|id| !self.buffers.contains_key(id)) {
+            return Err("windows don't match");
+        }
+        self.order = ids.to_vec();
+        (self.position, self.ratio, self.masters) = (position, ratio, masters);
+        self.reorder();
+        Ok(())
+    }
+}
+
+fn parse_arrangement(arrangement: &str) -> Result<(MasterPosition, f32, usize), &'static str> {
+    let mut parts = arrangement.split_whitespace();
+    let position = parts
+        .next()
+        .and_then(MasterPosition::from_name)
+        .ok_or("invalid master position")?;
+    let ratio = parts
+        .next()
+        .and_then(|ratio| ratio.parse::<f32>().ok())
+        .filter(|ratio| (MIN_RATIO..=MAX_RATIO).contains(ratio))
+        .ok_or("invalid master ratio")?;
+    let masters = parts
+        .next()
+        .and_then(|masters| masters.parse().ok())
+        .ok_or("invalid master count")?;
+    Ok((position, ratio, masters))
+}
+
+/// extent of the screen in ``dir``
+fn size_in(dir: SplitDirection) -> u16 {
+    let (width, height) = size();
+    match dir {
+        SplitDirection::Vertical => width,
+        SplitDirection::Horizontal => height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 24,
+    };
+
+    fn cells(rects: Vec<Rect>) -> Vec<(u16, u16, u16, u16)> {
+        rects
+            .iter()
+            .map(|r| (r.x, r.y, r.width, r.height))
+            .collect()
+    }
+
+    #[test]
+    fn master_left() {
+        let rects = master_rects(3, 1, 0.5, MasterPosition::Left, SCREEN);
+        assert_eq!(
+            cells(rects),
+            vec![(0, 0, 40, 24), (40, 0, 40, 12), (40, 12, 40, 12)]
+        );
+    }
+
+    #[test]
+    fn master_right_with_ratio() {
+        let rects = master_rects(2, 1, 0.75, MasterPosition::Right, SCREEN);
+        assert_eq!(cells(rects), vec![(20, 0, 60, 24), (0, 0, 20, 24)]);
+    }
+
+    #[test]
+    fn two_masters_on_top() {
+        let rects = master_rects(3, 2, 0.5, MasterPosition::Top, SCREEN);
+        assert_eq!(
+            cells(rects),
+            vec![(0, 0, 40, 12), (40, 0, 40, 12), (0, 12, 80, 12)]
+        );
+    }
+
+    #[test]
+    fn only_masters_fill_screen() {
+        let rects = master_rects(1, 1, 0.3, MasterPosition::Bottom, SCREEN);
+        assert_eq!(cells(rects), vec![(0, 0, 80, 24)]);
+    }
+
+    #[test]
+    fn promote_to_master() {
+        let mut order = vec![0, 1, 2];
+        promote(&mut order, 2).unwrap();
+        assert_eq!(order, vec![2, 0, 1]);
+        // the master itself swaps with the next one
+        promote(&mut order, 2).unwrap();
+        assert_eq!(order, vec![0, 2, 1]);
+        assert!(promote(&mut order, 5).is_err());
+    }
+
+    #[test]
+    fn arrangement_round_trip() {
+        let mut layout = MasterLayout::new();
+        layout.position = MasterPosition::Bottom;
+        layout.ratio = 0.65;
+        layout.masters = 2;
+        assert_eq!(
+            parse_arrangement(&layout.arrangement()),
+            Ok((MasterPosition::Bottom, 0.65, 2))
+        );
+        assert!(parse_arrangement("left 2 1").is_err());
+        assert!(parse_arrangement("middle 0.5 1").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use super::*;
+
+/// Zooms into one window: only the shown window is drawn, using the whole screen. It wraps the
+/// previous layout of the layer, which still owns the windows, so unzooming brings back the old
+/// arrangement unchanged
+pub struct MonocleLayout {
+    inner: Box<dyn Layout>,
+    shown: Option<BufferId>,
+}
+
+impl std::fmt::Debug for MonocleLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MonocleLayout")
+            .field("shown", &self.shown)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MonocleLayout {
+    pub fn new(inner: Box<dyn Layout>, shown: BufferId) -> Self {
+        let mut ret = MonocleLayout { inner, shown: None };
+        ret.show(shown);
+        ret
+    }
+
+    /// gives back the wrapped layout, with the original window sizes
+    pub async fn into_inner(self) -> Box<dyn Layout> {
+        let mut inner = self.inner;
+        inner.term_resized().await;
+        inner
+    }
+
+    /// the wrapped layout
+    pub fn inner(&self) -> &dyn Layout {
+        &*self.inner
+    }
+
+    /// the window filling the screen
+    pub fn shown(&self) -> Option<BufferId> {
+        self.shown
+    }
+
+    fn show(&mut self, name: BufferId) {
+        self.shown = if self.inner.get_buf(name).is_ok() {
+            Some(name)
+        } else {
+            self.inner.get_next_focused()
+        };
+        self.fit();
+    }
+
+    /// makes the shown window fill the screen again, after the inner layout rearranged it
+    fn fit(&mut self) {
+        let Some(shown) = self.shown else { return };
+        let (width, height) = size();
+        if let Ok(buf) = self.inner.get_buf_mut(shown) {
+            (buf.offx, buf.offy) = (0, 0);
+            (buf.width, buf.height) = (width, height);
+        }
+    }
+}
+
+#[async_trait]
+impl Layout for MonocleLayout {
+    async fn render(&mut self, render_buf: &mut RenderBuffer) {
+        if let Some(shown) = self.shown {
+            render_internal_faster(self.inner.get_buf(shown).into_iter(), render_buf).await;
+        }
+    }
+    async fn add_buf(&mut self, name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
+        let id = self.inner.add_buf(name, buf).await?;
+        self.show(id);
+        Ok(id)
+    }
+    async fn add_buf_next_to(
+        &mut self,
+        target: BufferId,
+        dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<BufferId, &'static str> {
+        let id = self.inner.add_buf_next_to(target, dir, buf).await?;
+        self.show(id);
+        Ok(id)
+    }
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str> {
+        self.inner.adopt_buf(name, buf).await?;
+        self.fit();
+        Ok(())
+    }
+    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
+        let buf = self.inner.rem_buf(name).await?;
+        match self.shown {
+            Some(shown) if shown == name => {
+                self.shown = self.inner.get_next_focused();
+                self.fit();
+            }
+            _ => self.fit(),
+        }
+        Ok(buf)
+    }
+    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
+        self.inner.get_buf(name)
+    }
+    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
+        self.inner.get_buf_mut(name)
+    }
+    fn buf_ids(&self) -> Vec<BufferId> {
+        self.inner.buf_ids()
+    }
+    fn is_full(&self) -> bool {
+        self.inner.is_full()
+    }
+    fn get_next_focused(&self) -> Option<BufferId> {
+        self.shown
+    }
+    fn visible_ids(&self) -> Vec<BufferId> {
+        self.shown.into_iter().collect()
+    }
+    async fn resize(
+        &mut self,
+        name: BufferId,
+        dir: SplitDirection,
+        delta: i16,
+    ) -> Result<(), &'static str> {
+        let res = self.inner.resize(name, dir, delta).await;
+        self.fit();
+        res
+    }
+    async fn equalize(&mut self) {
+        self.inner.equalize().await;
+        self.fit();
+    }
+    async fn swap(&mut self, a: BufferId, b: BufferId) -> Result<(), &'static str> {
+        let res = self.inner.swap(a, b).await;
+        self.fit();
+        res
+    }
+    async fn term_resized(&mut self) {
+        self.inner.term_resized().await;
+        self.fit();
+    }
+    fn focus_changed(&mut self, name: BufferId) {
+        self.inner.focus_changed(name);
+        self.show(name);
+    }
+    fn arrangement(&self) -> String {
+        self.inner.arrangement()
+    }
+    fn arrange(&mut self, arrangement: &str, ids: &[BufferId]) -> Result<(), &'static str> {
+        let res = self.inner.arrange(arrangement, ids);
+        self.fit();
+        res
+    }
+}
@@ -0,0 +1,544 @@
+use super::*;
+
+pub(super) const MAX_WINDOWS: usize = 16;
+
+/// Node of the split tree. Leaves are windows, inner nodes split their area in two along
+/// ``dir``, ``ratio`` being the share of the first child
+#[derive(Debug)]
+enum SplitNode {
+    Leaf(BufferId),
+    Split {
+        dir: SplitDirection,
+        ratio: f32,
+        first: Box<SplitNode>,
+        second: Box<SplitNode>,
+    },
+}
+
+impl SplitNode {
+    fn first_extent(ratio: f32, extent: u16) -> u16 {
+        if extent < 2 {
+            return extent;
+        }
+        ((extent as f32 * ratio).round() as u16).clamp(1, extent - 1)
+    }
+
+    fn rects(&self, rect: Rect, out: &mut Vec<(BufferId, Rect)>) {
+        match self {
+            SplitNode::Leaf(id) => out.push((*id, rect)),
+            SplitNode::Split {
+                dir,
+                ratio,
+                first,
+                second,
+            } => {
+                let (r1, r2) = rect.cut(*dir, SplitNode::first_extent(*ratio, rect.extent(*dir)));
+                first.rects(r1, out);
+                second.rects(r2, out);
+            }
+        }
+    }
+
+    fn contains(&self, id: BufferId) -> bool {
+        match self {
+            SplitNode::Leaf(leaf) => *leaf == id,
+            SplitNode::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    fn last_leaf(&self) -> BufferId {
+        match self {
+            SplitNode::Leaf(id) => *id,
+            SplitNode::Split { second, .. } => second.last_leaf(),
+        }
+    }
+
+    fn first_leaf(&self) -> BufferId {
+        match self {
+            SplitNode::Leaf(id) => *id,
+            SplitNode::Split { first, .. } => first.first_leaf(),
+        }
+    }
+
+    /// replaces the leaf ``target`` with a split of ``target`` and ``new``
+    fn split_leaf(&mut self, target: BufferId, new: BufferId, dir: SplitDirection) -> bool {
+        match self {
+            SplitNode::Leaf(id) if *id == target => {
+                *self = SplitNode::Split {
+                    dir,
+                    ratio: 0.5,
+                    first: Box::new(SplitNode::Leaf(target)),
+                    second: Box::new(SplitNode::Leaf(new)),
+                };
+                true
+            }
+            SplitNode::Leaf(_) => false,
+            SplitNode::Split { first, second, .. } => {
+                first.split_leaf(target, new, dir) || second.split_leaf(target, new, dir)
+            }
+        }
+    }
+
+    /// removes the leaf ``target``, its sibling takes over the space of the parent split.
+    /// Returns false if ``target`` is not in this (sub)tree. The root leaf has to be handled by
+    /// the caller
+    fn remove(&mut self, target: BufferId) -> bool {
+        let SplitNode::Split { first, second, .. } = self else {
+            return false;
+        };
+        let remaining = if matches!(**first, SplitNode::Leaf(id) if id == target) {
+            std::mem::replace(&mut **second, SplitNode::Leaf(target))
+        } else if matches!(**second, SplitNode::Leaf(id) if id == target) {
+            std::mem::replace(&mut **first, SplitNode::Leaf(target))
+        } else {
+            return first.remove(target) || second.remove(target);
+        };
+        *self = remaining;
+        true
+    }
+
+    /// grows the window ``target`` by ``delta`` cells along ``dir`` (shrinks it if negative), by
+    /// moving the closest enclosing split with that orientation
+    fn resize(&mut self, target: BufferId, dir: SplitDirection, delta: i16, rect: Rect) -> bool {
+        let SplitNode::Split {
+            dir: split_dir,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        let extent = rect.extent(*split_dir);
+        let first_extent = SplitNode::first_extent(*ratio, extent);
+        let (r1, r2) = rect.cut(*split_dir, first_extent);
+        let in_first = first.contains(target);
+        let handled = if in_first {
+            first.resize(target, dir, delta, r1)
+        } else {
+            second.resize(target, dir, delta, r2)
+        };
+        if handled || *split_dir != dir || !(in_first || second.contains(target)) {
+            return handled;
+        }
+        let delta = if in_first { delta } else { -delta };
+        let new_first = (first_extent as i32 + delta as i32).clamp(1, extent as i32 - 1);
+        *ratio = new_first as f32 / extent as f32;
+        true
+    }
+
+    /// number of windows sharing the space along ``dir`` (nested splits in the other direction
+    /// count as one)
+    fn weight(&self, dir: SplitDirection) -> u32 {
+        match self {
+            SplitNode::Split {
+                dir: split_dir,
+                first,
+                second,
+                ..
+            } if *split_dir == dir => first.weight(dir) + second.weight(dir),
+            _ => 1,
+        }
+    }
+
+    fn equalize(&mut self) {
+        if let SplitNode::Split {
+            dir,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            let w1 = first.weight(*dir);
+            *ratio = w1 as f32 / (w1 + second.weight(*dir)) as f32;
+            first.equalize();
+            second.equalize();
+        }
+    }
+
+    /// the tree in prefix notation: ``h<ratio>``/``v<ratio>`` for splits and ``_`` for windows,
+    /// e.g. ``v0.5 _ h0.3 _ _``
+    fn describe(&self, out: &mut Vec<String>) {
+        match self {
+            SplitNode::Leaf(_) => out.push("_".to_string()),
+            SplitNode::Split {
+                dir,
+                ratio,
+                first,
+                second,
+            } => {
+                let dir = match dir {
+                    SplitDirection::Horizontal => 'h',
+                    SplitDirection::Vertical => 'v',
+                };
+                out.push(format!("{dir}{ratio}"));
+                first.describe(out);
+                second.describe(out);
+            }
+        }
+    }
+
+    /// builds a tree from the notation of ``describe``, the windows are taken from ``ids``
+    fn parse<'a>(
+        tokens: &mut impl Iterator<Item = &'a str>,
+        ids: &mut impl Iterator<Item = BufferId>,
+    ) -> Result<SplitNode, &'static str> {
+        let token = tokens.next().ok_or("incomplete split tree")?;
+        if token == "_" {
+            return Ok(SplitNode::Leaf(ids.next().ok_or("too few windows")?));
+        }
+        let dir = match token.chars().next() {
+            Some('h') => SplitDirection::Horizontal,
+            Some('v') => SplitDirection::Vertical,
+            _ => return Err("invalid split"),
+        };
+        let ratio = token[1..]
+            .parse::<f32>()
+            .ok()
+            .filter(|ratio| (0.0..=1.0).contains(ratio))
+            .ok_or("invalid split ratio")?;
+        Ok(SplitNode::Split {
+            dir,
+            ratio,
+            first: Box::new(SplitNode::parse(tokens, ids)?),
+            second: Box::new(SplitNode::parse(tokens, ids)?),
+        })
+    }
+
+    fn swap(&mut self, a: BufferId, b: BufferId) {
+        match self {
+            SplitNode::Leaf(id) if *id == a => *id = b,
+            SplitNode::Leaf(id) if *id == b => *id = a,
+            SplitNode::Leaf(_) => {}
+            SplitNode::Split { first, second, .. } => {
+                first.swap(a, b);
+                second.swap(a, b);
+            }
+        }
+    }
+}
+
+/// Tiling layout, that arranges its windows in a binary tree of horizontal and vertical splits,
+/// like the windows of vim
+#[derive(Debug)]
+pub struct SplitLayout {
+    root: Option<SplitNode>,
+    top_key: BufferId,
+    buffers: HashMap<BufferId, Buffer>,
+}
+
+impl SplitLayout {
+    pub fn new() -> Self {
+        SplitLayout {
+            root: None,
+            top_key: 0,
+            buffers: HashMap::new(),
+        }
+    }
+
+    fn get_id(&mut self) -> BufferId {
+        let ret = self.top_key;
+        self.top_key += 1;
+        ret
+    }
+
+    fn screen() -> Rect {
+        let (width, height) = size();
+        Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    fn rects(&self, screen: Rect) -> Vec<(BufferId, Rect)> {
+        let mut ret = Vec::with_capacity(self.buffers.len());
+        if let Some(root) = &self.root {
+            root.rects(screen, &mut ret);
+        }
+        ret
+    }
+
+    fn reorder(&mut self) {
+        for (id, rect) in self.rects(SplitLayout::screen()) {
+            let buf = self
+                .buffers
+                .get_mut(&id)
+                .expect("BUG: split tree and buffers out of sync");
+            (buf.offx, buf.offy) = (rect.x, rect.y);
+            (buf.width, buf.height) = (rect.width, rect.height);
+        }
+    }
+
+    fn insert(
+        &mut self,
+        target: Option<BufferId>,
+        dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<BufferId, &'static str> {
+        let id = self.get_id();
+        self.insert_as(id, target, dir, buf)?;
+        Ok(id)
+    }
+
+    fn insert_as(
+        &mut self,
+        id: BufferId,
+        target: Option<BufferId>,
+        dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<(), &'static str> {
+        if self.is_full() {
+            return Err("too many windows");
+        }
+        if self.buffers.contains_key(&id) {
+            return Err("duplicate");
+        }
+        match self.root.as_mut() {
+            None => self.root = Some(SplitNode::Leaf(id)),
+            Some(root) => {
+                let target = target.unwrap_or_else(|| root.last_leaf());
+                if !root.split_leaf(target, id, dir) {
+                    return Err("not found");
+                }
+            }
+        }
+        self.buffers.insert(id, buf);
+        self.reorder();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Layout for SplitLayout {
+    async fn render(&mut self, render_buf: &mut RenderBuffer) {
+        render_internal_faster(self.buffers.values(), render_buf).await;
+    }
+    async fn add_buf(&mut self, _name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
+        self.insert(None, SplitDirection::Horizontal, buf)
+    }
+    async fn add_buf_next_to(
+        &mut self,
+        target: BufferId,
+        dir: SplitDirection,
+        buf: Buffer,
+    ) -> Result<BufferId, &'static str> {
+        self.insert(Some(target), dir, buf)
+    }
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str> {
+        self.top_key = self.top_key.max(name + 1);
+        self.insert_as(name, None, SplitDirection::Horizontal, buf)
+    }
+    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
+        let buf = self.buffers.remove(&name).ok_or("not found")?;
+        match self.root.as_mut() {
+            Some(SplitNode::Leaf(_)) => self.root = None,
+            Some(root) => {
+                root.remove(name);
+            }
+            None => {}
+        }
+        self.reorder();
+        Ok(buf)
+    }
+    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
+        self.buffers.get(&name).ok_or("not found")
+    }
+    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
+        self.buffers.get_mut(&name).ok_or("not found")
+    }
+    fn buf_ids(&self) -> Vec<BufferId> {
+        self.rects(SplitLayout::screen())
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+    fn is_full(&self) -> bool {
+        self.buffers.len() >= MAX_WINDOWS
+    }
+    fn get_next_focused(&self) -> Option<BufferId> {
+        self.root.as_ref().map(|root| root.first_leaf())
+    }
+    async fn resize(
+        &mut self,
+        name: BufferId,
+        dir: SplitDirection,
+        delta: i16,
+    ) -> Result<(), &'static str> {
+        let root = self.root.as_mut().ok_or("not found")?;
+        if !root.resize(name, dir, delta, SplitLayout::screen()) {
+            return Err("no split to resize");
+        }
+        self.reorder();
+        Ok(())
+    }
+    async fn term_resized(&mut self) {
+        self.reorder();
+    }
+    async fn equalize(&mut self) {
+        if let Some(root) = self.root.as_mut() {
+            root.equalize();
+        }
+        self.reorder();
+    }
+    async fn swap(&mut self, a: BufferId, b: BufferId) -> Result<(), &'static str> {
+        if !self.buffers.contains_key(&a) || !self.buffers.contains_key(&b) {
+            return Err("not found");
+        }
+        if let Some(root) = self.root.as_mut() {
+            root.swap(a, b);
+        }
+        // the buffers keep their content, only the place in the tree changes
+        self.reorder();
+        Ok(())
+    }
+    fn arrangement(&self) -> String {
+        let mut tokens = Vec::new();
+        if let Some(root) = &self.root {
+            root.describe(&mut tokens);
+        }
+        tokens.join(" ")
+    }
+    fn arrange(&mut self, arrangement: &str, ids: &[BufferId]) -> Result<(), &'static str> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        if ids.len() != self.buffers.len() || ids.iter().any(|id| !self.buffers.contains_key(id)) {
+            return Err("windows don't match");
+        }
+        let mut tokens = arrangement.split_whitespace();
+        let mut leaves = ids.iter().copied();
+        let root = SplitNode::parse(&mut tokens, &mut leaves)?;
+        if tokens.next().is_some() || leaves.next().is_some() {
+            return Err("windows don't match");
+        }
+        self.root = Some(root);
+        self.reorder();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 24,
+    };
+
+    fn tree() -> SplitNode {
+        // 0 | 1
+        //   |---
+        //   | 2
+        let mut root = SplitNode::Leaf(0);
+        root.split_leaf(0, 1, SplitDirection::Vertical);
+        root.split_leaf(1, 2, SplitDirection::Horizontal);
+        root
+    }
+
+    fn rects(root: &SplitNode) -> Vec<(BufferId, Rect)> {
+        let mut ret = Vec::new();
+        root.rects(SCREEN, &mut ret);
+        ret
+    }
+
+    #[test]
+    fn split_geometry() {
+        assert_eq!(
+            rects(&tree()),
+            vec![
+                (
+                    0,
+                    Rect {
+                        x: 0,
+                        y: 0,
+                        width: 40,
+                        height: 24
+                    }
+                ),
+                (
+                    1,
+                    Rect {
+                        x: 40,
+                        y: 0,
+                        width: 40,
+                        height: 12
+                    }
+                ),
+                (
+                    2,
+                    Rect {
+                        x: 40,
+                        y: 12,
+                        width: 40,
+                        height: 12
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_gives_space_to_sibling() {
+        let mut root = tree();
+        assert!(root.remove(1));
+        let rects = rects(&root);
+        assert_eq!(rects.len(), 2);
+        assert_eq!(rects[1].0, 2);
+        assert_eq!(rects[1].1.height, 24);
+    }
+
+    #[test]
+    fn resize_uses_matching_split() {
+        let mut root = tree();
+        assert!(root.resize(2, SplitDirection::Vertical, 5, SCREEN));
+        assert!(root.resize(2, SplitDirection::Horizontal, 2, SCREEN));
+        let rects = rects(&root);
+        assert_eq!(rects[0].1.width, 35);
+        assert_eq!(rects[2].1.width, 45);
+        assert_eq!(rects[2].1.height, 14);
+    }
+
+    #[test]
+    fn equalize_counts_windows() {
+        // three windows side by side should each get a third
+        let mut root = SplitNode::Leaf(0);
+        root.split_leaf(0, 1, SplitDirection::Vertical);
+        root.split_leaf(1, 2, SplitDirection::Vertical);
+        root.equalize();
+        let widths: Vec<u16> = rects(&root).iter().map(|(_, r)| r.width).collect();
+        assert_eq!(widths, vec![27, 27, 26]);
+    }
+
+    #[test]
+    fn swap_windows() {
+        let mut root = tree();
+        root.swap(0, 2);
+        let ids: Vec<BufferId> = rects(&root).iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn describe_and_parse() {
+        let mut root = tree();
+        assert!(root.resize(0, SplitDirection::Vertical, 20, SCREEN));
+        let mut tokens = Vec::new();
+        root.describe(&mut tokens);
+        assert_eq!(tokens.join(" "), "v0.75 _ h0.5 _ _");
+        // the windows are numbered in the order they appear
+        let parsed = SplitNode::parse(
+            &mut tokens.iter().map(|t| t.as_str()),
+            &mut [7, 8, 9].into_iter(),
+        )
+        .unwrap();
+        let ids: Vec<BufferId> = rects(&parsed).iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![7, 8, 9]);
+        assert_eq!(rects(&parsed)[0].1.width, 60);
+        assert!(SplitNode::parse(&mut "v0.5 _".split(' '), &mut [0, 1].into_iter()).is_err());
+        assert!(SplitNode::parse(&mut "x0.5 _ _".split(' '), &mut [0, 1].into_iter()).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use super::*;
+use crate::core::render::theme;
+
+/// name of a tab in the tabline: the file name of its document
+fn tab_title(doc: &Document) -> String {
+    let name = match doc.path() {
+        Some(path) => std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string()),
+        None => "[No Name]".to_string(),
+    };
+    if doc.is_modified() {
+        format!("{name} +")
+    } else {
+        name
+    }
+}
+
+/// builds the tabline, padded to ``width``. Also returns the char range of the current tab, which
+/// gets highlighted
+fn tabline(titles: &[String], current: usize, width: usize) -> (String, Range<usize>) {
+    let mut line = String::new();
+    let mut highlight = 0..0;
+    for (i, title) in titles.iter().enumerate() {
+        if i > 0 {
+            line.push('|');
+        }
+        let start = line.chars().count();
+        line.push_str(format!(" {}: {title} ", i + 1).as_str());
+        if i == current {
+            highlight = start..line.chars().count();
+        }
+    }
+    let mut line: String = line.chars().take(width).collect();
+    let len = line.chars().count();
+    line.extend(std::iter::repeat_n(' ', width - len));
+    let highlight = highlight.start.min(width)..highlight.end.min(width);
+    (line, highlight)
+}
+
+/// Tab pages: every window gets the whole screen below the tabline, only the current one is shown
+#[derive(Debug)]
+pub struct TabLayout {
+    top_key: BufferId,
+    tabs: Vec<BufferId>,
+    current: usize,
+    buffers: HashMap<BufferId, Buffer>,
+}
+
+impl TabLayout {
+    pub fn new() -> Self {
+        TabLayout {
+            top_key: 0,
+            tabs: Vec::new(),
+            current: 0,
+            buffers: HashMap::new(),
+        }
+    }
+
+    fn get_id(&mut self) -> BufferId {
+        let ret = self.top_key;
+        self.top_key += 1;
+        ret
+    }
+
+    /// index of the tab in front
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    fn place(buf: &mut Buffer) {
+        let (width, height) = size();
+        // the first line belongs to the tabline
+        (buf.offx, buf.offy) = (0, 1);
+        (buf.width, buf.height) = (width, height.saturating_sub(1));
+    }
+
+    /// adds a new tab after the current one and brings it to the front
+    fn insert(&mut self, name: BufferId, mut buf: Buffer) -> Result<(), &'static str> {
+        if self.is_full() {
+            return Err("too many tabs");
+        }
+        if self.buffers.contains_key(&name) {
+            return Err("duplicate");
+        }
+        TabLayout::place(&mut buf);
+        self.buffers.insert(name, buf);
+        self.current = if self.tabs.is_empty() {
+            0
+        } else {
+            self.current + 1
+        };
+        self.tabs.insert(self.current, name);
+        Ok(())
+    }
+
+    async fn render_tabline(&self, render_buf: &mut RenderBuffer) {
+        let titles: Vec<String> = self
+            .tabs
+            .iter()
+            .map(|id| tab_title(&self.buffers[id].doc.read().unwrap()))
+            .collect();
+        let term_width = size().0;
+        let (line, highlight) = tabline(&titles, self.current, term_width as usize);
+        let (base, selected) = (theme::get("TabLine").await, theme::get("TabLineSel").await);
+        for (start, style) in [
+            (0, base),
+            (highlight.start, selected),
+            (highlight.end, base),
+        ] {
+            if start >= line.chars().count() {
+                continue;
+            }
+            render_buf
+                .add_ctrl_code(ANSICode::reset(), start, 0, term_width)
+                .await;
+            for code in style.codes() {
+                render_buf.add_ctrl_code(code, start, 0, term_width).await;
+            }
+        }
+        render_buf.write_str(0, 0, term_width, &line).await;
+    }
+}
+
+#[async_trait]
+impl Layout for TabLayout {
+    async fn render(&mut self, render_buf: &mut RenderBuffer) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        self.render_tabline(render_buf).await;
+        let current = &self.buffers[&self.tabs[self.current]];
+        render_internal_faster(std::iter::once(current), render_buf).await;
+    }
+    async fn add_buf(&mut self, _name: BufferId, buf: Buffer) -> Result<BufferId, &'static str> {
+        let id = self.get_id();
+        self.insert(id, buf)?;
+        Ok(id)
+    }
+    async fn adopt_buf(&mut self, name: BufferId, buf: Buffer) -> Result<(), &'static str> {
+        self.insert(name, buf)?;
+        self.top_key = self.top_key.max(name + 1);
+        Ok(())
+    }
+    async fn rem_buf(&mut self, name: BufferId) -> Result<Buffer, &'static str> {
+        let buf = self.buffers.remove(&name).ok_or("not found")?;
+        let idx = self
+            .tabs
+            .iter()
+            .position(|id| *id == name)
+            .expect("BUG: tabs and buffers out of sync");
+        self.tabs.remove(idx);
+        // like vim, closing the current tab goes to the one on the right, if there is one
+        if idx < self.current || self.current >= self.tabs.len() {
+            self.current = self.current.saturating_sub(1);
+        }
+        Ok(buf)
+    }
+    fn get_buf(&self, name: BufferId) -> Result<&Buffer, &'static str> {
+        self.buffers.get(&name).ok_or("not found")
+    }
+    fn get_buf_mut(&mut self, name: BufferId) -> Result<&mut Buffer, &str> {
+        self.buffers.get_mut(&name).ok_or("not found")
+    }
+    fn buf_ids(&self) -> Vec<BufferId> {
+        self.tabs.clone()
+    }
+    fn is_full(&self) -> bool {
+        self.buffers.len() >= split::MAX_WINDOWS
+    }
+    fn get_next_focused(&self) -> Option<BufferId> {
+        self.tabs.get(self.current).copied()
+    }
+    async fn swap(&mut self, a: BufferId, b: BufferId) -> Result<(), &'static str> {
+        let ia = self
+            .tabs
+            .iter()
+            .position(|id| *id == a)
+            .ok_or("not found")?;
+        let ib = self
+            .tabs
+            .iter()
+            .position(|id| *id == b)
+            .ok_or("not found")?;
+        self.tabs.swap(ia, ib);
+        Ok(())
+    }
+    async fn term_resized(&mut self) {
+        for buf in self.buffers.values_mut() {
+            TabLayout::place(buf);
+        }
+    }
+    fn focus_changed(&mut self, name: BufferId) {
+        if let Some(idx) = self.tabs.iter().position(|id| *id == name) {
+            self.current = idx;
+        }
+    }
+    /// the index of the tab in front
+    fn arrangement(&self) -> String {
+        self.current.to_string()
+    }
+    fn arrange(&mut self, arrangement: &str, ids: &[BufferId]) -> Result<(), &'static str> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        if ids.len() != self.tabs.len() || ids.iter().any(|id| !self.buffers.contains_key(id)) {
+            return Err("windows don't match");
+        }
+        self.current = arrangement
+            .trim()
+            .parse()
+            .ok()
+            .filter(|current| *current < ids.len())
+            .ok_or("invalid tab")?;
+        self.tabs = ids.to_vec();
+        Ok(())
+    }
+    fn visible_ids(&self) -> Vec<BufferId> {
+        self.get_next_focused().into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles() {
+        let mut doc = Document::new(vec![]);
+        doc.set_path(Some("src/main.rs".to_string()));
+        assert_eq!(tab_title(&doc), "main.rs");
+        doc.set_modified(true);
+        assert_eq!(tab_title(&doc), "main.rs +");
+        assert_eq!(tab_title(&Document::new(vec![])), "[No Name]");
+    }
+
+    #[test]
+    fn tabline_highlights_current() {
+        let titles = vec!["a".to_string(), "b".to_string()];
+        let (line, highlight) = tabline(&titles, 1, 16);
+        assert_eq!(line, " 1: a | 2: b    ");
+        assert_eq!(&line[highlight], " 2: b ");
+    }
+
+    #[test]
+    fn tabline_cut_at_width() {
+        let titles = vec!["a".to_string(), "b".to_string()];
+        let (line, highlight) = tabline(&titles, 1, 9);
+        assert_eq!(line, " 1: a | 2");
+        assert_eq!(highlight, 7..9);
+    }
+}
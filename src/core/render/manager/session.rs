@@ -0,0 +1,134 @@
+use super::*;
+
+/// a window as it is saved in a session
+#[derive(Debug)]
+pub struct WindowState {
+    pub doc: DocumentRef,
+    pub cursor: CursorPosition,
+    pub scroll: u32,
+}
+
+/// a layer as it is saved in a session. ``W`` describes a window, the session file refers to
+/// documents by their path instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerState<W> {
+    /// name of the layout, like ``:layout`` takes it
+    pub layout: String,
+    /// split ratios, master area, ... of the layout, see ``Layout::arrangement``
+    pub arrangement: String,
+    /// index of the window filling the screen, if the layer is zoomed
+    pub zoomed: Option<usize>,
+    /// in the order the layout shows them
+    pub windows: Vec<W>,
+}
+
+/// all layers with their windows, as well as the layer and index of the focused window
+pub async fn layer_states() -> (Vec<LayerState<WindowState>>, Option<(usize, usize)>) {
+    let handle = bufman_read().await;
+    let mut ret = Vec::with_capacity(handle.layers.len());
+    let mut focus = None;
+    for (idx, layer) in handle.layers.iter().enumerate() {
+        let layout = layer.lock().await;
+        let ids = layout.buf_ids();
+        let position = |id: BufferId| ids.iter().position(|other| *other == id);
+        if let Some(BufferRef { layer, id }) = handle.focused {
+            if layer as usize == idx {
+                focus = position(id).map(|pos| (idx, pos));
+            }
+        }
+        let zoomed = layout
+            .downcast_ref::<MonocleLayout>()
+            .and_then(|monocle| monocle.shown())
+            .and_then(position);
+        let windows = ids
+            .iter()
+            .filter_map(|id| layout.get_buf(*id).ok())
+            .map(|buf| WindowState {
+                doc: Arc::clone(&buf.doc),
+                cursor: buf.cursor_pos,
+                scroll: buf.scroll,
+            })
+            .collect();
+        ret.push(LayerState {
+            layout: builtin_layouts::layout_name(&**layout)
+                .unwrap_or("split")
+                .to_string(),
+            arrangement: layout.arrangement(),
+            zoomed,
+            windows,
+        });
+    }
+    (ret, focus)
+}
+
+/// builds the layout of a saved layer, returns it with the ids of its windows
+async fn build_layer(
+    state: LayerState<WindowState>,
+) -> Result<(Box<dyn Layout>, Vec<BufferId>), String> {
+    let mut layout = builtin_layouts::new_layout(&state.layout)
+        .ok_or(format!("unknown layout: {}", state.layout))?;
+    let mut ids = Vec::with_capacity(state.windows.len());
+    let mut cursors = Vec::with_capacity(state.windows.len());
+    for window in state.windows {
+        let mut buf = Buffer::default();
+        buf.doc = window.doc;
+        buf.scroll = window.scroll;
+        ids.push(layout.add_buf(0, buf).await?);
+        cursors.push(window.cursor);
+    }
+    layout.arrange(&state.arrangement, &ids)?;
+    // only now the windows have their final size, which decides whether the cursor is visible
+    for (id, cursor) in ids.iter().zip(cursors) {
+        layout.get_buf_mut(*id)?.set_cursor_pos(cursor);
+    }
+    if let Some(shown) = state.zoomed.and_then(|idx| ids.get(idx)) {
+        layout = Box::new(MonocleLayout::new(layout, *shown));
+    }
+    Ok((layout, ids))
+}
+
+/// replaces the layers with the saved ones and focuses the window ``focus`` (layer and index).
+/// Only works while no window is open, since their ClientBuffers would point nowhere afterwards
+pub async fn restore_layers(
+    layers: Vec<LayerState<WindowState>>,
+    focus: Option<(usize, usize)>,
+) -> Result<(), String> {
+    let mut refs: Vec<Vec<BufferRef>> = Vec::with_capacity(layers.len());
+    {
+        let handle = bufman_read().await;
+        if layers.len() > handle.layers.len() {
+            return Err("too many layers".to_string());
+        }
+        for layer in handle.layers.iter() {
+            if !layer.lock().await.buf_ids().is_empty() {
+                return Err("windows are already open".to_string());
+            }
+        }
+        // everything is built first, so a broken layer doesn't leave the others half restored
+        let mut built = Vec::with_capacity(layers.len());
+        for state in layers {
+            built.push(build_layer(state).await?);
+        }
+        for (idx, (layout, ids)) in built.into_iter().enumerate() {
+            *handle.layers[idx].lock().await = layout;
+            let layer = idx as u8;
+            refs.push(ids.into_iter().map(|id| BufferRef { layer, id }).collect());
+        }
+    }
+    WINDOWS
+        .lock()
+        .await
+        .extend(refs.iter().flatten().map(|bufman_ref| ClientBuffer {
+            bufman_ref: bufman_ref.clone(),
+        }));
+    let focus = focus
+        .and_then(|(layer, idx)| refs.get(layer)?.get(idx))
+        .or_else(|| refs.iter().flatten().next());
+    match focus {
+        Some(buf_ref) => {
+            focus_ref(buf_ref.clone()).await?;
+            rerender_and_refocus().await
+        }
+        None => rerender().await,
+    }
+}
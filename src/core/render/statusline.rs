@@ -0,0 +1,371 @@
+//! # Status line
+//! The last line of the screen, showing information about the focused window. It is made of
+//! segments, every one of them renders a piece of text (or nothing) from a ``StatusContext``.
+//! Plugins can add their own segments with ``add``, the colors come from the theme.
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use tokio::sync::RwLock;
+
+use super::manager;
+use super::theme::{self, Style};
+use crate::core::editor::fileformat::FileFormat;
+use crate::core::editor::mode::Mode;
+use crate::core::editor::CursorPosition;
+
+/// everything the segments get to know about the editor
+#[derive(Debug, Clone)]
+pub struct StatusContext {
+    pub mode: Mode,
+    pub path: Option<String>,
+    /// the branch of the repository the document is in
+    pub git_branch: Option<String>,
+    pub modified: bool,
+    pub readonly: bool,
+    /// how the document is stored on disk
+    pub format: FileFormat,
+    pub cursor: CursorPosition,
+    pub line_count: usize,
+    /// what's typed on the command line, if in command line mode
+    pub cmdline: Option<String>,
+    /// a question, that waits for an answer. It replaces everything else
+    pub message: Option<String>,
+    /// the register a macro is recorded into
+    pub recording: Option<char>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+pub type SegmentFunctionType = Arc<Box<dyn Fn(&StatusContext) -> Option<String> + Send + Sync>>;
+
+#[derive(Clone)]
+pub struct Segment {
+    name: String,
+    align: Align,
+    /// highlight group of the theme
+    group: String,
+    render: SegmentFunctionType,
+}
+
+impl Segment {
+    pub fn new<F>(name: &str, align: Align, group: &str, render: F) -> Self
+    where
+        F: Fn(&StatusContext) -> Option<String> + Send + Sync + 'static,
+    {
+        Segment {
+            name: name.to_string(),
+            align,
+            group: group.to_string(),
+            render: Arc::new(Box::new(render)),
+        }
+    }
+}
+
+/// text of the status line and the styles of its parts, as char ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusLine {
+    pub text: String,
+    pub styles: Vec<(Range<usize>, Style)>,
+}
+
+static SEGMENTS: Lazy<RwLock<Vec<Segment>>> = Lazy::new(|| RwLock::new(builtin_segments()));
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// number of lines taken from the layouts
+pub fn height() -> u16 {
+    if enabled() {
+        1
+    } else {
+        0
+    }
+}
+
+/// shows or hides the status line, the layouts get rearranged to use the freed up space
+pub async fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    manager::dispatch_resize().await;
+}
+
+/// adds a segment after the ones with the same alignment. A segment with the same name gets
+/// replaced in place
+pub async fn add(segment: Segment) {
+    let mut segments = SEGMENTS.write().await;
+    match segments.iter_mut().find(|seg| seg.name == segment.name) {
+        Some(seg) => *seg = segment,
+        None => segments.push(segment),
+    }
+}
+
+pub async fn remove(name: &str) {
+    SEGMENTS.write().await.retain(|seg| seg.name != name);
+}
+
+/// renders all segments for ``ctx``
+pub async fn build(ctx: &StatusContext, width: usize) -> StatusLine {
+    let base = theme::get("StatusLine").await;
+    if let Some(message) = &ctx.message {
+        return compose(vec![(message.clone(), Align::Left, base)], base, width);
+    }
+    if let Some(cmdline) = &ctx.cmdline {
+        return compose(
+            vec![(format!(":{cmdline}"), Align::Left, base)],
+            base,
+            width,
+        );
+    }
+    let segments = SEGMENTS.read().await.clone();
+    let mut parts = Vec::with_capacity(segments.len());
+    for seg in segments.iter() {
+        if let Some(text) = (seg.render)(ctx) {
+            parts.push((text, seg.align, theme::get(&seg.group).await));
+        }
+    }
+    compose(parts, base, width)
+}
+
+/// puts the segments together, the left aligned ones at the start, the right aligned ones at the
+/// end and the gap filled with ``base``. Every segment gets a space on both sides
+fn compose(parts: Vec<(String, Align, Style)>, base: Style, width: usize) -> StatusLine {
+    let (left, right): (Vec<_>, Vec<_>) = parts
+        .into_iter()
+        .map(|(part, align, style)| (format!(" {part} "), align, style))
+        .partition(|(_, align, _)| *align == Align::Left);
+    let parts_len = |parts: &[(String, Align, Style)]| -> usize {
+        parts.iter().map(|(part, _, _)| part.chars().count()).sum()
+    };
+    let gap = width.saturating_sub(parts_len(&left) + parts_len(&right));
+    let gap = (" ".repeat(gap), Align::Left, base);
+    let mut text = String::with_capacity(width);
+    let mut styles = Vec::with_capacity(left.len() + right.len() + 1);
+    let mut len = 0;
+    for (part, _, style) in left.iter().chain(std::iter::once(&gap)).chain(right.iter()) {
+        let part_len = part.chars().count();
+        text.push_str(part);
+        styles.push((len..len + part_len, *style));
+        len += part_len;
+    }
+    // too long, cut off at the end
+    let text: String = text.chars().take(width).collect();
+    let styles = styles
+        .into_iter()
+        .filter(|(range, _)| range.start < width && !range.is_empty())
+        .map(|(range, style)| (range.start..range.end.min(width), style))
+        .collect();
+    StatusLine { text, styles }
+}
+
+/// the file type, judging from the extension
+pub fn filetype(path: &str) -> Option<String> {
+    let ext = Path::new(path).extension()?.to_str()?;
+    let name = match ext {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "md" => "markdown",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "sh" | "bash" => "sh",
+        "txt" => "text",
+        "yml" => "yaml",
+        other => other,
+    };
+    Some(name.to_string())
+}
+
+/// e.g. ``latin1[dos][noeol]``, like the file info of vim
+fn fileformat(format: &FileFormat) -> String {
+    let mut ret = format!("{}[{}]", format.encoding.name(), format.line_ending.name());
+    if !format.final_newline {
+        ret.push_str("[noeol]");
+    }
+    ret
+}
+
+/// like vim's ruler: ``Top``, ``Bot``, ``All`` or how far the cursor is into the file
+fn percentage(line: u32, line_count: usize) -> String {
+    let last = line_count.saturating_sub(1) as u32;
+    if last == 0 {
+        "All".to_string()
+    } else if line == 0 {
+        "Top".to_string()
+    } else if line >= last {
+        "Bot".to_string()
+    } else {
+        format!("{}%", line * 100 / last)
+    }
+}
+
+/// the branch name from the content of ``.git/HEAD``, or the short commit hash if detached
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None if head.len() >= 7 => Some(head[..7].to_string()),
+        None => None,
+    }
+}
+
+/// the content of the ``HEAD`` file of the repository ``dir`` is in. The closest ``.git`` counts,
+/// in worktrees and submodules it is a file pointing to the actual git directory
+fn read_head(dir: &Path) -> Option<String> {
+    for dir in dir.ancestors() {
+        let git = dir.join(".git");
+        let Ok(metadata) = std::fs::metadata(&git) else {
+            continue;
+        };
+        let git_dir = if metadata.is_file() {
+            let link = std::fs::read_to_string(&git).ok()?;
+            dir.join(link.trim().strip_prefix("gitdir:")?.trim())
+        } else {
+            git
+        };
+        return std::fs::read_to_string(git_dir.join("HEAD")).ok();
+    }
+    None
+}
+
+/// the git branch of the repository ``path`` is in (the working directory, if there is no path).
+/// This reads from disk, so it shouldn't be called for every redraw
+pub fn git_branch(path: Option<&str>) -> Option<String> {
+    let cwd = std::env::current_dir().ok()?;
+    let start = match path {
+        Some(path) => cwd.join(path).parent()?.to_path_buf(),
+        None => cwd,
+    };
+    read_head(&start).and_then(|head| parse_head(&head))
+}
+
+fn builtin_segments() -> Vec<Segment> {
+    vec![
+        Segment::new("mode", Align::Left, "StatusLineMode", |ctx| {
+            Some(ctx.mode.name().to_string())
+        }),
+        Segment::new("recording", Align::Left, "StatusLineInfo", |ctx| {
+            ctx.recording.map(|reg| format!("recording @{reg}"))
+        }),
+        Segment::new("git_branch", Align::Left, "StatusLineInfo", |ctx| {
+            ctx.git_branch.clone()
+        }),
+        Segment::new("path", Align::Left, "StatusLine", |ctx| {
+            Some(ctx.path.clone().unwrap_or_else(|| "[No Name]".to_string()))
+        }),
+        Segment::new("modified", Align::Left, "StatusLine", |ctx| {
+            ctx.modified.then(|| "[+]".to_string())
+        }),
+        Segment::new("readonly", Align::Left, "StatusLine", |ctx| {
+            ctx.readonly.then(|| "[RO]".to_string())
+        }),
+        Segment::new("fileformat", Align::Right, "StatusLine", |ctx| {
+            (!ctx.format.is_default()).then(|| fileformat(&ctx.format))
+        }),
+        Segment::new("filetype", Align::Right, "StatusLine", |ctx| {
+            ctx.path.as_deref().and_then(filetype)
+        }),
+        Segment::new("percentage", Align::Right, "StatusLineInfo", |ctx| {
+            Some(percentage(ctx.cursor.y, ctx.line_count))
+        }),
+        Segment::new("position", Align::Right, "StatusLineMode", |ctx| {
+            Some(format!("{}:{}", ctx.cursor.y + 1, ctx.cursor.x + 1))
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: Style = Style::new(None, None);
+    const RED: Style = Style::new(Some((255, 0, 0)), None);
+
+    #[test]
+    fn left_and_right() {
+        let line = compose(
+            vec![
+                ("NORMAL".to_string(), Align::Left, RED),
+                ("1:1".to_string(), Align::Right, RED),
+            ],
+            BASE,
+            20,
+        );
+        assert_eq!(line.text, " NORMAL         1:1 ");
+        assert_eq!(line.styles, vec![(0..8, RED), (8..15, BASE), (15..20, RED)]);
+    }
+
+    #[test]
+    fn cut_when_too_long() {
+        let line = compose(
+            vec![
+                ("NORMAL".to_string(), Align::Left, RED),
+                ("1:1".to_string(), Align::Right, RED),
+            ],
+            BASE,
+            10,
+        );
+        assert_eq!(line.text, " NORMAL  1");
+        assert_eq!(line.styles, vec![(0..8, RED), (8..10, RED)]);
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(percentage(0, 1), "All");
+        assert_eq!(percentage(0, 10), "Top");
+        assert_eq!(percentage(9, 10), "Bot");
+        assert_eq!(percentage(3, 10), "33%");
+    }
+
+    #[test]
+    fn filetypes() {
+        assert_eq!(filetype("src/main.rs").as_deref(), Some("rust"));
+        assert_eq!(filetype("Cargo.toml").as_deref(), Some("toml"));
+        assert_eq!(filetype("Makefile"), None);
+    }
+
+    #[test]
+    fn worktree_head() {
+        let root = std::env::temp_dir().join(format!("neoxide-git-{}", std::process::id()));
+        let worktree = root.join("worktree");
+        let git_dir = root.join(".git").join("worktrees").join("wt");
+        std::fs::create_dir_all(&git_dir).unwrap();
+        std::fs::create_dir_all(worktree.join("src")).unwrap();
+        std::fs::write(root.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature\n").unwrap();
+        std::fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", git_dir.display()),
+        )
+        .unwrap();
+        let head = |dir: &Path| read_head(dir).and_then(|head| parse_head(&head));
+        assert_eq!(head(&worktree.join("src")).as_deref(), Some("feature"));
+        assert_eq!(head(&root).as_deref(), Some("main"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn git_head() {
+        assert_eq!(
+            parse_head("ref: refs/heads/main\n").as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            parse_head("edaacc5f00d1e2c3b4a5968778695a4b3c2d1e0f").as_deref(),
+            Some("edaacc5")
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! # Theme
+//! Colors of the editor UI, looked up by the name of a highlight group (like vim's ``:highlight``).
+//! Plugins can change existing groups or add their own with ``set``.
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use tokio::sync::RwLock;
+
+use super::manager::ANSICode;
+
+pub type Rgb = (u8, u8, u8);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Rgb>,
+    pub bg: Option<Rgb>,
+}
+
+impl Style {
+    pub const fn new(fg: Option<Rgb>, bg: Option<Rgb>) -> Self {
+        Style { fg, bg }
+    }
+
+    /// the control codes, that switch the terminal to this style
+    pub fn codes(&self) -> Vec<ANSICode> {
+        let mut ret = Vec::with_capacity(2);
+        if let Some(fg) = self.fg {
+            ret.push(ANSICode::color(true, fg));
+        }
+        if let Some(bg) = self.bg {
+            ret.push(ANSICode::color(false, bg));
+        }
+        ret
+    }
+}
+
+static THEME: Lazy<RwLock<HashMap<String, Style>>> = Lazy::new(|| RwLock::new(default_theme()));
+
+fn default_theme() -> HashMap<String, Style> {
+    [
+        (
+            "StatusLine",
+            Style::new(Some((220, 220, 220)), Some((50, 50, 60))),
+        ),
+        (
+            "StatusLineMode",
+            Style::new(Some((30, 30, 30)), Some((130, 170, 230))),
+        ),
+        (
+            "StatusLineInfo",
+            Style::new(Some((220, 220, 220)), Some((80, 80, 100))),
+        ),
+        ("TabLine", Style::default()),
+        ("LineNr", Style::new(Some((110, 110, 130)), None)),
+        ("CursorLineNr", Style::new(Some((230, 200, 90)), None)),
+        ("SignColumn", Style::default()),
+        ("FoldColumn", Style::new(Some((110, 110, 130)), None)),
+        ("TabLineSel", Style::new(None, Some((80, 80, 120)))),
+        ("Visual", Style::new(None, Some((70, 75, 110)))),
+    ]
+    .into_iter()
+    .map(|(group, style)| (group.to_string(), style))
+    .collect()
+}
+
+/// the style of a highlight group, unknown groups don't change the colors at all
+pub async fn get(group: &str) -> Style {
+    THEME.read().await.get(group).copied().unwrap_or_default()
+}
+
+pub async fn set(group: &str, style: Style) {
+    THEME.write().await.insert(group.to_string(), style);
+}
@@ -0,0 +1,135 @@
+//! # Terminal
+//! Puts the terminal into the state the editor needs (alternate screen, raw mode and the input
+//! options of [`InputConfig`]) and gives the user their old terminal back, no matter if the editor
+//! exits normally, panics or gets killed by SIGTERM/SIGHUP.
+use std::io::{stdout, Result as IoResult, Write};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture},
+    queue,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use super::input::{self, InputConfig};
+use super::logger::{self, LogLevel};
+use super::render::cursor;
+
+/// whether the terminal is currently set up for the editor and has to be restored
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+/// set for ``--headless``: there is no terminal, so nothing gets drawn and the screen has a
+/// fixed size
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
+pub fn set_headless(headless: bool) {
+    HEADLESS.store(headless, Ordering::Relaxed);
+}
+
+pub fn headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+/// the size of the terminal, or the one of the screen without a terminal
+pub fn screen_size() -> IoResult<(u16, u16)> {
+    if headless() {
+        return Ok(HEADLESS_SIZE);
+    }
+    terminal::size()
+}
+
+/// Guard for the terminal state. The terminal is restored, when it gets dropped.
+/// Only one session should exist at a time
+#[derive(Debug)]
+pub struct TerminalSession {
+    config: InputConfig,
+}
+
+impl TerminalSession {
+    /// Sets the terminal up for the editor. This has to be called from within the tokio runtime,
+    /// because the signal handlers live in their own task
+    pub fn enter(config: InputConfig) -> IoResult<Self> {
+        install_panic_hook();
+        terminal::enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        let session = TerminalSession { config };
+        queue!(stdout(), EnterAlternateScreen)?;
+        input::apply_config(&session.config)?;
+        #[cfg(unix)]
+        handle_signals()?;
+        Ok(session)
+    }
+
+    pub fn config(&self) -> &InputConfig {
+        &self.config
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        let _ = restore();
+    }
+}
+
+/// Undoes everything [`TerminalSession::enter`] did. Calling it more than once (or without a
+/// session) does nothing
+pub fn restore() -> IoResult<()> {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let mut out = stdout();
+    // disabling options, that were never enabled, is harmless
+    queue!(
+        out,
+        DisableBracketedPaste,
+        DisableFocusChange,
+        DisableMouseCapture,
+        Show,
+        LeaveAlternateScreen
+    )?;
+    out.flush()?;
+    cursor::restore()?;
+    terminal::disable_raw_mode()
+}
+
+/// restores the terminal before the panic message is printed, otherwise it would end up on the
+/// alternate screen (and in raw mode). The process exits afterwards: a panic in a spawned task
+/// only ends that task, which would leave the editor running on a restored terminal
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let _ = restore();
+            prev(info);
+            // the exit code of a panicking main thread
+            std::process::exit(101);
+        }));
+    });
+}
+
+#[cfg(unix)]
+fn handle_signals() -> IoResult<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut term = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        // exit codes like a shell would report them (128 + signal number)
+        let (name, code) = tokio::select! {
+            _ = term.recv() => ("SIGTERM", 143),
+            _ = hangup.recv() => ("SIGHUP", 129),
+        };
+        let _ = restore();
+        logger::log(
+            LogLevel::Normal,
+            format!("Received {name}, exiting").as_str(),
+        )
+        .await;
+        std::process::exit(code);
+    });
+    Ok(())
+}
@@ -1,8 +1,7 @@
-use neoxide::core::editor::motions::{LeftRightMotion, Motion, MotionDirection, UpDownMotion};
-use neoxide::core::event_handling::EventCallback;
-use neoxide::core::{io, logger, render};
+use neoxide::core::editor::{autoread, keymap, mode, mouse, paste, session, shada};
+use neoxide::core::{io, render};
 use std::io::{prelude::*, stdin};
-use std::ops::{AddAssign, Deref};
+use std::ops::AddAssign;
 use std::time::Duration;
 use std::{io::stdout, process::Command};
 use tokio::task::JoinHandle;
@@ -71,91 +70,171 @@ async fn benchmark(rounds: u32) {
     println!("Avg time per round: {:.3?}", sum.div_f64(rounds.into()));
 }
 
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
-use neoxide::core::input::{self, EvtData, InputConfig, InputEvent};
-use std::sync::Arc;
-use tokio::sync::Mutex;
-async fn editor_demo() {
-    let handle = tokio::spawn(input::input_loop(InputConfig {
-        bracketed_paste: false,
-        focus_change: false,
-        mouse_capture: false,
-    }));
-    let buf = io::open_file("log.neo2").await.unwrap();
-    let _ = buf.focus().await;
-    input::subscribe(EventCallback::new(
-        Arc::new(Box::new(move |evt: Arc<Mutex<_>>| {
-            let evt = evt.clone();
-            let fut = async move {
-                let buf = render::manager::focused().await.unwrap();
-                let evt = evt.lock().await;
-                if let Event::Key(KeyEvent {
-                    code,
-                    modifiers: _,
-                    kind: KeyEventKind::Press,
-                    state: _,
-                }) = evt.0
-                {
-                    let dbr = buf.deref().await;
-                    let cursor_position = &dbr.cursor_position();
-                    logger::log(LogLevel::Debug, "Fetched buffer content...").await;
-                    if let KeyCode::Char(c) = code {
-                        let pos = match c {
-                            'j' => UpDownMotion.get_new_cursor_position(
-                                dbr,
-                                cursor_position,
-                                MotionDirection::Foward,
-                            ),
-                            'k' => UpDownMotion.get_new_cursor_position(
-                                dbr,
-                                cursor_position,
-                                MotionDirection::Backward,
-                            ),
-                            'h' => LeftRightMotion.get_new_cursor_position(
-                                dbr,
-                                cursor_position,
-                                MotionDirection::Backward,
-                            ),
-                            'l' => LeftRightMotion.get_new_cursor_position(
-                                dbr,
-                                cursor_position,
-                                MotionDirection::Foward,
-                            ),
-                            _ => todo!(),
-                        };
-                        logger::log(
-                            LogLevel::Normal,
-                            format!("New cursor position is {:?}", pos).as_str(),
-                        )
-                        .await;
-                        let mut dbr = buf.deref().await;
-                        dbr.set_cursor_pos(pos);
-                    }
-                    logger::log(LogLevel::Debug, format!("Got keycode: {code}").as_str()).await;
-                }
+use neoxide::core::cli::{self, Args};
+use neoxide::core::editor::document::{Document, DocumentRef};
+use neoxide::core::editor::fileformat::{self, FileFormat};
+use neoxide::core::editor::{commands, CursorPosition};
+
+/// the documents the editor starts with: the text from stdin and the files of the command line,
+/// or an empty one if there are none. Some of them come with a cursor position from the state file
+async fn startup_documents(
+    args: &Args,
+    stdin: Option<Vec<u8>>,
+) -> Vec<(DocumentRef, Option<CursorPosition>)> {
+    let mut ret = Vec::with_capacity(args.files.len() + 1);
+    if let Some(bytes) = stdin {
+        let doc = Document::new_ref(Vec::new(), None);
+        {
+            let mut doc = doc.write().unwrap();
+            // read like a file, so it's written back in the same format
+            let (text, encoding) = fileformat::decode(&bytes);
+            doc.set_content(&text);
+            let format = FileFormat {
+                encoding,
+                ..doc.format()
             };
-            Box::pin(fut)
-        })),
-        true,
-        InputEvent(Event::Key(KeyEvent::new(
-            event::KeyCode::Char(' '), // doesn't matter which char goes here
-            KeyModifiers::empty(),
-        ))),
-    ))
-    .await;
+            doc.set_format(format);
+            // like in vim, the text exists nowhere else
+            doc.set_modified(true);
+        }
+        ret.push((doc, None));
+    }
+    for file in args.files.iter() {
+        match io::open_or_create(file).await {
+            Ok(doc) => ret.push(doc),
+            Err(err) => log(LogLevel::Error, &format!("can't open {file}: {err}")).await,
+        }
+    }
+    if ret.is_empty() {
+        ret.push((Document::new_ref(Vec::new(), None), None));
+    }
+    ret
+}
+
+/// opens a window for every startup document and focuses the first one
+async fn open_windows(args: &Args, stdin: Option<Vec<u8>>) {
+    for (idx, (doc, cursor)) in startup_documents(args, stdin).await.into_iter().enumerate() {
+        if args.readonly {
+            doc.write().unwrap().set_readonly(true);
+        }
+        let cursor = match args.line {
+            Some(line) if idx == 0 => Some(doc.read().unwrap().clamp(CursorPosition {
+                x: 0,
+                y: line.saturating_sub(1),
+            })),
+            _ => cursor,
+        };
+        let buf = match ClientBuffer::build_with_document(0, true, doc).await {
+            Ok(buf) => buf,
+            Err(msg) => {
+                log(LogLevel::Error, &msg).await;
+                break;
+            }
+        };
+        if let Some(pos) = cursor {
+            buf.set_cursor_position(pos).await;
+        }
+        if idx == 0 {
+            let _ = buf.focus().await;
+        }
+        render::manager::adopt(buf).await;
+    }
+    if let Err(msg) = render::manager::rerender().await {
+        log(LogLevel::Error, &msg).await;
+    }
+}
+
+use neoxide::core::headless;
+use neoxide::core::input::{self, InputConfig};
+use neoxide::core::terminal::TerminalSession;
+async fn editor(args: Args, stdin: Option<Vec<u8>>) {
+    let handle = tokio::spawn(input::input_loop());
+    if args.clean {
+        shada::set_path(None).await;
+    }
+    // before opening files, so their marks and cursor positions are restored
+    if let Err(msg) = shada::load().await {
+        log(LogLevel::Error, &msg).await;
+    }
+    let restored = match &args.session {
+        Some(path) => match session::restore(path).await {
+            Ok(()) => true,
+            Err(msg) => {
+                log(LogLevel::Error, &msg).await;
+                false
+            }
+        },
+        None => false,
+    };
+    if !restored {
+        open_windows(&args, stdin).await;
+    }
+    keymap::init().await;
+    mouse::init().await;
+    paste::init().await;
+    autoread::init().await;
+    autoread::watch();
+    for command in args.commands.iter() {
+        if let Err(msg) = commands::execute(command).await {
+            log(LogLevel::Error, &format!("{command}: {msg}")).await;
+        }
+    }
     handle.await.unwrap().unwrap();
-    drop(buf);
+    if let Err(msg) = shada::save().await {
+        log(LogLevel::Error, &msg).await;
+    }
 }
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("neoxide: {msg}");
+            eprint!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if args.version {
+        println!("neoxide {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    // read before the terminal is taken over, the keys come from the terminal itself then
+    let stdin = if args.stdin {
+        let mut bytes = Vec::new();
+        stdin().read_to_end(&mut bytes)?;
+        Some(bytes)
+    } else {
+        None
+    };
     let _ = Command::new("rm").arg(LOGFILE_PATH).output();
-    terminal::enable_raw_mode()?;
-    editor_demo().await;
-    terminal::disable_raw_mode()?;
-    // let test = editor_demo().await.await??;
-    // let mut stdin = stdin();
-    // let _ = stdin.read(&mut [0u8]).unwrap();
-    // let buf = io::open_file("log.neo2").await.unwrap();
+    if args.headless {
+        headless::enter();
+        match &args.session {
+            Some(path) => {
+                if let Err(msg) = session::restore(path).await {
+                    eprintln!("neoxide: {msg}");
+                    std::process::exit(1);
+                }
+            }
+            None => open_windows(&args, stdin).await,
+        }
+        if let Err(msg) = headless::run(&args.commands).await {
+            eprintln!("neoxide: {msg}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let _session = TerminalSession::enter(InputConfig {
+        bracketed_paste: true,
+        focus_change: true,
+        mouse_capture: true,
+    })?;
+    render::cursor::apply(mode::current().await).await?;
+    editor(args, stdin).await;
     Ok(())
 }